@@ -0,0 +1,542 @@
+// Hand cam paths, keyframed in the headset and played back along a spline
+
+use std::ops::{Add, Mul, Sub};
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::hand_cam_lens::HandCamLensSettings;
+use crate::hand_cam_recording::{HandCamRecordToggle, HandCamRecorder};
+use crate::main_menu::HandCamBox;
+use crate::scene_file::{splats_file_path, RonFile, SceneTransform};
+use crate::ui_window::WindowContexts;
+
+
+/// Seconds between a new keyframe and the one before it
+pub const KEYFRAME_SPACING: f32 = 2.0;
+/// Segments drawn per second of path
+const GIZMO_SAMPLES_PER_SECOND: f32 = 20.0;
+const TIMELINE_HEIGHT: f32 = 24.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the path
+    pub time: f32,
+    /// Global transform of the hand cam box
+    pub transform: SceneTransform,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+    #[serde(default)]
+    pub looped: bool,
+}
+
+impl RonFile for CameraPath {}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Adds a keyframe [`KEYFRAME_SPACING`] after the last one
+    pub fn push(&mut self, transform: Transform, fov: f32) {
+        let time = self
+            .keyframes
+            .last()
+            .map_or(0.0, |k| k.time + KEYFRAME_SPACING);
+        self.keyframes.push(CameraKeyframe {
+            time,
+            transform: transform.into(),
+            fov,
+        });
+    }
+
+    pub fn sort(&mut self) {
+        self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    /// Wraps or clamps `time` onto the path
+    pub fn path_time(&self, time: f32) -> f32 {
+        let duration = self.duration();
+        if self.looped && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time.clamp(0.0, duration)
+        }
+    }
+
+    /// Transform and FOV at `time`, a cubic Hermite spline through the keyframes with tangents
+    /// from the neighbouring keyframes, so uneven spacing doesn't overshoot
+    pub fn sample(&self, time: f32) -> Option<(Transform, f32)> {
+        let keys = &self.keyframes;
+        let first = keys.first()?;
+        if keys.len() == 1 {
+            return Some((first.transform.into(), first.fov));
+        }
+
+        let time = self.path_time(time);
+        let i = keys
+            .windows(2)
+            .position(|w| time <= w[1].time)
+            .unwrap_or(keys.len() - 2);
+        let span = keys[i + 1].time - keys[i].time;
+        if span <= 0.0 {
+            return Some((keys[i + 1].transform.into(), keys[i + 1].fov));
+        }
+        let s = ((time - keys[i].time) / span).clamp(0.0, 1.0);
+
+        let times: Vec<f32> = keys.iter().map(|k| k.time).collect();
+        let translations: Vec<Vec3> = keys
+            .iter()
+            .map(|k| Vec3::from_array(k.transform.translation))
+            .collect();
+        let fovs: Vec<f32> = keys.iter().map(|k| k.fov).collect();
+        // q and -q are the same rotation, keep neighbours on the same side so they blend the short way
+        let mut rotations: Vec<Vec4> = Vec::with_capacity(keys.len());
+        for key in keys.iter() {
+            let mut q = Vec4::from_array(key.transform.rotation);
+            if rotations.last().is_some_and(|prev| prev.dot(q) < 0.0) {
+                q = -q;
+            }
+            rotations.push(q);
+        }
+
+        let translation = hermite_segment(&times, &translations, i, s);
+        let rotation = Quat::from_vec4(hermite_segment(&times, &rotations, i, s)).normalize();
+        let scale = Vec3::from_array(keys[i].transform.scale)
+            .lerp(Vec3::from_array(keys[i + 1].transform.scale), s);
+        let fov = hermite_segment(&times, &fovs, i, s);
+
+        Some((
+            Transform {
+                translation,
+                rotation,
+                scale,
+            },
+            fov,
+        ))
+    }
+}
+
+/// Slope at key `i` from its neighbours, one sided at the ends
+fn tangent<T>(times: &[f32], values: &[T], i: usize) -> T
+where
+    T: Copy + Default + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let prev = i.saturating_sub(1);
+    let next = (i + 1).min(values.len() - 1);
+    let dt = times[next] - times[prev];
+    if dt <= 0.0 {
+        return T::default();
+    }
+    (values[next] - values[prev]) * (1.0 / dt)
+}
+
+fn hermite_segment<T>(times: &[f32], values: &[T], i: usize, s: f32) -> T
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let span = times[i + 1] - times[i];
+    let m0 = tangent(times, values, i) * span;
+    let m1 = tangent(times, values, i + 1) * span;
+    let s2 = s * s;
+    let s3 = s2 * s;
+    values[i] * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + m0 * (s3 - 2.0 * s2 + s)
+        + values[i + 1] * (-2.0 * s3 + 3.0 * s2)
+        + m1 * (s3 - s2)
+}
+
+
+pub fn default_camera_path_file() -> PathBuf {
+    splats_file_path("camera_path.ron")
+}
+
+/// Adds the hand cam's current pose to the path
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct AddCameraKeyframe;
+
+#[derive(Resource, Default)]
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32,
+    pub playing: bool,
+    /// Records the hand cam for as long as the path plays
+    pub record: bool,
+    started_recording: bool,
+    /// Moves the hand cam to `time` next frame even when paused
+    needs_apply: bool,
+}
+
+impl CameraPathPlayer {
+    pub fn play(&mut self) {
+        if !self.path.looped && self.time >= self.path.duration() {
+            self.time = 0.0;
+        }
+        self.playing = true;
+        self.needs_apply = true;
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = self.path.path_time(time);
+        self.needs_apply = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.seek(0.0);
+    }
+}
+
+
+pub struct CameraPathPlugin;
+
+impl Plugin for CameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraPathPlayer>()
+            .add_event::<AddCameraKeyframe>()
+            .add_systems(
+                Update,
+                (
+                    update_camera_path_ws,
+                    add_camera_keyframes,
+                    play_camera_path,
+                    draw_camera_path,
+                ).chain(),
+            );
+    }
+}
+
+
+fn add_camera_keyframes(
+    mut events: EventReader<AddCameraKeyframe>,
+    hand_cam: Query<&GlobalTransform, With<HandCamBox>>,
+    lens: Res<HandCamLensSettings>,
+    mut player: ResMut<CameraPathPlayer>,
+) {
+    for _ in events.read() {
+        let Ok(global) = hand_cam.get_single() else {
+            warn!("no hand cam to keyframe");
+            continue;
+        };
+        player.path.push(global.compute_transform(), lens.fov);
+        player.time = player.path.duration();
+    }
+}
+
+/// Drives the hand cam box along the path, the path is in world space whatever it's parented to
+pub fn play_camera_path(
+    time: Res<Time>,
+    mut player: ResMut<CameraPathPlayer>,
+    mut hand_cam: Query<(&mut Transform, Option<&Parent>), With<HandCamBox>>,
+    parents: Query<&GlobalTransform>,
+    mut lens: ResMut<HandCamLensSettings>,
+    recorder: Res<HandCamRecorder>,
+    mut record: EventWriter<HandCamRecordToggle>,
+) {
+    let player = player.as_mut();
+    if player.playing {
+        if player.record && !player.started_recording && !recorder.is_recording() {
+            record.send(HandCamRecordToggle);
+            player.started_recording = true;
+        }
+
+        let next = player.time + time.delta_seconds();
+        if !player.path.looped && next >= player.path.duration() {
+            player.time = player.path.duration();
+            player.playing = false;
+        } else {
+            player.time = player.path.path_time(next);
+        }
+    } else if !player.needs_apply {
+        if player.started_recording {
+            if recorder.is_recording() {
+                record.send(HandCamRecordToggle);
+            }
+            player.started_recording = false;
+        }
+        return;
+    }
+    player.needs_apply = false;
+
+    let Some((global, fov)) = player.path.sample(player.time) else {
+        return;
+    };
+    for (mut transform, parent) in hand_cam.iter_mut() {
+        let parent_global = parent.and_then(|p| parents.get(p.get()).ok());
+        *transform = match parent_global {
+            Some(parent_global) => GlobalTransform::from(global).reparented_to(parent_global),
+            None => global,
+        };
+    }
+    if !lens.orthographic && (lens.fov - fov).abs() > 1e-3 {
+        lens.fov = fov;
+    }
+}
+
+fn draw_camera_path(player: Res<CameraPathPlayer>, mut gizmos: Gizmos) {
+    let path = &player.path;
+    if path.keyframes.len() < 2 {
+        return;
+    }
+    let samples = ((path.duration() * GIZMO_SAMPLES_PER_SECOND) as usize).max(2);
+    gizmos.linestrip(
+        (0..=samples).filter_map(|i| {
+            path.sample(path.duration() * i as f32 / samples as f32)
+                .map(|(t, _)| t.translation)
+        }),
+        Color::srgb(1.0, 0.8, 0.1),
+    );
+    for key in path.keyframes.iter() {
+        let transform: Transform = key.transform.into();
+        gizmos.sphere(transform.translation, transform.rotation, 0.02, Color::srgb(1.0, 0.5, 0.0));
+        gizmos.arrow(
+            transform.translation,
+            transform.translation + transform.forward() * 0.1,
+            Color::srgb(1.0, 0.5, 0.0),
+        );
+    }
+}
+
+
+#[derive(Component, Default)]
+pub struct CameraPathWS {
+    file: String,
+}
+
+/// Keyframes as dots on a strip with the playhead, click or drag to scrub
+fn timeline(ui: &mut egui::Ui, player: &mut CameraPathPlayer) {
+    let size = egui::vec2(ui.available_width().max(200.0), TIMELINE_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let duration = player.path.duration().max(f32::EPSILON);
+    let x_at = |time: f32| rect.left() + rect.width() * (time / duration);
+
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    painter.hline(rect.x_range(), rect.center().y, visuals.widgets.noninteractive.fg_stroke);
+    for key in player.path.keyframes.iter() {
+        painter.circle_filled(
+            egui::pos2(x_at(key.time), rect.center().y),
+            4.0,
+            egui::Color32::from_rgb(255, 128, 0),
+        );
+    }
+    painter.vline(x_at(player.time), rect.y_range(), egui::Stroke::new(2.0, egui::Color32::RED));
+
+    if let Some(pos) = response.interact_pointer_pos() {
+        if response.clicked() || response.dragged() {
+            player.playing = false;
+            player.seek((pos.x - rect.left()) / rect.width() * duration);
+        }
+    }
+}
+
+pub fn update_camera_path_ws(
+    mut windows: Query<(Entity, &mut CameraPathWS)>,
+    mut contexts: WindowContexts,
+    mut player: ResMut<CameraPathPlayer>,
+    mut add_keyframe: EventWriter<AddCameraKeyframe>,
+    hand_cam: Query<&GlobalTransform, With<HandCamBox>>,
+    lens: Res<HandCamLensSettings>,
+) {
+    for (entity, mut window) in windows.iter_mut() {
+        if window.file.is_empty() {
+            window.file = default_camera_path_file().to_string_lossy().to_string();
+        }
+
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("Camera Path")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let play_label = if player.playing { "Pause" } else { "Play" };
+                    if ui.button(play_label).clicked() {
+                        if player.playing {
+                            player.playing = false;
+                        } else {
+                            player.play();
+                        }
+                    }
+                    if ui.button("Stop").clicked() {
+                        player.stop();
+                    }
+                    if ui.button("Add Keyframe").clicked() {
+                        add_keyframe.send(AddCameraKeyframe);
+                    }
+                });
+
+                timeline(ui, &mut player);
+                ui.label(format!("{:.2} / {:.2} s", player.time, player.path.duration()));
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut player.path.looped, "Loop");
+                    ui.checkbox(&mut player.record, "Record while playing");
+                });
+
+                let mut remove = None;
+                let mut overwrite = None;
+                let mut go_to = None;
+                let mut retimed = false;
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for (i, key) in player.path.keyframes.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("#{}", i + 1));
+                            retimed |= ui
+                                .add(egui::DragValue::new(&mut key.time).speed(0.05).range(0.0..=f32::MAX).suffix(" s"))
+                                .changed();
+                            ui.label(format!("{:.0}°", key.fov));
+                            if ui.button("Go to").clicked() {
+                                go_to = Some(key.time);
+                            }
+                            if ui.button("Set").clicked() {
+                                overwrite = Some(i);
+                            }
+                            if ui.button("X").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if player.path.keyframes.is_empty() {
+                        ui.label("Press the blue button on the hand cam to add keyframes");
+                    }
+                });
+
+                if retimed {
+                    player.path.sort();
+                }
+                if let Some(i) = remove {
+                    player.path.keyframes.remove(i);
+                }
+                if let (Some(i), Ok(global)) = (overwrite, hand_cam.get_single()) {
+                    let key = &mut player.path.keyframes[i];
+                    key.transform = global.compute_transform().into();
+                    key.fov = lens.fov;
+                }
+                if let Some(time) = go_to {
+                    player.playing = false;
+                    player.seek(time);
+                }
+
+                ui.label("Path File");
+                ui.add(egui::TextEdit::singleline(&mut window.file));
+                ui.horizontal(|ui| {
+                    let file = PathBuf::from(&window.file);
+                    if ui.button("Save Path").clicked() {
+                        match player.path.save(&file) {
+                            Ok(()) => info!("saved camera path to {:?}", file),
+                            Err(e) => error!("failed to save camera path to {:?}: {}", file, e),
+                        }
+                    }
+                    if ui.button("Load Path").clicked() {
+                        match CameraPath::load(&file) {
+                            Ok(mut path) => {
+                                path.sort();
+                                player.path = path;
+                                player.stop();
+                            }
+                            Err(e) => error!("failed to load camera path {:?}: {}", file, e),
+                        }
+                    }
+                    if ui.button("Clear").clicked() {
+                        player.path.keyframes.clear();
+                        player.stop();
+                    }
+                });
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, translation: Vec3, yaw: f32, fov: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            transform: Transform::from_translation(translation)
+                .with_rotation(Quat::from_rotation_y(yaw))
+                .into(),
+            fov,
+        }
+    }
+
+    #[test]
+    fn spline_passes_through_keyframes() {
+        let path = CameraPath {
+            keyframes: vec![
+                key(0.0, Vec3::ZERO, 0.0, 40.0),
+                key(1.0, Vec3::new(1.0, 0.0, 0.0), 1.0, 60.0),
+                key(3.0, Vec3::new(1.0, 0.0, 2.0), 2.0, 50.0),
+            ],
+            looped: false,
+        };
+        for k in path.keyframes.iter() {
+            let (transform, fov) = path.sample(k.time).unwrap();
+            assert!(transform.translation.distance(Vec3::from_array(k.transform.translation)) < 1e-5);
+            assert!(transform.rotation.angle_between(Quat::from_array(k.transform.rotation)) < 1e-3);
+            assert!((fov - k.fov).abs() < 1e-4);
+        }
+        // Clamped past the ends
+        let (end, _) = path.sample(10.0).unwrap();
+        assert!(end.translation.distance(Vec3::new(1.0, 0.0, 2.0)) < 1e-5);
+    }
+
+    #[test]
+    fn even_straight_paths_move_at_constant_speed() {
+        let path = CameraPath {
+            keyframes: (0..4)
+                .map(|i| key(i as f32, Vec3::X * i as f32, 0.0, 45.0))
+                .collect(),
+            looped: false,
+        };
+        for t in [0.25, 1.5, 2.75] {
+            let (transform, _) = path.sample(t).unwrap();
+            assert!(transform.translation.distance(Vec3::X * t) < 1e-4, "{} {}", t, transform.translation);
+        }
+    }
+
+    #[test]
+    fn rotations_take_the_short_way() {
+        let mut a = key(0.0, Vec3::ZERO, 0.1, 45.0);
+        let b = key(1.0, Vec3::ZERO, -0.1, 45.0);
+        // Same rotation as the other hemisphere
+        a.transform.rotation = (-Quat::from_array(a.transform.rotation)).to_array();
+        let path = CameraPath {
+            keyframes: vec![a, b],
+            looped: false,
+        };
+        let (mid, _) = path.sample(0.5).unwrap();
+        assert!(mid.rotation.angle_between(Quat::IDENTITY) < 1e-3);
+    }
+
+    #[test]
+    fn looped_paths_wrap() {
+        let path = CameraPath {
+            keyframes: vec![key(0.0, Vec3::ZERO, 0.0, 45.0), key(2.0, Vec3::X, 0.0, 45.0)],
+            looped: true,
+        };
+        assert!((path.path_time(3.0) - 1.0).abs() < 1e-6);
+        assert!((path.path_time(-0.5) - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn path_round_trip() {
+        let mut path = CameraPath::default();
+        path.push(Transform::from_xyz(0.0, 1.5, -1.0), 45.0);
+        path.push(Transform::from_xyz(1.0, 1.5, 0.0).looking_at(Vec3::ZERO, Vec3::Y), 30.0);
+        assert_eq!(path.duration(), KEYFRAME_SPACING);
+
+        let loaded = CameraPath::from_ron(&path.to_ron().unwrap()).unwrap();
+        assert_eq!(path, loaded);
+    }
+}
@@ -0,0 +1,233 @@
+// Splat viewer controls for desktop mode
+
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContext};
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use crate::gaussian::SelectedSplat;
+
+
+const DEFAULT_FOCUS: Vec3 = Vec3::ZERO;
+const DEFAULT_RADIUS: f32 = 3.0;
+const DEFAULT_YAW: f32 = 0.0;
+const DEFAULT_PITCH: f32 = 0.3;
+
+/// Metres per second, doubled while shift is held
+const FLY_SPEED: f32 = 1.5;
+/// Radians per pixel of mouse motion
+const LOOK_SENSITIVITY: f32 = 0.003;
+/// How far in front of the camera the orbit focus lands when leaving fly mode
+const FLY_FOCUS_DISTANCE: f32 = 2.0;
+
+
+/// Fly mode hands the camera from the orbit controller to WASD + mouse look
+#[derive(Resource, Default)]
+pub struct DesktopCamera {
+    pub flying: bool,
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewCommand {
+    ToggleFly,
+    FocusSelected,
+    Reset,
+}
+
+
+pub struct DesktopPlugin;
+
+impl Plugin for DesktopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DesktopCamera>()
+            .add_event::<ViewCommand>()
+            .add_systems(
+                Update,
+                (
+                    setup_orbit_camera,
+                    (view_shortcuts, update_view_panel),
+                    apply_view_commands,
+                    fly_camera,
+                    block_orbit_over_ui,
+                ).chain(),
+            );
+    }
+}
+
+
+/// Leaves the left mouse button to grabbing through the window pointer
+fn setup_orbit_camera(mut cams: Query<&mut PanOrbitCamera, Added<PanOrbitCamera>>) {
+    for mut cam in cams.iter_mut() {
+        cam.button_orbit = MouseButton::Right;
+        cam.button_pan = MouseButton::Middle;
+        reset_view(&mut cam);
+    }
+}
+
+fn reset_view(cam: &mut PanOrbitCamera) {
+    cam.target_focus = DEFAULT_FOCUS;
+    cam.target_radius = DEFAULT_RADIUS;
+    cam.target_yaw = DEFAULT_YAW;
+    cam.target_pitch = DEFAULT_PITCH;
+}
+
+/// Points the orbit controller at wherever the camera was flown to
+fn orbit_from_transform(cam: &mut PanOrbitCamera, transform: &Transform) {
+    let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    let focus = transform.translation + transform.forward() * FLY_FOCUS_DISTANCE;
+    cam.focus = focus;
+    cam.target_focus = focus;
+    cam.yaw = Some(yaw);
+    cam.target_yaw = yaw;
+    cam.pitch = Some(-pitch);
+    cam.target_pitch = -pitch;
+    cam.radius = Some(FLY_FOCUS_DISTANCE);
+    cam.target_radius = FLY_FOCUS_DISTANCE;
+    cam.force_update = true;
+}
+
+
+pub fn view_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut ctxs: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut view_commands: EventWriter<ViewCommand>,
+) {
+    if ctxs.get_single_mut().is_ok_and(|mut ctx| ctx.get_mut().wants_keyboard_input()) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        view_commands.send(ViewCommand::ToggleFly);
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        view_commands.send(ViewCommand::FocusSelected);
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        view_commands.send(ViewCommand::Reset);
+    }
+}
+
+pub fn update_view_panel(
+    mut ctxs: Query<&mut EguiContext, With<PrimaryWindow>>,
+    desktop_camera: Res<DesktopCamera>,
+    mut view_commands: EventWriter<ViewCommand>,
+) {
+    let Ok(mut ctx) = ctxs.get_single_mut() else {
+        return;
+    };
+    egui::TopBottomPanel::bottom("View").show(ctx.get_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.selectable_label(desktop_camera.flying, "Fly (Tab)").clicked() {
+                view_commands.send(ViewCommand::ToggleFly);
+            }
+            if ui.button("Focus Splat (F)").clicked() {
+                view_commands.send(ViewCommand::FocusSelected);
+            }
+            if ui.button("Reset View (Home)").clicked() {
+                view_commands.send(ViewCommand::Reset);
+            }
+            ui.separator();
+            ui.label(match desktop_camera.flying {
+                true => "WASD move, Q/E down/up, Shift faster, right drag to look",
+                false => "Right drag orbit, middle drag pan, scroll zoom, left drag grabs",
+            });
+        });
+    });
+}
+
+pub fn apply_view_commands(
+    mut view_commands: EventReader<ViewCommand>,
+    mut desktop_camera: ResMut<DesktopCamera>,
+    mut cams: Query<(&mut PanOrbitCamera, &Transform)>,
+    selected: Res<SelectedSplat>,
+    splats: Query<&GaussianCloudSettings>,
+) {
+    for command in view_commands.read() {
+        for (mut cam, transform) in cams.iter_mut() {
+            match command {
+                ViewCommand::ToggleFly => {
+                    if desktop_camera.flying {
+                        orbit_from_transform(&mut cam, transform);
+                    }
+                }
+                ViewCommand::FocusSelected => {
+                    let Some(settings) = selected.0.and_then(|e| splats.get(e).ok()) else {
+                        continue;
+                    };
+                    if desktop_camera.flying {
+                        orbit_from_transform(&mut cam, transform);
+                    }
+                    cam.target_focus = settings.transform.translation;
+                    cam.target_radius = DEFAULT_RADIUS * settings.transform.scale.x.max(0.1);
+                }
+                ViewCommand::Reset => reset_view(&mut cam),
+            }
+        }
+
+        match command {
+            ViewCommand::ToggleFly => desktop_camera.flying = !desktop_camera.flying,
+            ViewCommand::FocusSelected | ViewCommand::Reset => desktop_camera.flying = false,
+        }
+    }
+}
+
+pub fn fly_camera(
+    desktop_camera: Res<DesktopCamera>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut cams: Query<&mut Transform, With<PanOrbitCamera>>,
+) {
+    let look: Vec2 = mouse_motion.read().map(|m| m.delta).sum();
+    if !desktop_camera.flying {
+        return;
+    }
+
+    for mut transform in cams.iter_mut() {
+        if mouse_buttons.pressed(MouseButton::Right) {
+            let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            let yaw = yaw - look.x * LOOK_SENSITIVITY;
+            let pitch = (pitch - look.y * LOOK_SENSITIVITY).clamp(-1.54, 1.54);
+            transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+        }
+
+        let mut direction = Vec3::ZERO;
+        for (key, dir) in [
+            (KeyCode::KeyW, *transform.forward()),
+            (KeyCode::KeyS, *transform.back()),
+            (KeyCode::KeyA, *transform.left()),
+            (KeyCode::KeyD, *transform.right()),
+            (KeyCode::KeyE, Vec3::Y),
+            (KeyCode::KeyQ, Vec3::NEG_Y),
+        ] {
+            if keys.pressed(key) {
+                direction += dir;
+            }
+        }
+        let speed = match keys.pressed(KeyCode::ShiftLeft) {
+            true => FLY_SPEED * 2.0,
+            false => FLY_SPEED,
+        };
+        transform.translation += direction.normalize_or_zero() * speed * time.delta_seconds();
+    }
+}
+
+/// The orbit controller shouldn't react to drags on the egui panels, or fight fly mode
+pub fn block_orbit_over_ui(
+    desktop_camera: Res<DesktopCamera>,
+    mut ctxs: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut cams: Query<&mut PanOrbitCamera>,
+) {
+    let over_ui = ctxs.get_single_mut().is_ok_and(|mut ctx| {
+        let ctx = ctx.get_mut();
+        ctx.is_pointer_over_area() || ctx.wants_pointer_input()
+    });
+    let enabled = !desktop_camera.flying && !over_ui;
+    for mut cam in cams.iter_mut() {
+        if cam.enabled != enabled {
+            cam.enabled = enabled;
+        }
+    }
+}
@@ -0,0 +1,736 @@
+// Undo / redo of scene edits
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use bevy_suis::{xr::HandInputMethodData, xr_controllers::XrControllerInputMethodData, InputHandlerCaptures};
+
+use crate::gaussian::{spawn_splat, GaussianMarker, SelectedSplat, SplatSource};
+use crate::grabbing::{Grabbed, Grabble, GRAB_SEPARATION};
+use crate::locomotion::LocomotionSettings;
+use crate::main_menu::{spawn_hand_cam, HandCamBox, HandCamScreen, HandCamTool};
+use crate::scene_file::HandCamPlacement;
+use crate::splat_crop::{CropBox, SplatCrop};
+use crate::splat_eraser::SplatErasures;
+use crate::splat_grade::SplatGrade;
+use crate::transform_gizmo::{spawn_transform_gizmo, GizmoDrag, SplatTransformTool};
+
+
+const MAX_HISTORY: usize = 100;
+/// Slider drags and other splat edits become one entry once they've been still this long
+const SETTLE_SECONDS: f32 = 0.4;
+/// Two controller squeezes this close together, grabbing nothing, undo
+const DOUBLE_SQUEEZE_SECONDS: f32 = 0.4;
+
+/// Grabbles that write their edits somewhere else, so their own grabs aren't recorded
+#[derive(Component)]
+pub struct NoHistory;
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+/// What's needed to bring a splat back after it's removed
+#[derive(Clone, Debug)]
+pub struct SplatSnapshot {
+    pub source: String,
+    pub settings: GaussianCloudSettings,
+    pub visibility: Visibility,
+    pub crop: Option<SplatCrop>,
+    pub grade: Option<SplatGrade>,
+    /// Brought back with the splat but not compared, erase strokes are edits of their own
+    pub erasures: Option<Arc<SplatErasures>>,
+}
+
+impl SplatSnapshot {
+    /// Whether the parts the history tracks are the same
+    fn same_state(&self, other: &SplatSnapshot) -> bool {
+        self.settings.transform == other.settings.transform
+            && self.settings.global_opacity == other.settings.global_opacity
+            && self.settings.global_scale == other.settings.global_scale
+            && self.visibility == other.visibility
+            && self.crop_boxes() == other.crop_boxes()
+            && self.grade.unwrap_or_default() == other.grade.unwrap_or_default()
+    }
+
+    fn crop_boxes(&self) -> &[CropBox] {
+        self.crop.as_ref().map_or(&[], |crop| &crop.boxes)
+    }
+}
+
+/// What's needed to bring a tool from the main menu back after it's removed
+#[derive(Clone, Copy, Debug)]
+pub enum ToolSnapshot {
+    /// The camera body and screen are grabbed on their own, grabs of them follow the new ones
+    HandCam { placement: HandCamPlacement, camera: Entity, screen: Entity },
+    /// Follows the selected splat, nothing to keep
+    TransformTool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Edit {
+    /// A grab of a [`Grabble`], from grab start to release
+    Transform { entity: Entity, before: Transform, after: Transform },
+    /// Settings, visibility, crop and grade of a splat
+    Splat { entity: Entity, before: SplatSnapshot, after: SplatSnapshot },
+    Spawned { entity: Entity, splat: SplatSnapshot },
+    Despawned { entity: Entity, splat: SplatSnapshot },
+    ToolSpawned { entity: Entity, tool: ToolSnapshot },
+    ToolDespawned { entity: Entity, tool: ToolSnapshot },
+    /// Gaussians an eraser stroke removed from a splat
+    Erase { entity: Entity, indices: Vec<usize> },
+}
+
+impl Edit {
+    fn remap(&mut self, from: Entity, to: Entity) {
+        let entities = match self {
+            Edit::ToolSpawned { entity, tool: ToolSnapshot::HandCam { camera, screen, .. } }
+            | Edit::ToolDespawned { entity, tool: ToolSnapshot::HandCam { camera, screen, .. } } => {
+                vec![entity, camera, screen]
+            }
+            Edit::Transform { entity, .. }
+            | Edit::Splat { entity, .. }
+            | Edit::Spawned { entity, .. }
+            | Edit::Despawned { entity, .. }
+            | Edit::ToolSpawned { entity, .. }
+            | Edit::ToolDespawned { entity, .. }
+            | Edit::Erase { entity, .. } => vec![entity],
+        };
+        for entity in entities {
+            if *entity == from {
+                *entity = to;
+            }
+        }
+    }
+}
+
+/// Each entry is the edits of one action, undone and redone together
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+}
+
+impl EditHistory {
+    pub fn push(&mut self, entry: Vec<Edit>) {
+        if entry.is_empty() {
+            return;
+        }
+        self.undo.push(entry);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// A removed entity came back as `to`
+    fn remap(&mut self, from: Entity, to: Entity) {
+        for edit in self.undo.iter_mut().chain(self.redo.iter_mut()).flatten() {
+            edit.remap(from, to);
+        }
+    }
+}
+
+/// Undo / redo buttons for menus
+#[derive(SystemParam)]
+pub struct HistoryControls<'w> {
+    history: Res<'w, EditHistory>,
+    actions: EventWriter<'w, HistoryAction>,
+}
+
+impl HistoryControls<'_> {
+    pub fn ui(&mut self, ui: &mut bevy_egui::egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.history.can_undo(), bevy_egui::egui::Button::new("Undo")).clicked() {
+                self.actions.send(HistoryAction::Undo);
+            }
+            if ui.add_enabled(self.history.can_redo(), bevy_egui::egui::Button::new("Redo")).clicked() {
+                self.actions.send(HistoryAction::Redo);
+            }
+        });
+    }
+}
+
+/// Last seen state of everything the history watches
+#[derive(Resource, Default)]
+struct EditTracker {
+    splats: HashMap<Entity, SplatSnapshot>,
+    /// Splat state from before an edit that hasn't settled yet, and when it last changed
+    pending: HashMap<Entity, (SplatSnapshot, f32)>,
+    grab_starts: HashMap<Entity, Transform>,
+    tools: HashMap<Entity, ToolSnapshot>,
+    /// A hand cam brought back by undo or redo, placed and taking over from its old parts once it's spawned
+    respawned_hand_cam: Option<ToolSnapshot>,
+}
+
+
+pub struct EditHistoryPlugin;
+
+impl Plugin for EditHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistory>()
+            .init_resource::<EditTracker>()
+            .add_event::<HistoryAction>()
+            .add_systems(Update, (history_gestures, apply_history_actions, record_edits).chain());
+    }
+}
+
+
+fn snapshot(
+    source: &SplatSource,
+    settings: &GaussianCloudSettings,
+    visibility: &Visibility,
+    crop: Option<&SplatCrop>,
+    grade: Option<&SplatGrade>,
+) -> SplatSnapshot {
+    SplatSnapshot {
+        source: source.0.clone(),
+        settings: settings.clone(),
+        visibility: *visibility,
+        crop: crop.cloned(),
+        grade: grade.copied(),
+        erasures: None,
+    }
+}
+
+/// Turns what changed since last frame into history entries
+fn record_edits(
+    mut history: ResMut<EditHistory>,
+    mut tracker: ResMut<EditTracker>,
+    splats: Query<
+        (
+            Entity,
+            &SplatSource,
+            &GaussianCloudSettings,
+            &Visibility,
+            Option<&SplatCrop>,
+            Option<&SplatGrade>,
+            Option<Ref<SplatErasures>>,
+        ),
+        With<GaussianMarker>,
+    >,
+    mut removed_splats: RemovedComponents<GaussianMarker>,
+    new_grabs: Query<(Entity, &Transform), (Added<Grabbed>, With<Grabble>, Without<NoHistory>)>,
+    mut released: RemovedComponents<Grabbed>,
+    transforms: Query<&Transform>,
+    active_grabs: Query<(), Or<(With<Grabbed>, With<GizmoDrag>)>>,
+    hand_cams: Query<Entity, With<HandCamTool>>,
+    cameras: Query<(Entity, &Transform), With<HandCamBox>>,
+    screens: Query<(Entity, &Transform), With<HandCamScreen>>,
+    transform_tools: Query<Entity, With<SplatTransformTool>>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds();
+    let tracker = &mut *tracker;
+
+    // Spawns and removals in the same frame, like a scene load, are one entry
+    let mut spawns = Vec::new();
+    for entity in removed_splats.read() {
+        let Some(splat) = tracker.splats.remove(&entity) else {
+            continue;
+        };
+        if let Some((before, _)) = tracker.pending.remove(&entity) {
+            if !before.same_state(&splat) {
+                history.push(vec![Edit::Splat { entity, before, after: splat.clone() }]);
+            }
+        }
+        spawns.push(Edit::Despawned { entity, splat });
+    }
+
+    for (entity, source, settings, visibility, crop, grade, erasures) in splats.iter() {
+        let mut current = snapshot(source, settings, visibility, crop, grade);
+        // Only copied when they change, they hold a flag per gaussian
+        let erasures_changed = erasures.as_ref().map_or(true, |e| e.is_changed());
+        match tracker.splats.get_mut(&entity) {
+            None => {
+                current.erasures = erasures.map(|e| Arc::new(SplatErasures::clone(&e)));
+                spawns.push(Edit::Spawned { entity, splat: current.clone() });
+                tracker.splats.insert(entity, current);
+            }
+            Some(known) => {
+                current.erasures = match erasures_changed {
+                    true => erasures.map(|e| Arc::new(SplatErasures::clone(&e))),
+                    false => known.erasures.take(),
+                };
+                if !known.same_state(&current) {
+                    let before = known.clone();
+                    tracker.pending.entry(entity).or_insert((before, now)).1 = now;
+                }
+                *known = current;
+            }
+        }
+    }
+
+    let hand_cam = match (cameras.get_single(), screens.get_single()) {
+        (Ok((camera, camera_transform)), Ok((screen, screen_transform))) => Some(ToolSnapshot::HandCam {
+            placement: HandCamPlacement {
+                camera: (*camera_transform).into(),
+                screen: (*screen_transform).into(),
+            },
+            camera,
+            screen,
+        }),
+        _ => None,
+    };
+    // A respawned hand cam's parts only show up once it's spawned
+    if let Some(ToolSnapshot::HandCam { placement, camera, screen }) = tracker.respawned_hand_cam {
+        if let Some(ToolSnapshot::HandCam { camera: new_camera, screen: new_screen, .. }) = hand_cam {
+            commands.entity(new_camera).insert(Transform::from(placement.camera));
+            commands.entity(new_screen).insert(Transform::from(placement.screen));
+            history.remap(camera, new_camera);
+            history.remap(screen, new_screen);
+            tracker.respawned_hand_cam = None;
+        }
+    }
+
+    let mut tools: HashMap<Entity, ToolSnapshot> =
+        transform_tools.iter().map(|entity| (entity, ToolSnapshot::TransformTool)).collect();
+    for entity in hand_cams.iter() {
+        // Keeps the last placement if its parts can't be found
+        if let Some(tool) = hand_cam.or_else(|| tracker.tools.get(&entity).copied()) {
+            tools.insert(entity, tool);
+        }
+    }
+    for (entity, tool) in tracker.tools.iter() {
+        if !tools.contains_key(entity) {
+            spawns.push(Edit::ToolDespawned { entity: *entity, tool: *tool });
+        }
+    }
+    for (entity, tool) in tools.iter() {
+        if !tracker.tools.contains_key(entity) {
+            spawns.push(Edit::ToolSpawned { entity: *entity, tool: *tool });
+        }
+    }
+    tracker.tools = tools;
+    history.push(spawns);
+
+    for (entity, transform) in new_grabs.iter() {
+        tracker.grab_starts.insert(entity, *transform);
+    }
+    for entity in released.read() {
+        let Some(before) = tracker.grab_starts.remove(&entity) else {
+            continue;
+        };
+        if let Ok(after) = transforms.get(entity) {
+            if *after != before {
+                history.push(vec![Edit::Transform { entity, before, after: *after }]);
+            }
+        }
+    }
+
+    // Splat edits wait for grabs of the gizmo to end, so a drag is one entry
+    if !active_grabs.is_empty() {
+        return;
+    }
+    let settled: Vec<Entity> = tracker
+        .pending
+        .iter()
+        .filter(|(_, (_, changed))| now - changed >= SETTLE_SECONDS)
+        .map(|(entity, _)| *entity)
+        .collect();
+    for entity in settled {
+        let (before, _) = tracker.pending.remove(&entity).unwrap();
+        let Some(after) = tracker.splats.get(&entity) else {
+            continue;
+        };
+        if !before.same_state(after) {
+            history.push(vec![Edit::Splat { entity, before, after: after.clone() }]);
+        }
+    }
+}
+
+pub fn apply_history_actions(
+    mut actions: EventReader<HistoryAction>,
+    mut history: ResMut<EditHistory>,
+    mut tracker: ResMut<EditTracker>,
+    mut transforms: Query<&mut Transform>,
+    mut splats: Query<(&mut GaussianCloudSettings, &mut Visibility), With<GaussianMarker>>,
+    mut erasures: Query<&mut SplatErasures>,
+    mut selected: ResMut<SelectedSplat>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    for action in actions.read() {
+        let Some(mut entry) = (match action {
+            HistoryAction::Undo => history.undo.pop(),
+            HistoryAction::Redo => history.redo.pop(),
+        }) else {
+            continue;
+        };
+
+        let undo = *action == HistoryAction::Undo;
+        let order: Vec<usize> = match undo {
+            true => (0..entry.len()).rev().collect(),
+            false => (0..entry.len()).collect(),
+        };
+        for i in order {
+            let mut respawned = None;
+            match &entry[i] {
+                Edit::Transform { entity, before, after } => {
+                    if let Ok(mut transform) = transforms.get_mut(*entity) {
+                        *transform = if undo { *before } else { *after };
+                    }
+                }
+                Edit::Splat { entity, before, after } => {
+                    let state = if undo { before } else { after };
+                    if let Ok((mut settings, mut visibility)) = splats.get_mut(*entity) {
+                        *settings = state.settings.clone();
+                        *visibility = state.visibility;
+                        match &state.crop {
+                            Some(crop) => commands.entity(*entity).insert(crop.clone()),
+                            None => commands.entity(*entity).remove::<SplatCrop>(),
+                        };
+                        match state.grade {
+                            Some(grade) => commands.entity(*entity).insert(grade),
+                            None => commands.entity(*entity).remove::<SplatGrade>(),
+                        };
+                    }
+                    // Known already, so it isn't recorded again
+                    tracker.pending.remove(entity);
+                    if let Some(known) = tracker.splats.get_mut(entity) {
+                        known.settings = state.settings.clone();
+                        known.visibility = state.visibility;
+                        known.crop = state.crop.clone();
+                        known.grade = state.grade;
+                    }
+                }
+                Edit::Spawned { entity, splat } | Edit::Despawned { entity, splat } => {
+                    let remove = matches!(entry[i], Edit::Spawned { .. }) == undo;
+                    if remove {
+                        tracker.splats.remove(entity);
+                        tracker.pending.remove(entity);
+                        if let Some(e) = commands.get_entity(*entity) {
+                            e.despawn_recursive();
+                        }
+                    } else {
+                        let new = spawn_splat(&mut commands, &asset_server, &splat.source, splat.settings.clone());
+                        commands.entity(new).insert(splat.visibility);
+                        if let Some(crop) = &splat.crop {
+                            commands.entity(new).insert(crop.clone());
+                        }
+                        if let Some(grade) = splat.grade {
+                            commands.entity(new).insert(grade);
+                        }
+                        if let Some(erasures) = &splat.erasures {
+                            commands.entity(new).insert(SplatErasures::clone(erasures));
+                        }
+                        tracker.splats.insert(new, splat.clone());
+                        respawned = Some((*entity, new));
+                    }
+                }
+                Edit::ToolSpawned { entity, tool } | Edit::ToolDespawned { entity, tool } => {
+                    let remove = matches!(entry[i], Edit::ToolSpawned { .. }) == undo;
+                    if remove {
+                        tracker.tools.remove(entity);
+                        if let Some(e) = commands.get_entity(*entity) {
+                            e.despawn_recursive();
+                        }
+                    } else {
+                        let new = match tool {
+                            ToolSnapshot::HandCam { .. } => {
+                                tracker.respawned_hand_cam = Some(*tool);
+                                spawn_hand_cam(&mut commands, &asset_server, &mut images, &mut materials, &mut meshes)
+                            }
+                            // Moves to the selected splat on its own
+                            ToolSnapshot::TransformTool => {
+                                spawn_transform_gizmo(&mut commands, &mut meshes, &mut materials, Transform::IDENTITY)
+                            }
+                        };
+                        tracker.tools.insert(new, *tool);
+                        respawned = Some((*entity, new));
+                    }
+                }
+                Edit::Erase { entity, indices } => {
+                    // The stroke added the erasures and undo leaves them, so they're still there
+                    if let Ok(mut erasures) = erasures.get_mut(*entity) {
+                        if undo {
+                            erasures.restore(indices);
+                        } else {
+                            for i in indices.iter() {
+                                erasures.erase(*i);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // The splat comes back as a new entity, everything pointing at the old one follows
+            if let Some((old, new)) = respawned {
+                for edit in entry.iter_mut() {
+                    edit.remap(old, new);
+                }
+                history.remap(old, new);
+                if selected.0 == Some(old) {
+                    selected.0 = Some(new);
+                }
+            }
+        }
+
+        match action {
+            HistoryAction::Undo => history.redo.push(entry),
+            HistoryAction::Redo => history.undo.push(entry),
+        }
+    }
+}
+
+#[derive(Default)]
+struct GestureState {
+    middle_pinch: bool,
+    ring_pinch: bool,
+    squeezed: bool,
+    last_squeeze: f32,
+}
+
+/// Pinching thumb and middle finger undoes, thumb and ring finger redoes. On controllers a double
+/// squeeze that doesn't grab anything undoes, unless that squeeze drags the world instead.
+fn history_gestures(
+    methods: Query<(Entity, Option<&HandInputMethodData>, Option<&XrControllerInputMethodData>)>,
+    handlers: Query<&InputHandlerCaptures>,
+    locomotion: Option<Res<LocomotionSettings>>,
+    time: Res<Time<Real>>,
+    mut gestures: Local<HashMap<Entity, GestureState>>,
+    mut actions: EventWriter<HistoryAction>,
+) {
+    let now = time.elapsed_seconds();
+    let squeeze_undo = !locomotion.is_some_and(|settings| settings.world_grab);
+    for (method, hand_data, controller_data) in methods.iter() {
+        if hand_data.is_none() && controller_data.is_none() {
+            continue;
+        }
+        let holding = handlers.iter().any(|c| c.captured_methods.contains(&method));
+        let state = gestures.entry(method).or_default();
+
+        if let Some(hand) = hand_data {
+            let hand = hand.get_in_relative_space(&GlobalTransform::IDENTITY);
+            let pinched = |tip_pos: Vec3, tip_radius: f32| {
+                hand.thumb.tip.pos.distance(tip_pos) < hand.thumb.tip.radius + tip_radius + GRAB_SEPARATION
+            };
+            let middle = pinched(hand.middle.tip.pos, hand.middle.tip.radius);
+            let ring = pinched(hand.ring.tip.pos, hand.ring.tip.radius) && !middle;
+            if middle && !state.middle_pinch && !holding {
+                actions.send(HistoryAction::Undo);
+            }
+            if ring && !state.ring_pinch && !holding {
+                actions.send(HistoryAction::Redo);
+            }
+            state.middle_pinch = middle;
+            state.ring_pinch = ring;
+        }
+
+        if let Some(controller) = controller_data {
+            if controller.squeezed && !state.squeezed && !holding && squeeze_undo {
+                if now - state.last_squeeze < DOUBLE_SQUEEZE_SECONDS {
+                    actions.send(HistoryAction::Undo);
+                    state.last_squeeze = f32::MIN;
+                } else {
+                    state.last_squeeze = now;
+                }
+            }
+            state.squeezed = controller.squeezed;
+        }
+    }
+    gestures.retain(|method, _| methods.contains(*method));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splat_state(x: f32) -> SplatSnapshot {
+        SplatSnapshot {
+            source: "cat1.gcloud".into(),
+            settings: GaussianCloudSettings {
+                transform: Transform::from_xyz(x, 0.0, 0.0),
+                ..default()
+            },
+            visibility: Visibility::Visible,
+            crop: None,
+            grade: None,
+            erasures: None,
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<bevy_gaussian_splatting::GaussianCloud>()
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_asset::<Image>()
+            .init_resource::<SelectedSplat>()
+            .init_resource::<EditHistory>()
+            .init_resource::<EditTracker>()
+            .add_event::<HistoryAction>()
+            .add_systems(Update, apply_history_actions);
+        app
+    }
+
+    #[test]
+    fn undo_and_redo_a_grab() {
+        let mut app = app();
+        let entity = app.world_mut().spawn(Transform::from_xyz(1.0, 0.0, 0.0)).id();
+        app.world_mut().resource_mut::<EditHistory>().push(vec![Edit::Transform {
+            entity,
+            before: Transform::IDENTITY,
+            after: Transform::from_xyz(1.0, 0.0, 0.0),
+        }]);
+
+        app.world_mut().send_event(HistoryAction::Undo);
+        app.update();
+        assert_eq!(app.world().get::<Transform>(entity).unwrap().translation, Vec3::ZERO);
+        assert!(app.world().resource::<EditHistory>().can_redo());
+
+        app.world_mut().send_event(HistoryAction::Redo);
+        app.update();
+        assert_eq!(app.world().get::<Transform>(entity).unwrap().translation, Vec3::X);
+        assert!(!app.world().resource::<EditHistory>().can_redo());
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let mut history = EditHistory::default();
+        let entity = Entity::from_raw(1);
+        history.push(vec![Edit::Splat { entity, before: splat_state(0.0), after: splat_state(1.0) }]);
+        history.redo = history.undo.drain(..).collect();
+        assert!(history.can_redo());
+        history.push(vec![Edit::Splat { entity, before: splat_state(1.0), after: splat_state(2.0) }]);
+        assert!(!history.can_redo());
+        assert!(history.can_undo());
+
+        for i in 0..MAX_HISTORY + 10 {
+            history.push(vec![Edit::Splat { entity, before: splat_state(i as f32), after: splat_state(0.0) }]);
+        }
+        assert_eq!(history.undo.len(), MAX_HISTORY);
+    }
+
+    #[test]
+    fn undoing_a_removal_remaps_the_splat() {
+        let mut app = app();
+        let removed = Entity::from_raw(1000);
+        let mut history = EditHistory::default();
+        history.push(vec![Edit::Despawned { entity: removed, splat: splat_state(0.0) }]);
+        history.push(vec![Edit::Splat { entity: removed, before: splat_state(0.0), after: splat_state(3.0) }]);
+        // Step back over the edit so the removal is next
+        history.redo.push(history.undo.pop().unwrap());
+        app.insert_resource(history);
+
+        app.world_mut().send_event(HistoryAction::Undo);
+        app.update();
+
+        let mut splats = app.world_mut().query_filtered::<Entity, With<GaussianMarker>>();
+        let respawned = splats.single(app.world());
+        assert_ne!(respawned, removed);
+        let history = app.world().resource::<EditHistory>();
+        assert!(history.redo.iter().flatten().all(|edit| match edit {
+            Edit::Splat { entity, .. } | Edit::Despawned { entity, .. } => *entity == respawned,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn crops_are_compared_and_restored() {
+        let mut cropped = splat_state(0.0);
+        cropped.crop = Some(SplatCrop {
+            boxes: vec![CropBox::new(Transform::IDENTITY, false)],
+        });
+        assert!(!splat_state(0.0).same_state(&cropped));
+        // No crop and an empty one are the same
+        let mut empty = splat_state(0.0);
+        empty.crop = Some(SplatCrop::default());
+        assert!(splat_state(0.0).same_state(&empty));
+
+        let mut app = app();
+        let entity = app
+            .world_mut()
+            .spawn((GaussianMarker, cropped.settings.clone(), Visibility::Visible, cropped.crop.clone().unwrap()))
+            .id();
+        app.world_mut().resource_mut::<EditHistory>().push(vec![Edit::Splat {
+            entity,
+            before: splat_state(0.0),
+            after: cropped,
+        }]);
+
+        app.world_mut().send_event(HistoryAction::Undo);
+        app.update();
+        assert!(app.world().get::<SplatCrop>(entity).is_none());
+        app.world_mut().send_event(HistoryAction::Redo);
+        app.update();
+        assert_eq!(app.world().get::<SplatCrop>(entity).unwrap().boxes.len(), 1);
+    }
+
+    #[test]
+    fn erase_strokes_undo_and_come_back_with_the_splat() {
+        let mut app = app();
+        let mut erasures = SplatErasures::default();
+        erasures.erase(2);
+        erasures.erase(4);
+        let removed = Entity::from_raw(1000);
+        let mut history = EditHistory::default();
+        history.push(vec![Edit::Erase { entity: removed, indices: vec![2, 4] }]);
+        history.push(vec![Edit::Despawned {
+            entity: removed,
+            splat: SplatSnapshot {
+                erasures: Some(Arc::new(erasures)),
+                ..splat_state(0.0)
+            },
+        }]);
+        app.insert_resource(history);
+
+        app.world_mut().send_event(HistoryAction::Undo);
+        app.update();
+        let mut splats = app.world_mut().query_filtered::<Entity, With<GaussianMarker>>();
+        let respawned = splats.single(app.world());
+        assert_eq!(app.world().get::<SplatErasures>(respawned).unwrap().len(), 2);
+
+        // The stroke follows the splat
+        app.world_mut().send_event(HistoryAction::Undo);
+        app.update();
+        assert!(app.world().get::<SplatErasures>(respawned).unwrap().is_empty());
+        app.world_mut().send_event(HistoryAction::Redo);
+        app.update();
+        let erasures = app.world().get::<SplatErasures>(respawned).unwrap();
+        assert!(erasures.is_erased(2) && erasures.is_erased(4));
+    }
+
+    #[test]
+    fn double_squeeze_leaves_world_grab_alone() {
+        let undos = |locomotion: Option<LocomotionSettings>| {
+            let mut app = App::new();
+            app.add_plugins(MinimalPlugins)
+                .add_event::<HistoryAction>()
+                .add_systems(Update, history_gestures);
+            if let Some(locomotion) = locomotion {
+                app.insert_resource(locomotion);
+            }
+            let controller = app.world_mut().spawn(XrControllerInputMethodData::default()).id();
+            for squeezed in [true, false, true] {
+                app.world_mut().get_mut::<XrControllerInputMethodData>(controller).unwrap().squeezed = squeezed;
+                app.update();
+            }
+            app.world().resource::<Events<HistoryAction>>().len()
+        };
+
+        assert_eq!(undos(None), 1);
+        let world_grab = LocomotionSettings { world_grab: true, ..default() };
+        assert_eq!(undos(Some(world_grab.clone())), 0);
+        assert_eq!(undos(Some(LocomotionSettings { world_grab: false, ..world_grab })), 1);
+    }
+}
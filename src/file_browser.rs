@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::{file::FileAssetReader, AssetSource, AssetSourceId};
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::{future, StreamExt};
+use bevy::tasks::{block_on, IoTaskPool, Task};
+use bevy_egui::egui;
+
+use crate::ui_window::WindowContexts;
+
+
+/// Asset source that reads straight from the device filesystem, rooted at `/`.
+pub const FS_ASSET_SOURCE: &str = "fs";
+
+/// Extensions `bevy_gaussian_splatting` can load with the features we enable.
+pub const SPLAT_EXTENSIONS: &[&str] = &["gcloud", "ply"];
+
+
+/// Registers the `fs://` asset source. Has to run before `AssetPlugin` is added.
+pub fn register_fs_asset_source(app: &mut App) {
+    app.register_asset_source(
+        FS_ASSET_SOURCE,
+        AssetSource::build().with_reader(|| Box::new(FileAssetReader::new("/"))),
+    );
+}
+
+
+/// Sent when a splat file has been picked in the browser.
+#[derive(Event, Clone, Debug)]
+pub struct SplatFileChosen {
+    /// Full asset path, including the source, e.g. `fs://sdcard/Download/room.ply`
+    pub asset_path: String,
+}
+
+
+#[derive(Clone, Debug)]
+pub struct BrowserRoot {
+    pub label: String,
+    pub source: &'static str,
+    pub path: PathBuf,
+}
+
+impl BrowserRoot {
+    pub fn embedded() -> Self {
+        Self {
+            label: "Embedded".into(),
+            source: "embedded",
+            path: PathBuf::new(),
+        }
+    }
+
+    /// A root on the device filesystem, `path` is absolute.
+    pub fn filesystem(label: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        Self {
+            label: label.into(),
+            source: FS_ASSET_SOURCE,
+            path: fs_relative_path(path.as_ref()),
+        }
+    }
+}
+
+/// Roots shown in the browser, the embedded assets are always first.
+pub fn default_roots() -> Vec<BrowserRoot> {
+    let mut roots = vec![BrowserRoot::embedded()];
+
+    #[cfg(target_os = "android")]
+    {
+        roots.push(BrowserRoot::filesystem("Downloads", "/sdcard/Download"));
+        roots.push(BrowserRoot::filesystem("Splats", "/sdcard/Splats"));
+        roots.push(BrowserRoot::filesystem("Internal Storage", "/storage/emulated/0"));
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            roots.push(BrowserRoot::filesystem("Home", home));
+        }
+        if let Ok(current_dir) = std::env::current_dir() {
+            roots.push(BrowserRoot::filesystem("Working Dir", current_dir));
+        }
+    }
+
+    roots
+}
+
+/// Paths on the `fs` source are relative to `/`.
+fn fs_relative_path(path: &Path) -> PathBuf {
+    path.strip_prefix("/").unwrap_or(path).to_path_buf()
+}
+
+pub fn is_splat_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SPLAT_EXTENSIONS.iter().any(|s| s.eq_ignore_ascii_case(ext)))
+}
+
+/// Builds the `source://path` string the asset server expects.
+pub fn asset_path_string(source: &str, path: &Path) -> String {
+    format!("{}://{}", source, path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Asset path of an absolute path on the device filesystem.
+pub fn fs_asset_path(path: &Path) -> String {
+    asset_path_string(FS_ASSET_SOURCE, &fs_relative_path(path))
+}
+
+
+#[derive(Clone, Debug)]
+pub struct BrowserEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+type ListingTask = Task<Result<Vec<BrowserEntry>, String>>;
+
+#[derive(Component)]
+pub struct FileBrowserWS {
+    roots: Vec<BrowserRoot>,
+    current_root: usize,
+    current_dir: PathBuf,
+    entries: Vec<BrowserEntry>,
+    listing: Option<ListingTask>,
+    error: Option<String>,
+    needs_refresh: bool,
+}
+
+impl Default for FileBrowserWS {
+    fn default() -> Self {
+        Self::new(default_roots())
+    }
+}
+
+impl FileBrowserWS {
+    pub fn new(roots: Vec<BrowserRoot>) -> Self {
+        let current_dir = roots.first().map(|r| r.path.clone()).unwrap_or_default();
+        Self {
+            roots,
+            current_root: 0,
+            current_dir,
+            entries: Vec::new(),
+            listing: None,
+            error: None,
+            needs_refresh: true,
+        }
+    }
+
+    fn root(&self) -> Option<&BrowserRoot> {
+        self.roots.get(self.current_root)
+    }
+
+    fn open_root(&mut self, index: usize) {
+        if let Some(root) = self.roots.get(index) {
+            self.current_root = index;
+            self.current_dir = root.path.clone();
+            self.needs_refresh = true;
+        }
+    }
+
+    fn open_dir(&mut self, dir: PathBuf) {
+        self.current_dir = dir;
+        self.needs_refresh = true;
+    }
+
+    fn can_go_up(&self) -> bool {
+        self.root().is_some_and(|root| self.current_dir != root.path)
+    }
+
+    fn go_up(&mut self) {
+        if !self.can_go_up() {
+            return;
+        }
+        if let Some(parent) = self.current_dir.parent() {
+            self.open_dir(parent.to_path_buf());
+        }
+    }
+}
+
+
+pub struct FileBrowserPlugin;
+
+impl Plugin for FileBrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SplatFileChosen>()
+            .add_systems(Update, (poll_directory_listing, update_file_browser_ws).chain());
+    }
+}
+
+
+fn list_directory(asset_server: AssetServer, source: &'static str, dir: PathBuf) -> ListingTask {
+    IoTaskPool::get().spawn(async move {
+        let source = asset_server
+            .get_source(AssetSourceId::from(source))
+            .map_err(|e| e.to_string())?;
+        let reader = source.reader();
+
+        let paths: Vec<PathBuf> = reader
+            .read_directory(&dir)
+            .await
+            .map_err(|e| e.to_string())?
+            .collect()
+            .await;
+
+        let mut entries = Vec::new();
+        for path in paths {
+            let is_dir = reader.is_directory(&path).await.unwrap_or(false);
+            if !is_dir && !is_splat_file(&path) {
+                continue;
+            }
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // hidden folders are mostly android/app junk
+            if name.starts_with('.') {
+                continue;
+            }
+            entries.push(BrowserEntry { name, path, is_dir });
+        }
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    })
+}
+
+fn poll_directory_listing(
+    mut browsers: Query<&mut FileBrowserWS>,
+    asset_server: Res<AssetServer>,
+) {
+    for mut browser in browsers.iter_mut() {
+        if browser.needs_refresh {
+            browser.needs_refresh = false;
+            if let Some(root) = browser.root() {
+                let task = list_directory(
+                    asset_server.clone(),
+                    root.source,
+                    browser.current_dir.clone(),
+                );
+                browser.listing = Some(task);
+            }
+        }
+
+        let Some(task) = browser.listing.as_mut() else {
+            continue;
+        };
+        let Some(result) = block_on(future::poll_once(task)) else {
+            continue;
+        };
+        browser.listing = None;
+        match result {
+            Ok(entries) => {
+                browser.entries = entries;
+                browser.error = None;
+            }
+            Err(e) => {
+                warn!("failed to list {:?}: {}", browser.current_dir, e);
+                browser.entries.clear();
+                browser.error = Some(e);
+            }
+        }
+    }
+}
+
+
+pub fn update_file_browser_ws(
+    mut browsers: Query<(Entity, &mut FileBrowserWS)>,
+    mut contexts: WindowContexts,
+    mut chosen_writer: EventWriter<SplatFileChosen>,
+) {
+    for (entity, mut browser) in browsers.iter_mut() {
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("File Browser")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                let mut root_index = browser.current_root;
+                egui::ComboBox::from_label("Location")
+                    .selected_text(browser.root().map(|r| r.label.clone()).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for (i, root) in browser.roots.iter().enumerate() {
+                            ui.selectable_value(&mut root_index, i, &root.label);
+                        }
+                    });
+                if root_index != browser.current_root {
+                    browser.open_root(root_index);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(browser.can_go_up(), egui::Button::new("Up")).clicked() {
+                        browser.go_up();
+                    }
+                    if ui.button("Refresh").clicked() {
+                        browser.needs_refresh = true;
+                    }
+                });
+
+                if let Some(root) = browser.root() {
+                    ui.label(asset_path_string(root.source, &browser.current_dir));
+                }
+
+                if browser.listing.is_some() {
+                    ui.spinner();
+                }
+                if let Some(error) = &browser.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                let mut open_dir = None;
+                let mut chosen = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entry in browser.entries.iter() {
+                        if entry.is_dir {
+                            if ui.button(format!("[{}]", entry.name)).clicked() {
+                                open_dir = Some(entry.path.clone());
+                            }
+                        } else if ui.button(&entry.name).clicked() {
+                            chosen = Some(entry.path.clone());
+                        }
+                    }
+                    if browser.entries.is_empty() && browser.listing.is_none() {
+                        ui.label("No splats here");
+                    }
+                });
+
+                if let Some(dir) = open_dir {
+                    browser.open_dir(dir);
+                }
+                if let (Some(path), Some(root)) = (chosen, browser.root()) {
+                    chosen_writer.send(SplatFileChosen {
+                        asset_path: asset_path_string(root.source, &path),
+                    });
+                }
+            });
+    }
+}
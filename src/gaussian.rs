@@ -8,3 +8,58 @@ pub struct GaussianMarker {
     pub prev_visibility: Visibility,
 }
 
+/// The asset path a splat was loaded from, kept so it can be duplicated.
+#[derive(Component, Clone, Debug)]
+pub struct SplatSource(pub String);
+
+/// The splat the menu sliders and the transform tool act on.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SelectedSplat(pub Option<Entity>);
+
+pub fn default_splat_settings() -> GaussianCloudSettings {
+    GaussianCloudSettings {
+        aabb: false,
+        global_opacity: 1.0,
+        global_scale: 1.0,
+        transform: Transform::from_xyz(0.0, 0.0, 0.0).with_rotation(Quat::from_rotation_z(std::f32::consts::PI)),
+        opacity_adaptive_radius: false,
+        ..Default::default()
+    }
+}
+
+pub fn spawn_splat(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    asset_path: &str,
+    settings: GaussianCloudSettings,
+) -> Entity {
+    let name = asset_path.rsplit('/').next().unwrap_or(asset_path);
+
+    commands.spawn((
+        GaussianSplattingBundle {
+            cloud: asset_server.load(asset_path.to_string()),
+            settings,
+            visibility: Visibility::Visible,
+            ..Default::default()
+        },
+        GaussianMarker {
+            prev_visibility: Visibility::Visible,
+        },
+        SplatSource(asset_path.to_string()),
+        Name::new(name.to_string()),
+        //RenderLayers::layer(2),
+        //RenderLayers::layer(1),
+    )).id()
+}
+
+/// Drops the selection once the selected splat is gone.
+pub fn clear_despawned_selection(
+    mut selected: ResMut<SelectedSplat>,
+    splats: Query<(), With<GaussianMarker>>,
+) {
+    if let Some(entity) = selected.0 {
+        if !splats.contains(entity) {
+            selected.0 = None;
+        }
+    }
+}
@@ -10,11 +10,52 @@ use bevy_suis::{
 #[derive(Clone, Copy, Component)]
 pub struct Grabble;
 
+/// Handler pose relative to the method(s) holding it
 #[derive(Clone, Copy, Component)]
-pub struct Grabbed(pub Transform);
+pub struct Grabbed {
+    pub method: Entity,
+    pub offset: Transform,
+    /// Set while a second method is holding on as well
+    pub second: Option<SecondGrab>,
+}
+
+#[derive(Clone, Copy)]
+pub struct SecondGrab {
+    pub method: Entity,
+    pub offset: Transform,
+    /// World space poses from when the second method joined
+    pub start_handler: Transform,
+    pub start_first: Vec3,
+    pub start_second: Vec3,
+}
+
+impl SecondGrab {
+    /// Scales by the change in separation of the two methods and rotates by the change of the axis between them
+    pub fn two_handed_transform(&self, first: Vec3, second: Vec3) -> Transform {
+        let start_axis = self.start_second - self.start_first;
+        let axis = second - first;
+        if start_axis.length_squared() < 1e-6 || axis.length_squared() < 1e-6 {
+            return self.start_handler;
+        }
+        let scale = axis.length() / start_axis.length();
+        let rotation = Quat::from_rotation_arc(start_axis.normalize(), axis.normalize());
+        let start_mid = (self.start_first + self.start_second) * 0.5;
+        let mid = (first + second) * 0.5;
+
+        Transform {
+            translation: mid + rotation * (self.start_handler.translation - start_mid) * scale,
+            rotation: rotation * self.start_handler.rotation,
+            scale: self.start_handler.scale * scale,
+        }
+    }
+}
 
 pub const GRAB_SEPARATION: f32 = 0.005;
 
+fn relative_to(method_gt: &GlobalTransform, handler_gt: &GlobalTransform) -> Transform {
+    Transform::from_matrix(method_gt.compute_matrix().inverse() * handler_gt.compute_matrix())
+}
+
 /// System to move grabbable objects when grabbed
 pub fn move_grabble(
     mut grabbles: Query<
@@ -37,54 +78,71 @@ pub fn move_grabble(
     parent_query: Query<&GlobalTransform>,
     mut cmds: Commands,
 ) {
-    for (handler_entity, handler, handler_gt, mut handler_transform, mut grabbed, parent) in
+    for (handler_entity, handler, handler_gt, mut handler_transform, grabbed, parent) in
         &mut grabbles
     {
-        let Some((method_transform, hand_data, controller_data, mouse_data)) = handler
+        // Up to two methods that are captured and actually holding on
+        let holding: Vec<(Entity, &GlobalTransform, Option<&MouseInputMethodData>)> = handler
             .captured_methods
-            .first()
-            .copied()
-            .and_then(|v| method_query.get(v).ok())
-        else {
-            cmds.entity(handler_entity).remove::<Grabbed>();
-            continue;
-        };
-        let mut grabbing = false;
-        if let Some(hand) = hand_data {
-            let hand = hand.get_in_relative_space(handler_gt);
-            grabbing |= finger_separation(&hand, GRAB_SEPARATION);
-        }
-        if let Some(controller) = controller_data {
-            grabbing |= controller.squeezed;
-        }
-        if let Some(mouse) = mouse_data.as_ref() {
-            grabbing |= mouse.left_button.pressed;
-        }
-        match (grabbed.is_some(), grabbing) {
-            (false, true) => {
-                cmds.entity(handler_entity)
-                    .insert(Grabbed(Transform::from_matrix(
-                        method_transform.compute_matrix().inverse() * handler_gt.compute_matrix(),
-                    )));
-            }
-            (true, false) => {
+            .iter()
+            .filter_map(|method| {
+                let (method_transform, hand_data, controller_data, mouse_data) =
+                    method_query.get(*method).ok()?;
+                method_grabbing(handler_gt, hand_data, controller_data, mouse_data)
+                    .then_some((*method, method_transform, mouse_data))
+            })
+            .take(2)
+            .collect();
+
+        let Some(&(first, first_transform, mouse_data)) = holding.first() else {
+            if grabbed.is_some() {
                 cmds.entity(handler_entity).remove::<Grabbed>();
             }
-            _ => {}
+            continue;
+        };
+        let second = holding.get(1).copied();
+
+        // (Re)start the grab whenever the set of holding methods changes
+        let needs_reset = match grabbed.as_deref() {
+            None => true,
+            Some(g) => g.method != first || g.second.map(|s| s.method) != second.map(|s| s.0),
+        };
+        if needs_reset {
+            cmds.entity(handler_entity).insert(Grabbed {
+                method: first,
+                offset: relative_to(first_transform, handler_gt),
+                second: second.map(|(method, second_transform, _)| SecondGrab {
+                    method,
+                    offset: relative_to(second_transform, handler_gt),
+                    start_handler: handler_gt.compute_transform(),
+                    start_first: first_transform.translation(),
+                    start_second: second_transform.translation(),
+                }),
+            });
+            continue;
         }
-        if let Some(mut t) = grabbed {
-            let w = parent
-                .and_then(|v| parent_query.get(v.get()).ok())
-                .copied()
-                .unwrap_or(GlobalTransform::IDENTITY);
-            if let Some(mouse) = mouse_data {
-                t.0.translation.z += mouse.discrete_scroll.y * 0.1;
+
+        let Some(mut t) = grabbed else {
+            continue;
+        };
+        let w = parent
+            .and_then(|v| parent_query.get(v.get()).ok())
+            .copied()
+            .unwrap_or(GlobalTransform::IDENTITY);
+
+        let world = match (t.second, second) {
+            (Some(second_grab), Some((_, second_transform, _))) => second_grab
+                .two_handed_transform(first_transform.translation(), second_transform.translation()),
+            _ => {
+                if let Some(mouse) = mouse_data {
+                    t.offset.translation.z += mouse.discrete_scroll.y * 0.1;
+                }
+                first_transform.mul_transform(t.offset).compute_transform()
             }
+        };
 
-            *handler_transform = Transform::from_matrix(
-                method_transform.mul_transform(t.0).compute_matrix() * w.compute_matrix().inverse(),
-            );
-        }
+        *handler_transform =
+            Transform::from_matrix(w.compute_matrix().inverse() * world.compute_matrix());
     }
 }
 
@@ -99,11 +157,10 @@ pub fn capture_condition(
     )>,
     handler_query: Query<&InputHandlerCaptures>,
 ) -> bool {
-    // Only capture one method
-    if !handler_query
-        .get(ctx.handler)
-        .is_ok_and(|v| v.captured_methods.is_empty())
-    {
+    // Capture at most two methods, the second one is for two handed grabs
+    if !handler_query.get(ctx.handler).is_ok_and(|v| {
+        v.captured_methods.len() < 2 && !v.captured_methods.contains(&ctx.input_method)
+    }) {
         return false;
     }
     let method_distance = ctx
@@ -145,6 +202,27 @@ pub fn capture_condition(
     capture
 }
 
+/// Whether an input method is currently holding on to the handler
+pub fn method_grabbing(
+    handler_gt: &GlobalTransform,
+    hand_data: Option<&HandInputMethodData>,
+    controller_data: Option<&XrControllerInputMethodData>,
+    mouse_data: Option<&MouseInputMethodData>,
+) -> bool {
+    let mut grabbing = false;
+    if let Some(hand) = hand_data {
+        let hand = hand.get_in_relative_space(handler_gt);
+        grabbing |= finger_separation(&hand, GRAB_SEPARATION);
+    }
+    if let Some(controller) = controller_data {
+        grabbing |= controller.squeezed;
+    }
+    if let Some(mouse) = mouse_data {
+        grabbing |= mouse.left_button.pressed;
+    }
+    grabbing
+}
+
 /// Function to check finger separation for grabbing with hands
 pub fn finger_separation(hand: &Hand, max_separation: f32) -> bool {
     hand.thumb.tip.pos.distance(hand.index.tip.pos)
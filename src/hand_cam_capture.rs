@@ -0,0 +1,514 @@
+// Hand cam photos, saved as timestamped PNGs
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{block_on, IoTaskPool, Task};
+use bevy_egui::{egui, EguiUserTextures};
+use bevy_gaussian_splatting::GaussianCamera;
+use bevy_suis::{
+    window_pointers::MouseInputMethodData, xr::HandInputMethodData,
+    xr_controllers::XrControllerInputMethodData, Field, InputHandler, InputHandlerCaptures,
+};
+
+use crate::camera_path::AddCameraKeyframe;
+use crate::file_browser::fs_asset_path;
+use crate::grabbing;
+use crate::hand_cam_lens::HandCamLensSettings;
+use crate::hand_cam_recording::{HandCamRecordToggle, HandCamRecorder, HandCamRecordingSettings};
+use crate::image_readback::{ReadbackComplete, ReadbackRequest};
+use crate::main_menu::HandCamLens;
+use crate::ui_window::WindowContexts;
+
+
+/// Frames the capture camera renders before it is read back, the splats need a few to get sorted
+/// for a new camera
+const CAPTURE_WARMUP_FRAMES: u32 = 5;
+
+#[derive(Resource, Clone, Debug)]
+pub struct HandCamCaptureSettings {
+    pub folder: PathBuf,
+}
+
+impl Default for HandCamCaptureSettings {
+    fn default() -> Self {
+        Self {
+            folder: default_capture_folder(),
+        }
+    }
+}
+
+pub fn default_capture_folder() -> PathBuf {
+    #[cfg(target_os = "android")]
+    return PathBuf::from("/sdcard/Pictures/HandCam");
+
+    #[cfg(not(target_os = "android"))]
+    return std::env::current_dir()
+        .unwrap_or_default()
+        .join("captures");
+}
+
+/// Takes a photo with every hand cam
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct HandCamShutter;
+
+#[derive(Event, Clone, Debug)]
+pub struct HandCamShotSaved(pub PathBuf);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandCamAction {
+    Photo,
+    ToggleRecording,
+    Keyframe,
+}
+
+/// Button on the hand cam body, pinch or squeeze it to run its action
+#[derive(Component)]
+pub struct HandCamButton {
+    pub action: HandCamAction,
+    pressed: bool,
+}
+
+impl HandCamButton {
+    pub fn new(action: HandCamAction) -> Self {
+        Self {
+            action,
+            pressed: false,
+        }
+    }
+}
+
+/// Camera rendering a single shot at the capture resolution, copies the lens it is parented to
+#[derive(Component)]
+struct PendingShot {
+    path: PathBuf,
+    image: Handle<Image>,
+    frames_left: u32,
+}
+
+#[derive(Resource, Default)]
+struct SavingShots(Vec<Task<Result<PathBuf, String>>>);
+
+
+pub struct HandCamCapturePlugin;
+
+impl Plugin for HandCamCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandCamCaptureSettings>()
+            .init_resource::<SavingShots>()
+            .add_event::<HandCamShutter>()
+            .add_event::<HandCamShotSaved>()
+            .add_systems(
+                Update,
+                (
+                    press_hand_cam_buttons,
+                    start_shots,
+                    tick_pending_shots,
+                    save_shots,
+                    poll_saving_shots,
+                    update_gallery_ws,
+                ).chain(),
+            );
+    }
+}
+
+
+pub fn spawn_hand_cam_button(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    action: HandCamAction,
+    translation: Vec3,
+) -> Entity {
+    let (name, color) = match action {
+        HandCamAction::Photo => ("Hand Cam Shutter", Color::srgb(0.9, 0.9, 0.9)),
+        HandCamAction::ToggleRecording => ("Hand Cam Record", Color::srgb(0.9, 0.1, 0.1)),
+        HandCamAction::Keyframe => ("Hand Cam Keyframe", Color::srgb(0.1, 0.3, 0.9)),
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Sphere::new(0.02)),
+            material: materials.add(StandardMaterial {
+                base_color: color,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        Name::new(name),
+        HandCamButton::new(action),
+        InputHandler::new(grabbing::capture_condition),
+        Field::Sphere(0.03),
+    )).id()
+}
+
+/// `handcam_20241018-153000-123.png`, UTC
+pub fn shot_file_name(since_epoch: std::time::Duration) -> String {
+    format!("handcam_{}.png", timestamp(since_epoch))
+}
+
+/// `YYYYMMDD-HHMMSS-mmm` in UTC
+pub fn timestamp(since_epoch: std::time::Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time_of_day = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// Days since 1970-01-01 to a (year, month, day) date, Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn capture_image(images: &mut Assets<Image>, resolution: UVec2) -> Handle<Image> {
+    let size = Extent3d {
+        width: resolution.x.max(1),
+        height: resolution.y.max(1),
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("hand_cam_capture"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    images.add(image)
+}
+
+/// A camera on the lens, rendering into `image` with the lens's projection
+pub fn spawn_lens_copy(
+    commands: &mut Commands,
+    lens: Entity,
+    lens_camera: &Camera,
+    projection: &Projection,
+    image: Handle<Image>,
+) -> Entity {
+    let camera = commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image),
+                clear_color: lens_camera.clear_color.clone(),
+                ..default()
+            },
+            projection: projection.clone(),
+            ..default()
+        },
+        GaussianCamera { warmup: true },
+    )).id();
+    commands.entity(lens).add_child(camera);
+    camera
+}
+
+
+pub fn press_hand_cam_buttons(
+    mut buttons: Query<(&InputHandlerCaptures, &GlobalTransform, &mut HandCamButton)>,
+    method_query: Query<(
+        Option<&HandInputMethodData>,
+        Option<&XrControllerInputMethodData>,
+        Option<&MouseInputMethodData>,
+    )>,
+    mut shutter: EventWriter<HandCamShutter>,
+    mut record: EventWriter<HandCamRecordToggle>,
+    mut keyframe: EventWriter<AddCameraKeyframe>,
+) {
+    for (captures, button_gt, mut button) in buttons.iter_mut() {
+        let pressed = captures.captured_methods.iter().any(|method| {
+            method_query.get(*method).is_ok_and(|(hand, controller, mouse)| {
+                grabbing::method_grabbing(button_gt, hand, controller, mouse)
+            })
+        });
+        if pressed && !button.pressed {
+            match button.action {
+                HandCamAction::Photo => {
+                    shutter.send(HandCamShutter);
+                }
+                HandCamAction::ToggleRecording => {
+                    record.send(HandCamRecordToggle);
+                }
+                HandCamAction::Keyframe => {
+                    keyframe.send(AddCameraKeyframe);
+                }
+            }
+        }
+        if button.pressed != pressed {
+            button.pressed = pressed;
+        }
+    }
+}
+
+fn start_shots(
+    mut shutter: EventReader<HandCamShutter>,
+    settings: Res<HandCamCaptureSettings>,
+    lens_settings: Res<HandCamLensSettings>,
+    lenses: Query<(Entity, &Camera, &Projection), With<HandCamLens>>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    for _ in shutter.read() {
+        if lenses.is_empty() {
+            warn!("shutter pressed without a hand cam");
+        }
+        for (lens, lens_camera, projection) in lenses.iter() {
+            let since_epoch = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            let image = capture_image(&mut images, lens_settings.resolution);
+            let camera = spawn_lens_copy(&mut commands, lens, lens_camera, projection, image.clone());
+            commands.entity(camera).insert((
+                Name::new("Hand Cam Shot"),
+                PendingShot {
+                    path: settings.folder.join(shot_file_name(since_epoch)),
+                    image,
+                    frames_left: CAPTURE_WARMUP_FRAMES,
+                },
+            ));
+        }
+    }
+}
+
+/// Renders the warmup frames, asks for the readback, then stops rendering until it arrives
+fn tick_pending_shots(
+    mut shots: Query<(Entity, &mut PendingShot, &mut Camera, Has<ReadbackRequest>)>,
+    mut commands: Commands,
+) {
+    for (entity, mut shot, mut camera, requested) in shots.iter_mut() {
+        if requested {
+            commands.entity(entity).remove::<ReadbackRequest>();
+            camera.is_active = false;
+        } else if shot.frames_left > 0 {
+            shot.frames_left -= 1;
+        } else if camera.is_active {
+            commands.entity(entity).insert(ReadbackRequest {
+                image: shot.image.clone(),
+            });
+        }
+    }
+}
+
+fn save_shots(
+    mut readbacks: EventReader<ReadbackComplete>,
+    shots: Query<&PendingShot>,
+    mut saving: ResMut<SavingShots>,
+    mut commands: Commands,
+) {
+    for readback in readbacks.read() {
+        let Ok(shot) = shots.get(readback.entity) else {
+            continue;
+        };
+        let path = shot.path.clone();
+        let image = readback.image.clone();
+        saving.0.push(IoTaskPool::get().spawn(async move { save_png(image, &path).map(|_| path) }));
+        commands.entity(readback.entity).despawn_recursive();
+    }
+}
+
+pub fn save_png(image: Image, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let image = image.try_into_dynamic().map_err(|e| e.to_string())?;
+    image.to_rgba8().save(path).map_err(|e| e.to_string())
+}
+
+fn poll_saving_shots(mut saving: ResMut<SavingShots>, mut saved: EventWriter<HandCamShotSaved>) {
+    saving.0.retain_mut(|task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        match result {
+            Ok(path) => {
+                info!("saved hand cam shot {:?}", path);
+                saved.send(HandCamShotSaved(path));
+            }
+            Err(e) => error!("failed to save hand cam shot: {}", e),
+        }
+        false
+    });
+}
+
+
+/// Lists the shots in the capture folder, newest first, with a preview of the picked one
+#[derive(Component, Default)]
+pub struct HandCamGalleryWS {
+    shots: Vec<PathBuf>,
+    needs_refresh: bool,
+    folder_text: String,
+    preview: Option<(PathBuf, Handle<Image>)>,
+}
+
+impl HandCamGalleryWS {
+    pub fn new() -> Self {
+        Self {
+            needs_refresh: true,
+            ..default()
+        }
+    }
+}
+
+fn list_shots(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut shots: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    // Timestamped names sort by age
+    shots.sort();
+    shots.reverse();
+    shots
+}
+
+pub fn update_gallery_ws(
+    mut galleries: Query<(Entity, &mut HandCamGalleryWS)>,
+    mut contexts: WindowContexts,
+    mut settings: ResMut<HandCamCaptureSettings>,
+    mut saved: EventReader<HandCamShotSaved>,
+    mut shutter: EventWriter<HandCamShutter>,
+    mut record: EventWriter<HandCamRecordToggle>,
+    recorder: Res<HandCamRecorder>,
+    mut recording_settings: ResMut<HandCamRecordingSettings>,
+    mut user_textures: ResMut<EguiUserTextures>,
+    asset_server: Res<AssetServer>,
+) {
+    let shot_saved = saved.read().count() > 0;
+    for (entity, mut gallery) in galleries.iter_mut() {
+        if shot_saved || gallery.needs_refresh {
+            gallery.shots = list_shots(&settings.folder);
+            gallery.folder_text = settings.folder.to_string_lossy().to_string();
+            gallery.needs_refresh = false;
+        }
+
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("Hand Cam Gallery")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Take Photo").clicked() {
+                        shutter.send(HandCamShutter);
+                    }
+                    let record_label = if recorder.is_recording() { "Stop Recording" } else { "Record" };
+                    if ui.button(record_label).clicked() {
+                        record.send(HandCamRecordToggle);
+                    }
+                });
+                if recorder.is_recording() {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("REC {} frames", recorder.frames_recorded()),
+                    );
+                } else if let Some(last) = recorder.last_recording() {
+                    ui.label(format!("Saved {}", last.to_string_lossy()));
+                }
+
+                ui.add_enabled_ui(!recorder.is_recording(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Frame rate");
+                        ui.add(egui::DragValue::new(&mut recording_settings.fps).range(1..=120));
+                    });
+                    #[cfg(feature = "gif")]
+                    ui.checkbox(&mut recording_settings.encode_gif, "Also save a GIF");
+                });
+
+                ui.label("Folder");
+                if ui.text_edit_singleline(&mut gallery.folder_text).lost_focus() {
+                    settings.folder = PathBuf::from(&gallery.folder_text);
+                    gallery.needs_refresh = true;
+                }
+
+                if let Some((path, handle)) = &gallery.preview {
+                    let texture = user_textures.add_image(handle.clone());
+                    ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                    ui.add(egui::Image::new((texture, egui::vec2(320.0, 180.0))).maintain_aspect_ratio(true));
+                }
+
+                let mut open = None;
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for shot in gallery.shots.iter() {
+                        let name = shot.file_name().unwrap_or_default().to_string_lossy();
+                        let is_open = gallery.preview.as_ref().is_some_and(|(p, _)| p == shot);
+                        if ui.selectable_label(is_open, name).clicked() {
+                            open = Some(shot.clone());
+                        }
+                    }
+                    if gallery.shots.is_empty() {
+                        ui.label("No shots yet");
+                    }
+                });
+
+                if let Some(path) = open {
+                    if let Some((_, old)) = gallery.preview.take() {
+                        user_textures.remove_image(&old);
+                    }
+                    let handle = asset_server.load(fs_asset_path(&path));
+                    gallery.preview = Some((path, handle));
+                }
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(timestamp(Duration::ZERO), "19700101-000000-000");
+        // 2024-02-29 12:34:56.789
+        assert_eq!(timestamp(Duration::from_millis(1709210096789)), "20240229-123456-789");
+        assert_eq!(
+            shot_file_name(Duration::from_secs(1735689599)),
+            "handcam_20241231-235959-000.png"
+        );
+    }
+
+    #[test]
+    fn shots_sort_newest_first() {
+        let older = shot_file_name(Duration::from_secs(1_700_000_000));
+        let newer = shot_file_name(Duration::from_secs(1_700_000_001));
+        assert!(newer > older);
+    }
+}
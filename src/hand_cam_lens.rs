@@ -0,0 +1,250 @@
+// Hand cam lens settings, shared by the preview screen, photos and recordings
+
+use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, RenderTarget, ScalingMode};
+use bevy::render::render_resource::Extent3d;
+use bevy_egui::egui;
+use bevy_suis::Field;
+
+use crate::main_menu::{HandCamLens, HandCamScreen, HandCamScreenDisplay};
+use crate::ui_window::WindowContexts;
+
+
+/// Longest side of the preview screen's texture, photos and recordings render at the full resolution
+const PREVIEW_MAX_SIZE: u32 = 512;
+/// Height of a full frame sensor, for focal lengths
+const SENSOR_HEIGHT_MM: f32 = 24.0;
+/// Longest side of the preview screen in meters
+const SCREEN_SIZE: f32 = 1.0;
+const SCREEN_DEPTH: f32 = 0.2;
+
+const ASPECT_PRESETS: [(&str, f32); 4] = [
+    ("1:1", 1.0),
+    ("4:3", 4.0 / 3.0),
+    ("16:9", 16.0 / 9.0),
+    ("9:16", 9.0 / 16.0),
+];
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct HandCamLensSettings {
+    /// Vertical field of view in degrees, for the perspective lens
+    pub fov: f32,
+    /// Size of photos and recordings, the preview screen uses the same aspect
+    pub resolution: UVec2,
+    pub orthographic: bool,
+    /// Meters of the scene that fit vertically in the orthographic lens
+    pub ortho_height: f32,
+    /// Clears to transparent instead of black, for compositing
+    pub transparent: bool,
+}
+
+impl Default for HandCamLensSettings {
+    fn default() -> Self {
+        Self {
+            fov: 45.0,
+            resolution: UVec2::new(1920, 1080),
+            orthographic: false,
+            ortho_height: 2.0,
+            transparent: false,
+        }
+    }
+}
+
+impl HandCamLensSettings {
+    pub fn aspect(&self) -> f32 {
+        self.resolution.x.max(1) as f32 / self.resolution.y.max(1) as f32
+    }
+
+    pub fn projection(&self) -> Projection {
+        if self.orthographic {
+            Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical(self.ortho_height),
+                ..default()
+            })
+        } else {
+            Projection::Perspective(PerspectiveProjection {
+                fov: self.fov.to_radians(),
+                aspect_ratio: self.aspect(),
+                ..default()
+            })
+        }
+    }
+
+    pub fn clear_color(&self) -> ClearColorConfig {
+        if self.transparent {
+            ClearColorConfig::Custom(Color::NONE)
+        } else {
+            ClearColorConfig::Custom(Color::BLACK)
+        }
+    }
+}
+
+/// Full frame equivalent focal length of a vertical field of view
+pub fn focal_length_mm(fov_degrees: f32) -> f32 {
+    SENSOR_HEIGHT_MM / 2.0 / (fov_degrees.to_radians() / 2.0).tan()
+}
+
+pub fn fov_from_focal_length(focal_length_mm: f32) -> f32 {
+    (2.0 * (SENSOR_HEIGHT_MM / 2.0 / focal_length_mm).atan()).to_degrees()
+}
+
+/// Preview texture size with the aspect of `resolution`, no bigger than [`PREVIEW_MAX_SIZE`]
+pub fn preview_size(resolution: UVec2) -> UVec2 {
+    let resolution = resolution.max(UVec2::ONE);
+    let scale = (PREVIEW_MAX_SIZE as f32 / resolution.max_element() as f32).min(1.0);
+    (resolution.as_vec2() * scale).round().as_uvec2().max(UVec2::ONE)
+}
+
+/// Preview screen size in meters with the aspect of `resolution`
+pub fn screen_size(resolution: UVec2) -> Vec2 {
+    let resolution = resolution.max(UVec2::ONE).as_vec2();
+    resolution / resolution.max_element() * SCREEN_SIZE
+}
+
+
+pub struct HandCamLensPlugin;
+
+impl Plugin for HandCamLensPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandCamLensSettings>()
+            .add_systems(Update, (update_hand_cam_settings_ws, apply_lens_settings).chain());
+    }
+}
+
+
+/// Resizes the preview in place, the tool hierarchy keeps its handles
+fn apply_lens_settings(
+    settings: Res<HandCamLensSettings>,
+    new_lenses: Query<(), Added<HandCamLens>>,
+    mut lenses: Query<(&mut Camera, &mut Projection), With<HandCamLens>>,
+    mut screens: Query<&mut Field, With<HandCamScreen>>,
+    displays: Query<(&Handle<Mesh>, &Handle<StandardMaterial>), With<HandCamScreenDisplay>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !settings.is_changed() && new_lenses.is_empty() {
+        return;
+    }
+
+    let preview = preview_size(settings.resolution);
+    for (mut camera, mut projection) in lenses.iter_mut() {
+        camera.clear_color = settings.clear_color();
+        *projection = settings.projection();
+
+        let RenderTarget::Image(handle) = &camera.target else {
+            continue;
+        };
+        if let Some(image) = images.get_mut(handle) {
+            let size = Extent3d {
+                width: preview.x,
+                height: preview.y,
+                ..default()
+            };
+            if image.texture_descriptor.size != size {
+                image.resize(size);
+            }
+        }
+    }
+
+    let screen = screen_size(settings.resolution);
+    for mut field in screens.iter_mut() {
+        *field = Field::Cuboid(Cuboid::from_size(screen.extend(SCREEN_DEPTH)));
+    }
+    for (mesh, material) in displays.iter() {
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = Plane3d::new(Vec3::Y, screen / 2.0).mesh().size(screen.x, screen.y).into();
+        }
+        if let Some(material) = materials.get_mut(material) {
+            material.alpha_mode = if settings.transparent { AlphaMode::Blend } else { AlphaMode::Opaque };
+        }
+    }
+}
+
+
+#[derive(Component)]
+pub struct HandCamSettingsWS;
+
+fn update_hand_cam_settings_ws(
+    windows: Query<Entity, With<HandCamSettingsWS>>,
+    mut contexts: WindowContexts,
+    mut settings: ResMut<HandCamLensSettings>,
+) {
+    for entity in windows.iter() {
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("Hand Cam Settings")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                // Edit a copy so dragging doesn't mark the settings changed every frame
+                let mut lens = settings.clone();
+
+                ui.checkbox(&mut lens.orthographic, "Orthographic");
+                if lens.orthographic {
+                    ui.add(egui::Slider::new(&mut lens.ortho_height, 0.1..=10.0).text("Height (m)"));
+                } else {
+                    ui.add(egui::Slider::new(&mut lens.fov, 5.0..=120.0).text("Vertical FOV"));
+                    let mut focal_length = focal_length_mm(lens.fov);
+                    let focal = ui.add(
+                        egui::DragValue::new(&mut focal_length)
+                            .range(10.0..=270.0)
+                            .suffix(" mm"),
+                    );
+                    if focal.changed() {
+                        lens.fov = fov_from_focal_length(focal_length);
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Resolution");
+                    ui.add(egui::DragValue::new(&mut lens.resolution.x).range(16..=4096));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut lens.resolution.y).range(16..=4096));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Aspect");
+                    for (name, aspect) in ASPECT_PRESETS {
+                        if ui.button(name).clicked() {
+                            // Keeps the longest side
+                            let long = lens.resolution.max_element();
+                            lens.resolution = if aspect >= 1.0 {
+                                UVec2::new(long, (long as f32 / aspect).round() as u32)
+                            } else {
+                                UVec2::new((long as f32 * aspect).round() as u32, long)
+                            };
+                        }
+                    }
+                });
+
+                ui.checkbox(&mut lens.transparent, "Transparent background");
+
+                if lens != *settings {
+                    *settings = lens;
+                }
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focal_length_round_trips() {
+        // A 50mm full frame lens sees about 27 degrees vertically
+        assert!((fov_from_focal_length(50.0) - 26.99).abs() < 0.01);
+        for fov in [10.0, 45.0, 90.0] {
+            assert!((fov_from_focal_length(focal_length_mm(fov)) - fov).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn preview_keeps_the_aspect() {
+        assert_eq!(preview_size(UVec2::new(1920, 1080)), UVec2::new(512, 288));
+        assert_eq!(preview_size(UVec2::new(1080, 1920)), UVec2::new(288, 512));
+        assert_eq!(preview_size(UVec2::new(256, 256)), UVec2::new(256, 256));
+        assert_eq!(screen_size(UVec2::new(1920, 1080)), Vec2::new(1.0, 0.5625));
+    }
+}
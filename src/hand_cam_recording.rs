@@ -0,0 +1,424 @@
+// Hand cam recordings, saved as numbered PNG sequences
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{block_on, IoTaskPool, Task};
+use bevy::time::TimeUpdateStrategy;
+
+use crate::hand_cam_capture::{
+    capture_image, save_png, spawn_lens_copy, timestamp, HandCamCaptureSettings,
+};
+use crate::hand_cam_lens::HandCamLensSettings;
+use crate::image_readback::{ReadbackComplete, ReadbackRequest};
+use crate::main_menu::HandCamLens;
+
+
+/// Frames the recording camera renders before the first one is kept
+const RECORDING_WARMUP_FRAMES: u32 = 5;
+/// Frames to wait after stopping for readbacks still in flight
+const RECORDING_DRAIN_FRAMES: u32 = 3;
+/// Frames read back or waiting to be written before the app waits for the disk
+const MAX_SAVING_FRAMES: usize = 16;
+const LIGHT_BLINK_SECS: f32 = 0.5;
+
+#[derive(Resource, Clone, Debug)]
+pub struct HandCamRecordingSettings {
+    /// Playback rate of the sequence, the app is stepped by `1 / fps` per frame while recording
+    pub fps: u32,
+    /// Muxes the sequence into `<folder>.gif` once it is written
+    pub encode_gif: bool,
+}
+
+impl Default for HandCamRecordingSettings {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            encode_gif: false,
+        }
+    }
+}
+
+/// Starts recording the hand cam, or stops the running recording
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct HandCamRecordToggle;
+
+/// Folder of a finished recording, every frame has been written
+#[derive(Event, Clone, Debug)]
+pub struct HandCamRecordingSaved(pub PathBuf);
+
+/// Blinks on the hand cam model while recording
+#[derive(Component)]
+pub struct HandCamRecordingLight;
+
+struct Recording {
+    camera: Entity,
+    image: Handle<Image>,
+    folder: PathBuf,
+    fps: u32,
+    encode_gif: bool,
+    warmup_left: u32,
+    /// Frames rendered with the readback on, the ones not in `frames` yet are still on the GPU
+    requested: u32,
+    frames: u32,
+    saving: Vec<Task<Result<(), String>>>,
+    /// Frames left to drain once stopped
+    stopping: Option<u32>,
+}
+
+#[derive(Resource, Default)]
+pub struct HandCamRecorder {
+    recording: Option<Recording>,
+    last_recording: Option<PathBuf>,
+    encoding: Vec<Task<Result<PathBuf, String>>>,
+}
+
+impl HandCamRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.as_ref().is_some_and(|r| r.stopping.is_none())
+    }
+
+    pub fn frames_recorded(&self) -> u32 {
+        self.recording.as_ref().map_or(0, |r| r.frames)
+    }
+
+    pub fn last_recording(&self) -> Option<&Path> {
+        self.last_recording.as_deref()
+    }
+}
+
+
+pub struct HandCamRecordingPlugin;
+
+impl Plugin for HandCamRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandCamRecordingSettings>()
+            .init_resource::<HandCamRecorder>()
+            .add_event::<HandCamRecordToggle>()
+            .add_event::<HandCamRecordingSaved>()
+            .add_systems(
+                Update,
+                (
+                    toggle_recording,
+                    tick_recording,
+                    save_recorded_frames,
+                    poll_encoding,
+                    blink_recording_lights,
+                ).chain(),
+            );
+    }
+}
+
+
+pub fn spawn_recording_light(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    translation: Vec3,
+) -> Entity {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Sphere::new(0.008)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 0.0, 0.0),
+                emissive: LinearRgba::rgb(4.0, 0.0, 0.0),
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(translation),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Name::new("Hand Cam Recording Light"),
+        HandCamRecordingLight,
+    )).id()
+}
+
+/// `frame_000042.png`, zero padded so the files sort in order
+pub fn frame_file_name(frame: u32) -> String {
+    format!("frame_{:06}.png", frame)
+}
+
+/// Recorded frames in `folder`, in order
+pub fn frame_paths(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut frames: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("frame_") && name.ends_with(".png"))
+        })
+        .collect();
+    frames.sort();
+    frames
+}
+
+#[cfg(feature = "gif")]
+fn encode_gif(folder: &Path, fps: u32) -> Result<PathBuf, String> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame};
+
+    let path = folder.with_extension("gif");
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut encoder = GifEncoder::new(std::io::BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+    let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+    for frame in frame_paths(folder) {
+        let pixels = image::open(&frame).map_err(|e| e.to_string())?.to_rgba8();
+        encoder
+            .encode_frame(Frame::from_parts(pixels, 0, 0, delay))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(path)
+}
+
+
+fn toggle_recording(
+    mut toggles: EventReader<HandCamRecordToggle>,
+    mut recorder: ResMut<HandCamRecorder>,
+    capture_settings: Res<HandCamCaptureSettings>,
+    settings: Res<HandCamRecordingSettings>,
+    lens_settings: Res<HandCamLensSettings>,
+    lenses: Query<(Entity, &Camera, &Projection), With<HandCamLens>>,
+    mut cameras: Query<&mut Camera, Without<HandCamLens>>,
+    mut images: ResMut<Assets<Image>>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut commands: Commands,
+) {
+    for _ in toggles.read() {
+        if let Some(recording) = recorder.recording.as_mut() {
+            if recording.stopping.is_none() {
+                recording.stopping = Some(RECORDING_DRAIN_FRAMES);
+                commands.entity(recording.camera).remove::<ReadbackRequest>();
+                if let Ok(mut camera) = cameras.get_mut(recording.camera) {
+                    camera.is_active = false;
+                }
+                *time_strategy = TimeUpdateStrategy::Automatic;
+                info!("stopped recording after {} frames", recording.frames);
+            }
+            continue;
+        }
+
+        let Some((lens, lens_camera, projection)) = lenses.iter().next() else {
+            warn!("record pressed without a hand cam");
+            continue;
+        };
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let folder = capture_settings
+            .folder
+            .join(format!("handcam_{}", timestamp(since_epoch)));
+        let image = capture_image(&mut images, lens_settings.resolution);
+        let camera = spawn_lens_copy(&mut commands, lens, lens_camera, projection, image.clone());
+        commands.entity(camera).insert(Name::new("Hand Cam Recording"));
+
+        // Every frame is one frame of the recording however long it took to render and save
+        let fps = settings.fps.max(1);
+        *time_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / fps as f64));
+        info!("recording hand cam to {:?}", folder);
+
+        recorder.recording = Some(Recording {
+            camera,
+            image,
+            folder,
+            fps,
+            encode_gif: settings.encode_gif,
+            warmup_left: RECORDING_WARMUP_FRAMES,
+            requested: 0,
+            frames: 0,
+            saving: Vec::new(),
+            stopping: None,
+        });
+    }
+}
+
+fn tick_recording(
+    mut recorder: ResMut<HandCamRecorder>,
+    cameras: Query<(), With<Camera>>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut saved: EventWriter<HandCamRecordingSaved>,
+    mut commands: Commands,
+) {
+    let recorder = recorder.as_mut();
+    let Some(recording) = recorder.recording.as_mut() else {
+        return;
+    };
+
+    recording.saving.retain_mut(|task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        if let Err(e) = result {
+            error!("failed to save recorded frame: {}", e);
+        }
+        false
+    });
+
+    // The hand cam was put away mid recording
+    if !cameras.contains(recording.camera) && recording.stopping.is_none() {
+        recording.stopping = Some(0);
+        *time_strategy = TimeUpdateStrategy::Automatic;
+    }
+
+    let finished = match recording.stopping.as_mut() {
+        None => {
+            if recording.warmup_left > 0 {
+                recording.warmup_left -= 1;
+                if recording.warmup_left == 0 {
+                    commands.entity(recording.camera).insert(ReadbackRequest {
+                        image: recording.image.clone(),
+                    });
+                }
+            }
+            if recording.warmup_left == 0 {
+                recording.requested += 1;
+                // Time is stepped per frame while recording, waiting on the disk here only slows
+                // the app down, every frame is still kept and the recording stays smooth
+                let in_flight = recording.requested.saturating_sub(recording.frames) as usize;
+                while !recording.saving.is_empty()
+                    && recording.saving.len() + in_flight > MAX_SAVING_FRAMES
+                {
+                    if let Err(e) = block_on(recording.saving.remove(0)) {
+                        error!("failed to save recorded frame: {}", e);
+                    }
+                }
+            }
+            false
+        }
+        Some(frames_left) if *frames_left > 0 => {
+            *frames_left -= 1;
+            false
+        }
+        Some(_) => recording.saving.is_empty(),
+    };
+    if !finished {
+        return;
+    }
+
+    let Some(recording) = recorder.recording.take() else {
+        return;
+    };
+    if let Some(camera) = commands.get_entity(recording.camera) {
+        camera.despawn_recursive();
+    }
+
+    #[cfg(feature = "gif")]
+    if recording.encode_gif && recording.frames > 0 {
+        let folder = recording.folder.clone();
+        let fps = recording.fps;
+        recorder.encoding.push(IoTaskPool::get().spawn(async move { encode_gif(&folder, fps) }));
+    }
+    #[cfg(not(feature = "gif"))]
+    if recording.encode_gif {
+        warn!("built without the gif feature, only the png sequence was saved");
+    }
+
+    info!("saved {} frames at {} fps to {:?}", recording.frames, recording.fps, recording.folder);
+    saved.send(HandCamRecordingSaved(recording.folder.clone()));
+    recorder.last_recording = Some(recording.folder);
+}
+
+fn save_recorded_frames(
+    mut readbacks: EventReader<ReadbackComplete>,
+    mut recorder: ResMut<HandCamRecorder>,
+) {
+    let Some(recording) = recorder.recording.as_mut() else {
+        readbacks.clear();
+        return;
+    };
+    for readback in readbacks.read() {
+        if readback.entity != recording.camera {
+            continue;
+        }
+        let path = recording.folder.join(frame_file_name(recording.frames));
+        recording.frames += 1;
+        let image = readback.image.clone();
+        recording.saving.push(IoTaskPool::get().spawn(async move { save_png(image, &path) }));
+    }
+}
+
+fn poll_encoding(mut recorder: ResMut<HandCamRecorder>) {
+    recorder.encoding.retain_mut(|task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        match result {
+            Ok(path) => info!("saved recording to {:?}", path),
+            Err(e) => error!("failed to encode recording: {}", e),
+        }
+        false
+    });
+}
+
+fn blink_recording_lights(
+    recorder: Res<HandCamRecorder>,
+    time: Res<Time<Real>>,
+    mut lights: Query<&mut Visibility, With<HandCamRecordingLight>>,
+) {
+    let on = recorder.is_recording()
+        && (time.elapsed_seconds() / LIGHT_BLINK_SECS) as u32 % 2 == 0;
+    let visibility = if on { Visibility::Inherited } else { Visibility::Hidden };
+    for mut light in lights.iter_mut() {
+        if *light != visibility {
+            *light = visibility;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_sort_in_order() {
+        assert_eq!(frame_file_name(42), "frame_000042.png");
+        assert!(frame_file_name(9) < frame_file_name(10));
+        assert!(frame_file_name(99_999) < frame_file_name(100_000));
+    }
+
+    #[test]
+    fn recording_steps_time_per_frame() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_event::<ReadbackComplete>()
+            .init_resource::<HandCamCaptureSettings>()
+            .init_resource::<HandCamLensSettings>()
+            .add_plugins(HandCamRecordingPlugin);
+        app.world_mut().spawn((
+            Camera3dBundle::default(),
+            HandCamLens,
+        ));
+        app.update();
+
+        app.world_mut().send_event(HandCamRecordToggle);
+        app.update();
+        assert!(app.world().resource::<HandCamRecorder>().is_recording());
+        assert!(matches!(
+            app.world().resource::<TimeUpdateStrategy>(),
+            TimeUpdateStrategy::ManualDuration(step) if *step == Duration::from_secs_f64(1.0 / 30.0)
+        ));
+
+        app.world_mut().send_event(HandCamRecordToggle);
+        app.update();
+        assert!(!app.world().resource::<HandCamRecorder>().is_recording());
+        assert!(matches!(
+            app.world().resource::<TimeUpdateStrategy>(),
+            TimeUpdateStrategy::Automatic
+        ));
+
+        for _ in 0..RECORDING_DRAIN_FRAMES + 2 {
+            app.update();
+        }
+        let recorder = app.world().resource::<HandCamRecorder>();
+        assert!(recorder.recording.is_none());
+        assert!(recorder.last_recording().is_some());
+    }
+}
@@ -0,0 +1,249 @@
+// Copies render target images back from the GPU
+
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::render::graph::CameraDriverLabel;
+use bevy::render::render_asset::{RenderAssetUsages, RenderAssets};
+use bevy::render::render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel};
+use bevy::render::render_resource::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, Texture, TextureDimension, TextureFormat,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::texture::{GpuImage, TextureFormatPixelInfo};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+
+
+/// Reads `image` back after every frame rendered while this is on the entity, the pixels arrive as
+/// [`ReadbackComplete`]s for this entity once the gpu has finished the copy, usually a frame or
+/// two later, in order. Remove it after one frame for a single image.
+#[derive(Component, Clone, Debug)]
+pub struct ReadbackRequest {
+    pub image: Handle<Image>,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ReadbackComplete {
+    /// Entity that had the [`ReadbackRequest`]
+    pub entity: Entity,
+    pub image: Image,
+}
+
+
+pub struct ImageReadbackPlugin;
+
+impl Plugin for ImageReadbackPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        app.add_event::<ReadbackComplete>()
+            .insert_resource(ReadbackReceiver(Mutex::new(receiver)))
+            .add_systems(First, receive_readbacks);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(ReadbackSender(sender))
+            .init_resource::<ExtractedReadbacks>()
+            .init_resource::<PreparedReadbacks>()
+            .init_resource::<PendingReadbacks>()
+            .add_systems(ExtractSchedule, extract_readback_requests)
+            .add_systems(
+                Render,
+                (
+                    prepare_readbacks.in_set(RenderSet::PrepareResources),
+                    (map_readbacks, send_mapped_readbacks)
+                        .chain()
+                        .after(RenderSet::Render)
+                        .before(RenderSet::Cleanup),
+                ),
+            );
+
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(ReadbackLabel, ReadbackNode);
+        graph.add_node_edge(CameraDriverLabel, ReadbackLabel);
+    }
+}
+
+
+#[derive(Resource)]
+struct ReadbackReceiver(Mutex<Receiver<ReadbackComplete>>);
+
+#[derive(Resource)]
+struct ReadbackSender(Sender<ReadbackComplete>);
+
+#[derive(Resource, Default)]
+struct ExtractedReadbacks(Vec<(Entity, Handle<Image>)>);
+
+struct PreparedReadback {
+    entity: Entity,
+    texture: Texture,
+    buffer: Buffer,
+    size: UVec2,
+    format: TextureFormat,
+    padded_bytes_per_row: usize,
+}
+
+#[derive(Resource, Default)]
+struct PreparedReadbacks(Vec<PreparedReadback>);
+
+/// Copied readbacks waiting for their buffer to be mapped
+struct PendingReadback {
+    readback: PreparedReadback,
+    mapped: Receiver<Result<(), BufferAsyncError>>,
+}
+
+#[derive(Resource, Default)]
+struct PendingReadbacks(Vec<PendingReadback>);
+
+
+fn receive_readbacks(receiver: Res<ReadbackReceiver>, mut events: EventWriter<ReadbackComplete>) {
+    let Ok(receiver) = receiver.0.lock() else {
+        return;
+    };
+    events.send_batch(receiver.try_iter());
+}
+
+fn extract_readback_requests(
+    mut extracted: ResMut<ExtractedReadbacks>,
+    requests: Extract<Query<(Entity, &ReadbackRequest)>>,
+) {
+    extracted.0 = requests
+        .iter()
+        .map(|(entity, request)| (entity, request.image.clone()))
+        .collect();
+}
+
+fn prepare_readbacks(
+    extracted: Res<ExtractedReadbacks>,
+    images: Res<RenderAssets<GpuImage>>,
+    device: Res<RenderDevice>,
+    mut prepared: ResMut<PreparedReadbacks>,
+) {
+    prepared.0.clear();
+    for (entity, handle) in extracted.0.iter() {
+        let Some(gpu_image) = images.get(handle) else {
+            warn!("readback image for {:?} isn't on the gpu yet", entity);
+            continue;
+        };
+        let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(
+            gpu_image.size.x as usize * gpu_image.texture_format.pixel_size(),
+        );
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("image_readback_buffer"),
+            size: (padded_bytes_per_row * gpu_image.size.y as usize) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        prepared.0.push(PreparedReadback {
+            entity: *entity,
+            texture: gpu_image.texture.clone(),
+            buffer,
+            size: gpu_image.size,
+            format: gpu_image.texture_format,
+            padded_bytes_per_row,
+        });
+    }
+}
+
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct ReadbackLabel;
+
+#[derive(Default)]
+struct ReadbackNode;
+
+impl render_graph::Node for ReadbackNode {
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        for readback in world.resource::<PreparedReadbacks>().0.iter() {
+            render_context.command_encoder().copy_texture_to_buffer(
+                readback.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &readback.buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(readback.padded_bytes_per_row as u32),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: readback.size.x,
+                    height: readback.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+
+/// Starts mapping the buffers copied into this frame, they are read once the gpu is done with them
+fn map_readbacks(mut prepared: ResMut<PreparedReadbacks>, mut pending: ResMut<PendingReadbacks>) {
+    for readback in prepared.0.drain(..) {
+        let (mapped_sender, mapped) = channel();
+        readback.buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let _ = mapped_sender.send(result);
+        });
+        pending.0.push(PendingReadback { readback, mapped });
+    }
+}
+
+/// Sends the pixels of every mapped buffer to the main world without the row padding, without
+/// waiting on the ones that aren't mapped yet
+fn send_mapped_readbacks(
+    mut pending: ResMut<PendingReadbacks>,
+    device: Res<RenderDevice>,
+    sender: Res<ReadbackSender>,
+) {
+    if pending.0.is_empty() {
+        return;
+    }
+    device.poll(Maintain::Poll);
+
+    pending.0.retain(|PendingReadback { readback, mapped }| {
+        match mapped.try_recv() {
+            Err(TryRecvError::Empty) => return true,
+            Ok(Ok(())) => {}
+            Ok(Err(_)) | Err(TryRecvError::Disconnected) => {
+                error!("failed to map readback buffer for {:?}", readback.entity);
+                return false;
+            }
+        }
+
+        let row_bytes = readback.size.x as usize * readback.format.pixel_size();
+        let data: Vec<u8> = readback
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(readback.padded_bytes_per_row)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect();
+        readback.buffer.unmap();
+
+        let image = Image::new(
+            Extent3d {
+                width: readback.size.x,
+                height: readback.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            readback.format,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let _ = sender.0.send(ReadbackComplete {
+            entity: readback.entity,
+            image,
+        });
+        false
+    });
+}
@@ -2,6 +2,8 @@ use bevy::prelude::*;
 use bevy_egui::EguiRenderToTextureHandle;
 use bevy_inspector_egui::bevy_inspector::hierarchy::SelectedEntities;
 
+use crate::ui_window::world_window_context;
+
 
 
 #[derive(Component, Reflect, Default)]
@@ -12,19 +14,19 @@ pub fn update_inspector_ws(
     world: &mut World,
     mut selected_entities: Local<SelectedEntities>,
 ) {
-    let Ok(egui_context) = world
-        .query_filtered::<&mut bevy_egui::EguiContext, With<InspectorWSMenu>>()
+    let Ok(inspector) = world
+        .query_filtered::<Entity, With<InspectorWSMenu>>()
         .get_single(world)
         else {
             return;
         };
-
-    let mut egui_context_2 = egui_context.clone();
+    let Some(ui_ctx) = world_window_context(world, inspector) else {
+        return;
+    };
     
-    bevy_egui::egui::Window::new("other ui")
+    ui_ctx.window("other ui")
     .scroll(true)
-    .movable(false)
-    .show(egui_context_2.get_mut(), |ui| {
+    .show(&ui_ctx.ctx, |ui| {
         ui.heading("Hierarchy");
 
         bevy_inspector_egui::bevy_inspector::hierarchy::hierarchy_ui(
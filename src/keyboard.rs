@@ -3,156 +3,397 @@ use bevy::input::ButtonState;
 use bevy::prelude::*;
 
 use bevy::window::PrimaryWindow;
-use bevy_egui::EguiContext;
-use bevy_spatial_egui::SpawnSpatialEguiWindowCommand;
-use get_egui_keys::{first_row, fn_row, number_row, second_row, third_row};
+use bevy_egui::{egui, EguiContext, EguiInput, EguiRenderToTextureHandle};
 use bevy_egui::egui::{Key, Ui, WidgetText};
 use bevy_egui::systems::bevy_to_egui_physical_key;
 
-#[derive(Clone, Copy, Default)]
+use crate::keyboard_layout::KeyboardLayouts;
+use crate::ui_window::{spawn_ui_window, UiMode};
+use crate::window_placement::NoCloseButton;
+
+/// Seconds a key has to be held before it starts repeating
+pub const REPEAT_DELAY: f32 = 0.5;
+/// Seconds between repeats while held
+pub const REPEAT_INTERVAL: f32 = 0.05;
+
+#[derive(Clone, Copy, Default, Debug)]
 pub struct ModifierState {
 	caps_lock: bool,
+	/// Shift, ctrl and alt are one shot, released again after the next key
+	shift: bool,
+	ctrl: bool,
+	alt: bool,
 }
 
-#[derive(Clone, Debug)]
+impl ModifierState {
+	pub fn is_active(&self, modifier: ModifierKey) -> bool {
+		match modifier {
+			ModifierKey::Shift => self.shift,
+			ModifierKey::Ctrl => self.ctrl,
+			ModifierKey::Alt => self.alt,
+			ModifierKey::CapsLock => self.caps_lock,
+		}
+	}
+
+	fn toggle(&mut self, modifier: ModifierKey) -> bool {
+		let state = match modifier {
+			ModifierKey::Shift => &mut self.shift,
+			ModifierKey::Ctrl => &mut self.ctrl,
+			ModifierKey::Alt => &mut self.alt,
+			ModifierKey::CapsLock => &mut self.caps_lock,
+		};
+		*state = !*state;
+		*state
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModifierKey {
+	Shift,
+	Ctrl,
+	Alt,
+	CapsLock,
+}
+
+impl ModifierKey {
+	pub const ONE_SHOT: [ModifierKey; 3] = [ModifierKey::Shift, ModifierKey::Ctrl, ModifierKey::Alt];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			ModifierKey::Shift => "Shift",
+			ModifierKey::Ctrl => "Ctrl",
+			ModifierKey::Alt => "Alt",
+			ModifierKey::CapsLock => "Caps Lock",
+		}
+	}
+
+	pub fn key_code(&self) -> KeyCode {
+		match self {
+			ModifierKey::Shift => KeyCode::ShiftLeft,
+			ModifierKey::Ctrl => KeyCode::ControlLeft,
+			ModifierKey::Alt => KeyCode::AltLeft,
+			ModifierKey::CapsLock => KeyCode::CapsLock,
+		}
+	}
+
+	pub fn logical_key(&self) -> bevy::input::keyboard::Key {
+		match self {
+			ModifierKey::Shift => bevy::input::keyboard::Key::Shift,
+			ModifierKey::Ctrl => bevy::input::keyboard::Key::Control,
+			ModifierKey::Alt => bevy::input::keyboard::Key::Alt,
+			ModifierKey::CapsLock => bevy::input::keyboard::Key::CapsLock,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum KeyValue {
 	Key(Key, bevy::input::keyboard::Key),
-	CharKey(String, Key),
+	/// The character typed, and the character typed with shift held
+	CharKey(String, String),
+	Modifier(ModifierKey),
 }
 
 impl KeyValue {
 	pub fn symbol_or_name(&self, modifier_state: &ModifierState) -> String {
 		match self {
+			KeyValue::Key(key, _) => key.symbol_or_name().to_string(),
+			KeyValue::CharKey(char, shifted) => char_key_text(char, shifted, modifier_state),
+			KeyValue::Modifier(modifier) => modifier.name().to_string(),
+		}
+	}
+
+	/// egui key for shortcuts, character keys only have one if egui knows the character
+	pub fn egui_key(&self) -> Option<Key> {
+		match self {
+			KeyValue::Key(key, _) => Some(*key),
+			KeyValue::CharKey(char, _) => Key::from_name(char),
+			KeyValue::Modifier(_) => None,
+		}
+	}
 
-			KeyValue::Key(key, logic_key) => key.symbol_or_name().to_string(),
-			KeyValue::CharKey(char, _) => match modifier_state.caps_lock {
-				true => char.to_uppercase(),
-				false => char.to_lowercase(),
+	pub fn key_code(&self) -> KeyCode {
+		match self {
+			KeyValue::Modifier(modifier) => modifier.key_code(),
+			_ => self
+				.egui_key()
+				.map(convert_egui_key)
+				.unwrap_or(KeyCode::Unidentified(bevy::input::keyboard::NativeKeyCode::Unidentified)),
+		}
+	}
+}
+
+/// The text a character key types. Letters are upper case when exactly one of shift and caps
+/// lock is active, everything else types the shifted character while shift is held.
+pub fn char_key_text(char: &str, shifted: &str, modifier_state: &ModifierState) -> String {
+	let is_letter = char.chars().all(char::is_alphabetic) && char.to_uppercase() == shifted;
+	let use_shifted = match is_letter {
+		true => modifier_state.caps_lock != modifier_state.shift,
+		false => modifier_state.shift,
+	};
+	match use_shifted {
+		true => shifted.to_string(),
+		false => char.to_string(),
+	}
+}
+
+/// [`char_key_text`] for the US layout
+pub fn apply_modifiers(char: &str, modifier_state: &ModifierState) -> String {
+	let shifted = shifted_symbol(char)
+		.map(str::to_string)
+		.unwrap_or_else(|| char.to_uppercase());
+	char_key_text(&char.to_lowercase(), &shifted, modifier_state)
+}
+
+/// US layout shifted symbols
+pub fn shifted_symbol(char: &str) -> Option<&'static str> {
+	let shifted = match char {
+		"`" => "~",
+		"1" => "!",
+		"2" => "@",
+		"3" => "#",
+		"4" => "$",
+		"5" => "%",
+		"6" => "^",
+		"7" => "&",
+		"8" => "*",
+		"9" => "(",
+		"0" => ")",
+		"-" => "_",
+		"=" => "+",
+		"[" => "{",
+		"]" => "}",
+		"\\" => "|",
+		";" => ":",
+		"'" => "\"",
+		"," => "<",
+		"." => ">",
+		"/" => "?",
+		_ => return None,
+	};
+	Some(shifted)
+}
+
+/// A key event produced by the keyboard, dispatched by [`update_keyboard_ws`]
+#[derive(Clone, Debug)]
+pub struct KeyStroke {
+	pub key_code: KeyCode,
+	pub logical_key: bevy::input::keyboard::Key,
+	pub state: ButtonState,
+	pub repeat: bool,
+	pub egui_key: Option<Key>,
+	/// Text typed by the press, if any
+	pub text: Option<String>,
+}
+
+impl KeyStroke {
+	fn modifier(modifier: ModifierKey, pressed: bool) -> Self {
+		Self {
+			key_code: modifier.key_code(),
+			logical_key: modifier.logical_key(),
+			state: match pressed {
+				true => ButtonState::Pressed,
+				false => ButtonState::Released,
 			},
+			repeat: false,
+			egui_key: None,
+			text: None,
+		}
+	}
+
+	fn pressed(key: &KeyValue, modifier_state: &ModifierState, repeat: bool) -> Self {
+		// Ctrl and Alt turn keys into shortcuts rather than text
+		let types_text = !modifier_state.ctrl && !modifier_state.alt;
+		let (logical_key, text) = match key {
+			KeyValue::Key(key, logic_key) => (
+				logic_key.clone(),
+				(*key == Key::Space && types_text).then(|| " ".to_string()),
+			),
+			KeyValue::CharKey(char, shifted) => {
+				let char = char_key_text(char, shifted, modifier_state);
+				(
+					bevy::input::keyboard::Key::Character(char.clone().into()),
+					types_text.then_some(char),
+				)
+			}
+			KeyValue::Modifier(modifier) => (modifier.logical_key(), None),
+		};
+		Self {
+			key_code: key.key_code(),
+			logical_key,
+			state: ButtonState::Pressed,
+			repeat,
+			egui_key: key.egui_key(),
+			text,
+		}
+	}
+
+	/// The matching release for a press
+	fn released(&self) -> Self {
+		Self {
+			state: ButtonState::Released,
+			repeat: false,
+			text: None,
+			..self.clone()
 		}
 	}
 }
 
+/// The key currently held down on the keyboard, for hold to repeat
+#[derive(Default)]
+pub struct KeyRepeat {
+	held: Option<(KeyValue, KeyStroke)>,
+	held_for: f32,
+	next_repeat: f32,
+}
+
 pub fn draw_keyboard(
 	ui: &mut Ui,
-	primary_window: Entity,
-	previously_pressed: &mut Local<Option<KeyValue>>,
-	keyboard_writer: &mut EventWriter<KeyboardInput>,
-	char_writer: &mut EventWriter<ReceivedCharacter>,
+	layouts: &mut KeyboardLayouts,
+	key_repeat: &mut KeyRepeat,
 	modifier_state: &mut ModifierState,
-) {
+	delta_seconds: f32,
+) -> Vec<KeyStroke> {
+	let mut strokes = Vec::new();
+
+	let mut current = layouts.current;
+	egui::ComboBox::from_label("Layout")
+		.selected_text(layouts.current().name.clone())
+		.show_ui(ui, |ui| {
+			for (i, layout) in layouts.layouts.iter().enumerate() {
+				ui.selectable_value(&mut current, i, &layout.name);
+			}
+		});
+	layouts.current = current;
+
 	let curr_modifier_state = *modifier_state;
-	let mut key_pressed = None;
-	ui.horizontal(|ui| {
-		show_row(ui, fn_row(), &mut key_pressed, &curr_modifier_state);
-	});
-	ui.horizontal(|ui| {
-		show_row(ui, number_row(), &mut key_pressed, &curr_modifier_state)
-	});
-	ui.horizontal(|ui| {
-		show_row(ui, first_row(), &mut key_pressed, &curr_modifier_state);
-	});
-	ui.horizontal(|ui| {
-		if ui
-			.checkbox(&mut modifier_state.caps_lock, "Caps Lock")
-			.clicked()
-		{
-			let button_state = match modifier_state.caps_lock {
-				true => ButtonState::Pressed,
-				false => ButtonState::Released,
-			};
-			keyboard_writer.send(KeyboardInput {
-				key_code: KeyCode::CapsLock,
-				logical_key: bevy::input::keyboard::Key::CapsLock,
-				state: button_state,
-				window: primary_window,
-			});
+	let mut key_held = None;
+	let mut modifier_clicked = None;
+	for row in layouts.current().rows.iter() {
+		ui.horizontal(|ui| {
+			show_row(ui, row, &mut key_held, &mut modifier_clicked, &curr_modifier_state);
+		});
+	}
+
+	if let Some(modifier) = modifier_clicked {
+		let pressed = modifier_state.toggle(modifier);
+		strokes.push(KeyStroke::modifier(modifier, pressed));
+	}
+
+	let held = key_repeat.held.take();
+	match (held, key_held) {
+		(Some((held_key, stroke)), Some(key)) if held_key == key => {
+			key_repeat.held_for += delta_seconds;
+			while key_repeat.held_for >= key_repeat.next_repeat {
+				strokes.push(KeyStroke::pressed(&key, &curr_modifier_state, true));
+				key_repeat.next_repeat += REPEAT_INTERVAL;
+			}
+			key_repeat.held = Some((held_key, stroke));
 		}
-		show_row(ui, second_row(), &mut key_pressed, &curr_modifier_state);
-	});
-	ui.horizontal(|ui| {
-		show_row(ui, third_row(), &mut key_pressed, &curr_modifier_state);
-	});
-
-	if let Some(key) = key_pressed {
-		match key.clone() {
-
-			KeyValue::Key(key_og, logic_key) => {
-				let key = convert_egui_key(key_og);
-				keyboard_writer.send(KeyboardInput {
-					key_code: key,
-					logical_key: logic_key,
-					state: ButtonState::Pressed,
-					window: primary_window,
-				});
+		(held, key) => {
+			if let Some((_, stroke)) = held {
+				strokes.push(stroke.released());
+				// One shot modifiers let go once the key they modified is released
+				for modifier in ModifierKey::ONE_SHOT {
+					if modifier_state.is_active(modifier) {
+						modifier_state.toggle(modifier);
+						strokes.push(KeyStroke::modifier(modifier, false));
+					}
+				}
 			}
-			KeyValue::CharKey(mut char, key) => {
-
-				let char = match curr_modifier_state.caps_lock {
-					true => char.to_uppercase(),
-					false => char.to_lowercase(),
-				};
-
-				let key = convert_egui_key(key);
-				keyboard_writer.send(KeyboardInput {
-					key_code: key,
-					logical_key: bevy::input::keyboard::Key::Character(char.into()),
-					state: ButtonState::Pressed,
-					window: primary_window,
-				});
+			if let Some(key) = key {
+				let stroke = KeyStroke::pressed(&key, &curr_modifier_state, false);
+				strokes.push(stroke.clone());
+				key_repeat.held = Some((key, stroke));
+				key_repeat.held_for = 0.0;
+				key_repeat.next_repeat = REPEAT_DELAY;
 			}
 		}
-		previously_pressed.replace(key);
 	}
+
+	strokes
 }
 
 fn show_row(
 	ui: &mut Ui,
-	row: Vec<KeyValue>,
-	key_code: &mut Option<KeyValue>,
+	row: &[KeyValue],
+	key_held: &mut Option<KeyValue>,
+	modifier_clicked: &mut Option<ModifierKey>,
 	modifier_state: &ModifierState,
 ) {
 	for key in row {
-		if let Some(key) = print_key(ui, key, modifier_state) {
-			key_code.replace(key);
+		let response = print_key(ui, key, modifier_state);
+		match key {
+			KeyValue::Modifier(modifier) => {
+				if response.clicked() {
+					modifier_clicked.replace(*modifier);
+				}
+			}
+			_ => {
+				if response.is_pointer_button_down_on() {
+					key_held.replace(key.clone());
+				}
+			}
 		}
 	}
 }
 
 fn print_key(
 	ui: &mut Ui,
-	key: KeyValue,
+	key: &KeyValue,
 	modifier_state: &ModifierState,
-) -> Option<KeyValue> {
+) -> egui::Response {
 	let text: WidgetText = key.symbol_or_name(modifier_state).into();
-	match ui.button(text.monospace()).clicked() {
-		true => Some(key),
-		false => None,
+	let mut button = egui::Button::new(text.monospace());
+	match key {
+		KeyValue::Key(Key::Space, _) => {
+			button = button.min_size(egui::vec2(160.0, 0.0));
+		}
+		KeyValue::Modifier(modifier) => {
+			button = button.selected(modifier_state.is_active(*modifier));
+		}
+		_ => {}
 	}
+	ui.add(button)
 }
 
-fn convert_egui_key(key: bevy_egui::egui::Key) -> KeyCode {
+/// Physical key for an egui key. Symbols that need shift on a US layout map to the key they are
+/// typed with.
+pub fn convert_egui_key(key: bevy_egui::egui::Key) -> KeyCode {
 	match key {
+		Key::ArrowDown => KeyCode::ArrowDown,
+		Key::ArrowLeft => KeyCode::ArrowLeft,
+		Key::ArrowRight => KeyCode::ArrowRight,
+		Key::ArrowUp => KeyCode::ArrowUp,
 		Key::Escape => KeyCode::Escape,
 		Key::Tab => KeyCode::Tab,
 		Key::Backspace => KeyCode::Backspace,
 		Key::Enter => KeyCode::Enter,
 		Key::Space => KeyCode::Space,
+		Key::Insert => KeyCode::Insert,
 		Key::Delete => KeyCode::Delete,
-		Key::Colon => todo!(),
+		Key::Home => KeyCode::Home,
+		Key::End => KeyCode::End,
+		Key::PageUp => KeyCode::PageUp,
+		Key::PageDown => KeyCode::PageDown,
+		Key::Copy => KeyCode::Copy,
+		Key::Cut => KeyCode::Cut,
+		Key::Paste => KeyCode::Paste,
+		Key::Colon => KeyCode::Semicolon,
 		Key::Comma => KeyCode::Comma,
 		Key::Backslash => KeyCode::Backslash,
 		Key::Slash => KeyCode::Slash,
-		Key::Pipe => todo!(),
-		Key::Questionmark => todo!(),
+		Key::Pipe => KeyCode::Backslash,
+		Key::Questionmark => KeyCode::Slash,
 		Key::OpenBracket => KeyCode::BracketLeft,
 		Key::CloseBracket => KeyCode::BracketRight,
 		Key::Backtick => KeyCode::Backquote,
 		Key::Minus => KeyCode::Minus,
 		Key::Period => KeyCode::Period,
-		Key::Plus => todo!(),
+		Key::Plus => KeyCode::Equal,
 		Key::Equals => KeyCode::Equal,
 		Key::Semicolon => KeyCode::Semicolon,
+		Key::Quote => KeyCode::Quote,
 		Key::Num0 => KeyCode::Digit0,
 		Key::Num1 => KeyCode::Digit1,
 		Key::Num2 => KeyCode::Digit2,
@@ -201,153 +442,255 @@ fn convert_egui_key(key: bevy_egui::egui::Key) -> KeyCode {
 		Key::F10 => KeyCode::F10,
 		Key::F11 => KeyCode::F11,
 		Key::F12 => KeyCode::F12,
-		_ => panic!("Unhandled key"),
+		Key::F13 => KeyCode::F13,
+		Key::F14 => KeyCode::F14,
+		Key::F15 => KeyCode::F15,
+		Key::F16 => KeyCode::F16,
+		Key::F17 => KeyCode::F17,
+		Key::F18 => KeyCode::F18,
+		Key::F19 => KeyCode::F19,
+		Key::F20 => KeyCode::F20,
+		Key::F21 => KeyCode::F21,
+		Key::F22 => KeyCode::F22,
+		Key::F23 => KeyCode::F23,
+		Key::F24 => KeyCode::F24,
+		Key::F25 => KeyCode::F25,
+		Key::F26 => KeyCode::F26,
+		Key::F27 => KeyCode::F27,
+		Key::F28 => KeyCode::F28,
+		Key::F29 => KeyCode::F29,
+		Key::F30 => KeyCode::F30,
+		Key::F31 => KeyCode::F31,
+		Key::F32 => KeyCode::F32,
+		Key::F33 => KeyCode::F33,
+		Key::F34 => KeyCode::F34,
+		Key::F35 => KeyCode::F35,
 	}
 }
 
-mod get_egui_keys {
-	// use bevy_egui::egui::Key;
-	use bevy_egui::egui::Key::*;
-
-	use super::KeyValue;
-	use super::KeyValue::Key;
-	use super::KeyValue::CharKey;
+/// The unshifted character a printable egui key types
+pub fn egui_key_char(key: Key) -> Option<&'static str> {
+	let char = match key {
+		Key::Colon => ":",
+		Key::Comma => ",",
+		Key::Backslash => "\\",
+		Key::Slash => "/",
+		Key::Pipe => "|",
+		Key::Questionmark => "?",
+		Key::OpenBracket => "[",
+		Key::CloseBracket => "]",
+		Key::Backtick => "`",
+		Key::Minus => "-",
+		Key::Period => ".",
+		Key::Plus => "+",
+		Key::Equals => "=",
+		Key::Semicolon => ";",
+		Key::Quote => "'",
+		Key::Num0 => "0",
+		Key::Num1 => "1",
+		Key::Num2 => "2",
+		Key::Num3 => "3",
+		Key::Num4 => "4",
+		Key::Num5 => "5",
+		Key::Num6 => "6",
+		Key::Num7 => "7",
+		Key::Num8 => "8",
+		Key::Num9 => "9",
+		Key::A => "a",
+		Key::B => "b",
+		Key::C => "c",
+		Key::D => "d",
+		Key::E => "e",
+		Key::F => "f",
+		Key::G => "g",
+		Key::H => "h",
+		Key::I => "i",
+		Key::J => "j",
+		Key::K => "k",
+		Key::L => "l",
+		Key::M => "m",
+		Key::N => "n",
+		Key::O => "o",
+		Key::P => "p",
+		Key::Q => "q",
+		Key::R => "r",
+		Key::S => "s",
+		Key::T => "t",
+		Key::U => "u",
+		Key::V => "v",
+		Key::W => "w",
+		Key::X => "x",
+		Key::Y => "y",
+		Key::Z => "z",
+		_ => return None,
+	};
+	Some(char)
+}
 
-	use bevy::input::keyboard::Key as BevyLogicKey;
+/// Logical key for an egui key. Printable keys become `Character` with the modifiers applied.
+pub fn egui_key_to_logical(key: Key, modifier_state: &ModifierState) -> bevy::input::keyboard::Key {
+	use bevy::input::keyboard::{Key as BevyLogicKey, NativeKey};
 
-	pub fn fn_row() -> Vec<KeyValue> {
-		vec![
-			Key(Escape, BevyLogicKey::Escape),
-			Key(F1, BevyLogicKey::F1),
-			Key(F2, BevyLogicKey::F2),
-			Key(F3, BevyLogicKey::F3),
-			Key(F4, BevyLogicKey::F4),
-			Key(F5, BevyLogicKey::F5),
-			Key(F6, BevyLogicKey::F6),
-			Key(F7, BevyLogicKey::F7),
-			Key(F8, BevyLogicKey::F8),
-			Key(F9, BevyLogicKey::F9),
-			Key(F10, BevyLogicKey::F10),
-			Key(F11, BevyLogicKey::F11),
-			Key(F12, BevyLogicKey::F12),
-
-		]
+	if let Some(char) = egui_key_char(key) {
+		return BevyLogicKey::Character(apply_modifiers(char, modifier_state).into());
 	}
 
-	pub fn number_row() -> Vec<KeyValue> {
-		vec![
-			//Key(Backtick, BevyLogicKey::Backquote),
-			CharKey("0".into(), Num0),
-			CharKey("1".into(), Num1),
-			CharKey("2".into(), Num2),
-			CharKey("3".into(), Num3),
-			CharKey("4".into(), Num4),
-			CharKey("5".into(), Num5),
-			CharKey("6".into(), Num6),
-			CharKey("7".into(), Num7),
-			CharKey("8".into(), Num8),
-			CharKey("9".into(), Num9),
-			CharKey("0".into(), Num0),
-			CharKey("-".into(), Minus),
-			CharKey("=".into(), Equals),
-			Key(Backspace, BevyLogicKey::Backspace),
-		]
-
+	match key {
+		Key::ArrowDown => BevyLogicKey::ArrowDown,
+		Key::ArrowLeft => BevyLogicKey::ArrowLeft,
+		Key::ArrowRight => BevyLogicKey::ArrowRight,
+		Key::ArrowUp => BevyLogicKey::ArrowUp,
+		Key::Escape => BevyLogicKey::Escape,
+		Key::Tab => BevyLogicKey::Tab,
+		Key::Backspace => BevyLogicKey::Backspace,
+		Key::Enter => BevyLogicKey::Enter,
+		Key::Space => BevyLogicKey::Space,
+		Key::Insert => BevyLogicKey::Insert,
+		Key::Delete => BevyLogicKey::Delete,
+		Key::Home => BevyLogicKey::Home,
+		Key::End => BevyLogicKey::End,
+		Key::PageUp => BevyLogicKey::PageUp,
+		Key::PageDown => BevyLogicKey::PageDown,
+		Key::Copy => BevyLogicKey::Copy,
+		Key::Cut => BevyLogicKey::Cut,
+		Key::Paste => BevyLogicKey::Paste,
+		Key::F1 => BevyLogicKey::F1,
+		Key::F2 => BevyLogicKey::F2,
+		Key::F3 => BevyLogicKey::F3,
+		Key::F4 => BevyLogicKey::F4,
+		Key::F5 => BevyLogicKey::F5,
+		Key::F6 => BevyLogicKey::F6,
+		Key::F7 => BevyLogicKey::F7,
+		Key::F8 => BevyLogicKey::F8,
+		Key::F9 => BevyLogicKey::F9,
+		Key::F10 => BevyLogicKey::F10,
+		Key::F11 => BevyLogicKey::F11,
+		Key::F12 => BevyLogicKey::F12,
+		Key::F13 => BevyLogicKey::F13,
+		Key::F14 => BevyLogicKey::F14,
+		Key::F15 => BevyLogicKey::F15,
+		Key::F16 => BevyLogicKey::F16,
+		Key::F17 => BevyLogicKey::F17,
+		Key::F18 => BevyLogicKey::F18,
+		Key::F19 => BevyLogicKey::F19,
+		Key::F20 => BevyLogicKey::F20,
+		Key::F21 => BevyLogicKey::F21,
+		Key::F22 => BevyLogicKey::F22,
+		Key::F23 => BevyLogicKey::F23,
+		Key::F24 => BevyLogicKey::F24,
+		Key::F25 => BevyLogicKey::F25,
+		Key::F26 => BevyLogicKey::F26,
+		Key::F27 => BevyLogicKey::F27,
+		Key::F28 => BevyLogicKey::F28,
+		Key::F29 => BevyLogicKey::F29,
+		Key::F30 => BevyLogicKey::F30,
+		Key::F31 => BevyLogicKey::F31,
+		Key::F32 => BevyLogicKey::F32,
+		Key::F33 => BevyLogicKey::F33,
+		Key::F34 => BevyLogicKey::F34,
+		Key::F35 => BevyLogicKey::F35,
+		// printable keys are handled above
+		_ => BevyLogicKey::Unidentified(NativeKey::Unidentified),
 	}
+}
 
-	pub fn first_row() -> Vec<KeyValue> {
-		vec![
-			Key(Tab, BevyLogicKey::Tab),
-			CharKey("q".into(), Q),
-			CharKey("w".into(), W),
-			CharKey("e".into(), E),
-			CharKey("r".into(), R),
-			CharKey("t".into(), T),
-			CharKey("y".into(), Y),
-			CharKey("u".into(), U),
-			CharKey("i".into(), I),
-			CharKey("o".into(), O),
-			CharKey("p".into(), P),
-			CharKey("\\".into(), Backslash),
-		]
-
-	}
+#[derive(Component)]
+pub struct KeyboardWS;
 
-	pub fn second_row() -> Vec<KeyValue> {
-		vec![
-			CharKey("a".into(), A),
-			CharKey("s".into(), S),
-			CharKey("d".into(), D),
-			CharKey("f".into(), F),
-			CharKey("g".into(), G),
-			CharKey("h".into(), H),
-			CharKey("j".into(), J),
-			CharKey("k".into(), K),
-			CharKey("l".into(), L),
-			CharKey(";".into(), Semicolon),
-			Key(Enter, BevyLogicKey::Enter),
-		]
+/// The spatial egui context that last took keyboard focus, typed keys go there
+#[derive(Resource, Default)]
+pub struct FocusedSpatialContext(pub Option<Entity>);
 
+pub fn track_spatial_focus(
+	mut focused: ResMut<FocusedSpatialContext>,
+	mut ctxs: Query<(Entity, &mut EguiContext), (With<EguiRenderToTextureHandle>, Without<KeyboardWS>)>,
+) {
+	let mut still_focused = false;
+	let mut newly_focused = None;
+	for (entity, mut ctx) in ctxs.iter_mut() {
+		if !ctx.get_mut().wants_keyboard_input() {
+			continue;
+		}
+		match focused.0 == Some(entity) {
+			true => still_focused = true,
+			false => newly_focused = Some(entity),
+		}
 	}
 
-	pub fn third_row() -> Vec<KeyValue> {
-		vec![
-			CharKey("z".into(), Z),
-			CharKey("x".into(), X),
-			CharKey("c".into(), C),
-			CharKey("v".into(), V),
-			CharKey("b".into(), B),
-			CharKey("n".into(), N),
-			CharKey("m".into(), M),
-			CharKey(",".into(), Comma),
-			CharKey(".".into(), Period),
-			CharKey("/".into(), Slash),
-		]
-
+	if newly_focused.is_some() {
+		focused.0 = newly_focused;
+	} else if !still_focused && focused.0.is_some() {
+		focused.0 = None;
 	}
 }
 
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-#[derive(Component)]
-struct KeyboardWS;
+fn egui_modifiers(modifier_state: &ModifierState) -> egui::Modifiers {
+	egui::Modifiers {
+		alt: modifier_state.alt,
+		ctrl: modifier_state.ctrl,
+		shift: modifier_state.shift,
+		command: modifier_state.ctrl,
+		..Default::default()
+	}
+}
 
 pub fn update_keyboard_ws(
 	mut ctxs: Query<&mut EguiContext, With<KeyboardWS>>,
+	mut egui_inputs: Query<&mut EguiInput>,
+	focused: Res<FocusedSpatialContext>,
 	window: Query<Entity, With<PrimaryWindow>>,
 	mut event_writer: EventWriter<KeyboardInput>,
-	mut char_writer: EventWriter<ReceivedCharacter>,
-	mut previously_pressed: Local<Option<KeyValue>>,
+	time: Res<Time>,
+	mut layouts: ResMut<KeyboardLayouts>,
+	mut key_repeat: Local<KeyRepeat>,
 	mut modifier_state: Local<ModifierState>,
 ) {
 	for mut ctx in ctxs.iter_mut() {
+		let mut strokes = Vec::new();
+		let modifiers = egui_modifiers(&modifier_state);
+
 		bevy_egui::egui::Window::new("Main Menu")
         .resizable(false)
         //.default_size([4000.0, 4000.0])
         .movable(false)
         .show(ctx.get_mut(), |ui| {
-			draw_keyboard(
+			strokes = draw_keyboard(
 				ui,
-				window.get_single().unwrap(),
-				&mut previously_pressed,
-				&mut event_writer,
-				&mut char_writer,
+				&mut layouts,
+				&mut key_repeat,
 				&mut modifier_state,
+				time.delta_seconds(),
 			);
 		});
 
+		// Straight into the focused spatial window, otherwise as regular key events on the primary window
+		if let Some(mut input) = focused.0.and_then(|e| egui_inputs.get_mut(e).ok()) {
+			for stroke in strokes {
+				let pressed = stroke.state == ButtonState::Pressed;
+				if let Some(key) = stroke.egui_key {
+					input.events.push(egui::Event::Key {
+						key,
+						physical_key: Some(key),
+						pressed,
+						repeat: stroke.repeat,
+						modifiers,
+					});
+				}
+				if let Some(text) = stroke.text {
+					input.events.push(egui::Event::Text(text));
+				}
+			}
+			input.modifiers = egui_modifiers(&modifier_state);
+		} else if let Ok(window) = window.get_single() {
+			for stroke in strokes {
+				event_writer.send(KeyboardInput {
+					key_code: stroke.key_code,
+					logical_key: stroke.logical_key,
+					state: stroke.state,
+					window,
+				});
+			}
+		}
 	}
 }
 
@@ -356,8 +699,10 @@ pub struct KeybaordWSPlugin;
 impl Plugin for KeybaordWSPlugin {
     fn build(&self, app: &mut App) {
 
-        app.add_systems(Startup, setup_keyboard_ws)
-        .add_systems(Update, update_keyboard_ws)
+        app.init_resource::<FocusedSpatialContext>()
+        .init_resource::<KeyboardLayouts>()
+        .add_systems(Startup, setup_keyboard_ws)
+        .add_systems(Update, (track_spatial_focus, update_keyboard_ws).chain())
 
         ;
     }
@@ -367,18 +712,108 @@ impl Plugin for KeybaordWSPlugin {
 
 fn setup_keyboard_ws(
     mut commands: Commands,
+    ui_mode: Res<UiMode>,
 ) {
+    // Screen space menus have a real keyboard
+    if *ui_mode == UiMode::Screen {
+        return;
+    }
+
     let keyboard_window = commands.spawn((
-        //Name::new("Inspector".to_string()),
+        Name::new("Keyboard".to_string()),
         KeyboardWS,
-        
+        // Nothing reopens it
+        NoCloseButton,
     )).id();
-    commands.push(SpawnSpatialEguiWindowCommand {
-        target_entity: Some(keyboard_window),
-        position: Vec3::new(0.0, 1.0, -0.5),
-        rotation: Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
-        resolution: UVec2::splat(512),
-        height: 1.0,
-        unlit: true,
-    });
-}
\ No newline at end of file
+    // Lower and closer than the inspector, which opens at the same height
+    spawn_ui_window(
+        &mut commands,
+        keyboard_window,
+        Vec3::new(0.0, 0.8, 0.0),
+        Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::input::keyboard::Key as BevyLogicKey;
+
+	fn all_modifier_states() -> Vec<ModifierState> {
+		let mut states = Vec::new();
+		for caps_lock in [false, true] {
+			for shift in [false, true] {
+				states.push(ModifierState { caps_lock, shift, ..Default::default() });
+			}
+		}
+		states
+	}
+
+	#[test]
+	fn every_layout_key_converts() {
+		for layout in KeyboardLayouts::default().layouts {
+			for modifier_state in all_modifier_states() {
+				for key in layout.rows.iter().flatten() {
+					assert!(!key.symbol_or_name(&modifier_state).is_empty());
+					key.key_code();
+					let stroke = KeyStroke::pressed(key, &modifier_state, false);
+					if let KeyValue::CharKey(..) = key {
+						assert!(stroke.text.is_some(), "{} {:?}", layout.name, key);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn modifiers_are_one_shot_and_block_text() {
+		let ctrl = ModifierState { ctrl: true, ..Default::default() };
+		let key = KeyValue::CharKey("c".into(), "C".into());
+		let stroke = KeyStroke::pressed(&key, &ctrl, false);
+		assert_eq!(stroke.egui_key, Some(Key::C));
+		assert_eq!(stroke.key_code, KeyCode::KeyC);
+		assert!(stroke.text.is_none());
+		assert_eq!(stroke.released().state, ButtonState::Released);
+	}
+
+	#[test]
+	fn every_egui_key_converts() {
+		for modifier_state in all_modifier_states() {
+			for key in Key::ALL {
+				convert_egui_key(*key);
+				let logical = egui_key_to_logical(*key, &modifier_state);
+				if egui_key_char(*key).is_some() {
+					assert!(matches!(logical, BevyLogicKey::Character(_)), "{:?}", key);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn shift_types_shifted_symbols() {
+		let shift = ModifierState { shift: true, ..Default::default() };
+		let caps = ModifierState { caps_lock: true, ..Default::default() };
+		let both = ModifierState { caps_lock: true, shift: true, ..Default::default() };
+
+		assert_eq!(apply_modifiers("1", &shift), "!");
+		assert_eq!(apply_modifiers("5", &shift), "%");
+		assert_eq!(apply_modifiers("/", &shift), "?");
+		assert_eq!(apply_modifiers("1", &caps), "1");
+		assert_eq!(apply_modifiers("a", &shift), "A");
+		assert_eq!(apply_modifiers("a", &caps), "A");
+		assert_eq!(apply_modifiers("a", &both), "a");
+		assert_eq!(char_key_text("é", "2", &caps), "é");
+		assert_eq!(char_key_text("é", "2", &shift), "2");
+		assert_eq!(char_key_text("ü", "Ü", &caps), "Ü");
+
+		assert_eq!(
+			egui_key_to_logical(Key::Num2, &shift),
+			BevyLogicKey::Character("@".into())
+		);
+		assert_eq!(
+			egui_key_to_logical(Key::Colon, &ModifierState::default()),
+			BevyLogicKey::Character(":".into())
+		);
+		assert_eq!(convert_egui_key(Key::Plus), KeyCode::Equal);
+	}
+}
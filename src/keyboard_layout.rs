@@ -0,0 +1,175 @@
+// Data driven layouts for the VR keyboard
+
+use bevy::prelude::*;
+use bevy_egui::egui::Key;
+
+use crate::keyboard::{egui_key_to_logical, KeyValue, ModifierKey, ModifierState};
+
+
+/// A keyboard layout, rows of keys from top to bottom.
+///
+/// Rows are written as space separated tokens. A character key is its character followed by the
+/// character typed with shift (`1!`), letters can leave the shifted form out (`q`). Named keys
+/// go in braces: `{Backspace}`, `{Enter}`, `{Space}`, `{Left}`, `{Shift}`, `{Ctrl}`, `{Alt}`, `{Caps}` ...
+#[derive(Clone, Debug)]
+pub struct KeyboardLayout {
+    pub name: String,
+    pub rows: Vec<Vec<KeyValue>>,
+}
+
+impl KeyboardLayout {
+    pub fn from_rows(name: impl Into<String>, rows: &[&str]) -> Result<Self, String> {
+        let rows = rows
+            .iter()
+            .map(|row| row.split_whitespace().map(parse_key_token).collect())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name: name.into(),
+            rows,
+        })
+    }
+
+    /// Same format as [`KeyboardLayout::from_rows`], the first non empty line is the name.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let name = lines.next().ok_or("empty layout")?;
+        let rows: Vec<&str> = lines.collect();
+        Self::from_rows(name, &rows)
+    }
+
+    pub fn qwerty() -> Self {
+        Self::from_rows("QWERTY", &[
+            r#"{Esc} `~ 1! 2@ 3# 4$ 5% 6^ 7& 8* 9( 0) -_ =+ {Backspace}"#,
+            r#"{Tab} q w e r t y u i o p [{ ]} \|"#,
+            r#"{Caps} a s d f g h j k l ;: '" {Enter}"#,
+            r#"{Shift} z x c v b n m ,< .> /?"#,
+            r#"{Ctrl} {Alt} {Space} {Left} {Down} {Up} {Right}"#,
+        ]).unwrap()
+    }
+
+    pub fn azerty() -> Self {
+        Self::from_rows("AZERTY", &[
+            r#"{Esc} ² &1 é2 "3 '4 (5 -6 è7 _8 ç9 à0 )° =+ {Backspace}"#,
+            r#"{Tab} a z e r t y u i o p ^¨ $£ *µ"#,
+            r#"{Caps} q s d f g h j k l m ù% {Enter}"#,
+            r#"{Shift} <> w x c v b n ,? ;. :/ !§"#,
+            r#"{Ctrl} {Alt} {Space} {Left} {Down} {Up} {Right}"#,
+        ]).unwrap()
+    }
+
+    pub fn qwertz() -> Self {
+        Self::from_rows("QWERTZ", &[
+            r#"{Esc} ^° 1! 2" 3§ 4$ 5% 6& 7/ 8( 9) 0= ß? ´` {Backspace}"#,
+            r#"{Tab} q w e r t z u i o p ü +*"#,
+            r#"{Caps} a s d f g h j k l ö ä #' {Enter}"#,
+            r#"{Shift} <> y x c v b n m ,; .: -_"#,
+            r#"{Ctrl} {Alt} {Space} {Left} {Down} {Up} {Right}"#,
+        ]).unwrap()
+    }
+
+    pub fn numpad() -> Self {
+        Self::from_rows("Numpad", &[
+            r#"7 8 9 {Backspace}"#,
+            r#"4 5 6 -"#,
+            r#"1 2 3 +"#,
+            r#"0 . , {Enter}"#,
+            r#"{Left} {Right}"#,
+        ]).unwrap()
+    }
+}
+
+pub fn parse_key_token(token: &str) -> Result<KeyValue, String> {
+    if token.len() > 2 && token.starts_with('{') && token.ends_with('}') {
+        let name = &token[1..token.len() - 1];
+        let modifier = match name {
+            "Shift" => Some(ModifierKey::Shift),
+            "Ctrl" => Some(ModifierKey::Ctrl),
+            "Alt" => Some(ModifierKey::Alt),
+            "Caps" | "CapsLock" => Some(ModifierKey::CapsLock),
+            _ => None,
+        };
+        if let Some(modifier) = modifier {
+            return Ok(KeyValue::Modifier(modifier));
+        }
+        let key = Key::from_name(name).ok_or_else(|| format!("unknown key {}", token))?;
+        return Ok(KeyValue::Key(key, egui_key_to_logical(key, &ModifierState::default())));
+    }
+
+    let mut chars = token.chars();
+    let (Some(char), shifted, None) = (chars.next(), chars.next(), chars.next()) else {
+        return Err(format!("bad key token {:?}", token));
+    };
+    let shifted = match shifted {
+        Some(shifted) => shifted.to_string(),
+        None => char.to_uppercase().to_string(),
+    };
+    Ok(KeyValue::CharKey(char.to_string(), shifted))
+}
+
+
+/// Layouts to pick from on the keyboard, `current` indexes `layouts`
+#[derive(Resource)]
+pub struct KeyboardLayouts {
+    pub layouts: Vec<KeyboardLayout>,
+    pub current: usize,
+}
+
+impl Default for KeyboardLayouts {
+    fn default() -> Self {
+        Self {
+            layouts: vec![
+                KeyboardLayout::qwerty(),
+                KeyboardLayout::azerty(),
+                KeyboardLayout::qwertz(),
+                KeyboardLayout::numpad(),
+            ],
+            current: 0,
+        }
+    }
+}
+
+impl KeyboardLayouts {
+    pub fn current(&self) -> &KeyboardLayout {
+        &self.layouts[self.current.min(self.layouts.len() - 1)]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tokens() {
+        assert_eq!(parse_key_token("q").unwrap(), KeyValue::CharKey("q".into(), "Q".into()));
+        assert_eq!(parse_key_token("1!").unwrap(), KeyValue::CharKey("1".into(), "!".into()));
+        assert_eq!(parse_key_token("é2").unwrap(), KeyValue::CharKey("é".into(), "2".into()));
+        assert_eq!(parse_key_token("{Shift}").unwrap(), KeyValue::Modifier(ModifierKey::Shift));
+        assert!(matches!(parse_key_token("{Backspace}").unwrap(), KeyValue::Key(Key::Backspace, _)));
+        assert!(parse_key_token("{Nope}").is_err());
+        assert!(parse_key_token("abc").is_err());
+    }
+
+    #[test]
+    fn built_in_layouts_have_no_duplicate_keys() {
+        for layout in KeyboardLayouts::default().layouts {
+            let keys: Vec<&KeyValue> = layout.rows.iter().flatten().collect();
+            for (i, key) in keys.iter().enumerate() {
+                assert!(
+                    !keys[i + 1..].contains(key),
+                    "{} has {:?} twice",
+                    layout.name,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layout_from_text() {
+        let layout = KeyboardLayout::from_text("Tiny\n\na b {Enter}\n{Space}\n").unwrap();
+        assert_eq!(layout.name, "Tiny");
+        assert_eq!(layout.rows.len(), 2);
+        assert_eq!(layout.rows[0].len(), 3);
+    }
+}
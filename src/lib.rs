@@ -11,7 +11,6 @@ use bevy_egui::EguiPlugin;
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_inspector_egui::DefaultInspectorConfigPlugin;
-use bevy_mod_openxr::session::OxrSession;
 use bevy_mod_openxr::types::EnvironmentBlendMode;
 use bevy_mod_openxr::{add_xr_plugins, init::OxrInitPlugin, types::OxrExtensions};
 use bevy_mod_picking::backends::raycast::RaycastPickable;
@@ -28,16 +27,58 @@ use bevy_suis::SuisCorePlugin;
 //use bevy_vr_controller::animation::defaults::default_character_animations;
 //use bevy_vr_controller::VrControllerPlugin;
 
+use camera_path::CameraPathPlugin;
+use edit_history::EditHistoryPlugin;
+use file_browser::FileBrowserPlugin;
+use hand_cam_capture::HandCamCapturePlugin;
+use hand_cam_lens::HandCamLensPlugin;
+use hand_cam_recording::HandCamRecordingPlugin;
+use image_readback::ImageReadbackPlugin;
 use keyboard::KeybaordWSPlugin;
 use main_menu::MainMenuPlugin;
+use passthrough::PassthroughPlugin;
+use refresh_rate::RefreshRatePlugin;
+use scene_file::SceneFilePlugin;
+use splat_crop::SplatCropPlugin;
+use splat_eraser::SplatEraserPlugin;
+use splat_list::SplatListPlugin;
+use splat_surface::SplatSurfacePlugin;
+use transform_gizmo::TransformGizmoPlugin;
+use ui_window::UiWindowPlugin;
+use window_placement::WindowPlacementPlugin;
+use world_scale::WorldScalePlugin;
+use xr_camera::XrGaussianCameraPlugin;
 
 
 pub mod gaussian;
 
+pub mod camera_path;
+pub mod edit_history;
+pub mod desktop;
+pub mod file_browser;
+pub mod hand_cam_capture;
+pub mod hand_cam_lens;
+pub mod hand_cam_recording;
+pub mod image_readback;
 pub mod inspector_ws;
 pub mod main_menu;
+pub mod passthrough;
+pub mod refresh_rate;
 pub mod grabbing;
 pub mod keyboard;
+pub mod keyboard_layout;
+pub mod locomotion;
+pub mod scene_file;
+pub mod splat_crop;
+pub mod splat_eraser;
+pub mod splat_grade;
+pub mod splat_list;
+pub mod splat_surface;
+pub mod transform_gizmo;
+pub mod ui_window;
+pub mod window_placement;
+pub mod world_scale;
+pub mod xr_camera;
 
 use bevy_gaussian_splatting::{GaussianCamera, GaussianCloudSettings, GaussianSplattingBundle, GaussianSplattingPlugin};
 use crate::inspector_ws::{InspectorWSMenu, update_inspector_ws};
@@ -46,6 +87,9 @@ use crate::inspector_ws::{InspectorWSMenu, update_inspector_ws};
 #[bevy_main]
 pub fn main() {
     let mut app = App::new();
+
+    // Needs to be registered before the AssetPlugin
+    file_browser::register_fs_asset_source(&mut app);
     
     #[cfg(feature = "native")]
     app.add_plugins(add_xr_plugins(DefaultPlugins).set(OxrInitPlugin {
@@ -54,7 +98,7 @@ pub fn main() {
             let mut exts = OxrExtensions::default();
             exts.enable_fb_passthrough();
             //exts.enable_hand_tracking();
-            //exts.enable_custom_refresh_rates();
+            exts.enable_custom_refresh_rates();
 
             exts
         },
@@ -106,8 +150,6 @@ pub fn main() {
         ..default()
     }));
 
-    // System for requesting refresh rate ( should refactor and upstream into bevy_openxr )
-    //.add_systems(Update, set_requested_refresh_rate)
     
     
     // Our plugins
@@ -136,6 +178,7 @@ pub fn main() {
         brightness: 1000.0,
     })
     .insert_resource(Msaa::Off)
+    // Passthrough until the saved view settings are applied
     .insert_resource(ClearColor(Color::NONE))
 
      
@@ -151,8 +194,27 @@ pub fn main() {
 
     .add_plugins(WorldInspectorPlugin::new())
 
+    .add_plugins(UiWindowPlugin)
+    .add_plugins(PassthroughPlugin)
+    .add_plugins(RefreshRatePlugin)
+    .add_plugins(WorldScalePlugin)
+    .add_plugins(WindowPlacementPlugin)
     .add_plugins(MainMenuPlugin)
+    .add_plugins(FileBrowserPlugin)
+    .add_plugins(SplatListPlugin)
+    .add_plugins(SplatCropPlugin)
+    .add_plugins(SplatEraserPlugin)
+    .add_plugins(SplatSurfacePlugin)
+    .add_plugins(EditHistoryPlugin)
+    .add_plugins(SceneFilePlugin)
+    .add_plugins(TransformGizmoPlugin)
     .add_plugins(KeybaordWSPlugin)
+    .add_plugins(XrGaussianCameraPlugin)
+    .add_plugins(ImageReadbackPlugin)
+    .add_plugins(HandCamCapturePlugin)
+    .add_plugins(HandCamRecordingPlugin)
+    .add_plugins(HandCamLensPlugin)
+    .add_plugins(CameraPathPlugin)
 
     .add_systems(Update, update_inspector_ws) 
 
@@ -177,11 +239,17 @@ pub fn main() {
     app.add_plugins(
         bevy_xr_utils::xr_utils_actions::XRUtilsActionsPlugin,
     )
+    .add_plugins(locomotion::LocomotionPlugin)
     .add_plugins((
     //    VrControllerPlugin,
     ))
     .add_systems(Update, grabbing::move_grabble);
 
+    // Screen space menus, orbit/fly camera and mouse grabbing
+    #[cfg(feature = "desktop")]
+    app.add_plugins(desktop::DesktopPlugin)
+    .add_systems(Update, grabbing::move_grabble);
+
     //.add_systems(Startup, setup_player)
     app.run();
 }
@@ -218,20 +286,6 @@ fn setup(
 #[derive(Component, Clone, Copy)]
 pub struct HandBoneColider(Entity);
 
-fn set_requested_refresh_rate(mut local: Local<bool>, mut session: Option<ResMut<OxrSession>>) {
-    if session.is_none() {
-        return;
-    }
-    if *local {
-        return;
-    }
-    *local = true;
-    session
-        .as_mut()
-        .unwrap()
-        .request_display_refresh_rate(72.0)
-        .unwrap();
-}
 
 
 /* 
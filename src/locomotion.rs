@@ -0,0 +1,551 @@
+// Thumbstick movement, snap turn, teleport and grabbing the world, all by moving the tracking root
+// so grabbables and windows stay where they are in the scene
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_egui::egui;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use bevy_mod_xr::camera::XrCamera;
+use bevy_mod_xr::session::XrTrackingRoot;
+use bevy_suis::{xr_controllers::XrControllerInputMethodData, InputHandlerCaptures};
+use bevy_xr_utils::xr_utils_actions::{
+    ActionType, ActiveSet, XRUtilsAction, XRUtilsActionSet, XRUtilsActionState, XRUtilsBinding,
+};
+
+use crate::splat_crop::EditedCloud;
+use crate::splat_eraser::SplatErasures;
+use crate::splat_surface::SplatSurface;
+use crate::ui_window::WindowContexts;
+
+
+const PROFILES: [&str; 2] = [
+    "/interaction_profiles/oculus/touch_controller",
+    "/interaction_profiles/valve/index_controller",
+];
+const STICK_DEADZONE: f32 = 0.15;
+/// Stick deflection that snaps a turn or starts aiming, and the one it has to come back under
+const STICK_PRESS: f32 = 0.7;
+const STICK_RELEASE: f32 = 0.3;
+
+const TELEPORT_SPEED: f32 = 7.0;
+const TELEPORT_STEP: f32 = 0.02;
+const TELEPORT_MAX_TIME: f32 = 2.0;
+/// How long a snap turn shows the vignette
+const SNAP_VIGNETTE_SECONDS: f32 = 0.15;
+const VIGNETTE_DISTANCE: f32 = 0.3;
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct LocomotionSettings {
+    /// Meters per second at full stick
+    pub move_speed: f32,
+    /// Degrees per snap
+    pub snap_angle: f32,
+    pub teleport: bool,
+    pub world_grab: bool,
+    /// Darkens the edges of the view while moving
+    pub vignette: bool,
+    /// 0 is a thin border, 1 leaves a small window in the middle
+    pub vignette_strength: f32,
+}
+
+impl Default for LocomotionSettings {
+    fn default() -> Self {
+        Self {
+            move_speed: 1.5,
+            snap_angle: 30.0,
+            teleport: true,
+            world_grab: true,
+            vignette: true,
+            vignette_strength: 0.5,
+        }
+    }
+}
+
+#[derive(Component)]
+struct MoveAction;
+
+#[derive(Component)]
+struct TurnAction;
+
+#[derive(Default)]
+struct WorldGrab {
+    method: Option<Entity>,
+    anchor: Vec3,
+}
+
+#[derive(Resource, Default)]
+pub struct LocomotionState {
+    turn_armed: bool,
+    aiming: bool,
+    pub teleport_target: Option<Vec3>,
+    world_grab: WorldGrab,
+    /// Seconds of vignette left from the last snap turn
+    snap_vignette: f32,
+    moving: bool,
+}
+
+#[derive(Component)]
+pub struct ComfortVignette;
+
+#[derive(Component, Default)]
+pub struct LocomotionWS;
+
+
+pub struct LocomotionPlugin;
+
+impl Plugin for LocomotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LocomotionSettings>()
+            .init_resource::<LocomotionState>()
+            .add_systems(Startup, (create_locomotion_actions, spawn_vignette))
+            .add_systems(
+                Update,
+                (
+                    update_locomotion_ws,
+                    smooth_locomotion,
+                    snap_turn,
+                    teleport,
+                    grab_the_world,
+                    update_vignette,
+                ).chain(),
+            );
+    }
+}
+
+
+fn create_locomotion_actions(mut commands: Commands) {
+    let set = commands.spawn((
+        XRUtilsActionSet {
+            name: "locomotion".into(),
+            pretty_name: "Locomotion".into(),
+            priority: u32::MIN,
+        },
+        ActiveSet,
+    )).id();
+
+    let move_action = spawn_stick_action(&mut commands, "move", "Move", "left");
+    commands.entity(move_action).insert(MoveAction);
+    let turn_action = spawn_stick_action(&mut commands, "turn", "Turn and teleport", "right");
+    commands.entity(turn_action).insert(TurnAction);
+    commands.entity(set).push_children(&[move_action, turn_action]);
+}
+
+fn spawn_stick_action(commands: &mut Commands, name: &str, pretty_name: &str, hand: &str) -> Entity {
+    let action = commands.spawn(XRUtilsAction {
+        action_name: name.into(),
+        localized_name: pretty_name.into(),
+        action_type: ActionType::Vector,
+    }).id();
+
+    for profile in PROFILES {
+        let binding = commands.spawn(XRUtilsBinding {
+            profile: profile.into(),
+            binding: format!("/user/hand/{}/input/thumbstick", hand).into(),
+        }).id();
+        commands.entity(action).add_child(binding);
+    }
+    action
+}
+
+fn stick<T: Component>(actions: &Query<&XRUtilsActionState, With<T>>) -> Vec2 {
+    actions
+        .iter()
+        .find_map(|state| match state {
+            XRUtilsActionState::Vector(v) => Some(Vec2::from_array(v.current_state)),
+            _ => None,
+        })
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// Midpoint of the eye cameras, scaled along with the tracking root
+pub fn head_pose(cameras: &Query<&GlobalTransform, With<XrCamera>>) -> Option<Transform> {
+    let count = cameras.iter().count();
+    let first = cameras.iter().next()?.compute_transform();
+    let translation = cameras.iter().map(|gt| gt.translation()).sum::<Vec3>() / count as f32;
+    Some(first.with_translation(translation))
+}
+
+/// Ground plane direction for a stick deflection, forward is where the head faces
+pub fn stick_direction(head_rotation: Quat, stick: Vec2) -> Vec3 {
+    let forward = (head_rotation * Vec3::NEG_Z).with_y(0.0).normalize_or_zero();
+    let right = (head_rotation * Vec3::X).with_y(0.0).normalize_or_zero();
+    forward * stick.y + right * stick.x
+}
+
+/// Turns the root around the head, so the head stays put
+pub fn turn_around_head(root: &mut Transform, head: Vec3, degrees: f32) {
+    root.rotate_around(head, Quat::from_rotation_y(degrees.to_radians()));
+}
+
+/// The input method furthest to the right of the head
+pub fn rightmost<'a>(
+    head: &Transform,
+    methods: impl Iterator<Item = &'a GlobalTransform>,
+) -> Option<&'a GlobalTransform> {
+    let right = head.rotation * Vec3::X;
+    methods.max_by(|a, b| {
+        let a = (a.translation() - head.translation).dot(right);
+        let b = (b.translation() - head.translation).dot(right);
+        a.total_cmp(&b)
+    })
+}
+
+/// Points along a thrown arc, spaced [`TELEPORT_STEP`] seconds apart. `scale` is the user's size,
+/// a giant throws further.
+pub fn teleport_arc(start: Vec3, direction: Vec3, scale: f32) -> impl Iterator<Item = Vec3> {
+    let velocity = direction.normalize_or_zero() * TELEPORT_SPEED * scale;
+    let steps = (TELEPORT_MAX_TIME / TELEPORT_STEP) as usize;
+    (0..=steps).map(move |i| {
+        let t = i as f32 * TELEPORT_STEP;
+        start + velocity * t + Vec3::NEG_Y * 9.81 * scale * 0.5 * t * t
+    })
+}
+
+
+fn smooth_locomotion(
+    settings: Res<LocomotionSettings>,
+    mut state: ResMut<LocomotionState>,
+    actions: Query<&XRUtilsActionState, With<MoveAction>>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    mut roots: Query<&mut Transform, With<XrTrackingRoot>>,
+    time: Res<Time>,
+) {
+    let stick = stick(&actions);
+    state.moving = stick.length() > STICK_DEADZONE;
+    if !state.moving {
+        return;
+    }
+    let Some(head) = head_pose(&cameras) else {
+        return;
+    };
+    // In the user's own meters, so a scaled up world is crossed as quickly
+    let step = stick_direction(head.rotation, stick) * settings.move_speed * head.scale.x * time.delta_seconds();
+    for mut root in roots.iter_mut() {
+        root.translation += step;
+    }
+}
+
+fn snap_turn(
+    settings: Res<LocomotionSettings>,
+    mut state: ResMut<LocomotionState>,
+    actions: Query<&XRUtilsActionState, With<TurnAction>>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    mut roots: Query<&mut Transform, With<XrTrackingRoot>>,
+    time: Res<Time>,
+) {
+    state.snap_vignette = (state.snap_vignette - time.delta_seconds()).max(0.0);
+    let x = stick(&actions).x;
+    if x.abs() < STICK_RELEASE {
+        state.turn_armed = true;
+        return;
+    }
+    if x.abs() < STICK_PRESS || !state.turn_armed {
+        return;
+    }
+    let Some(head) = head_pose(&cameras) else {
+        return;
+    };
+    state.turn_armed = false;
+    state.snap_vignette = SNAP_VIGNETTE_SECONDS;
+    // Stick right turns clockwise seen from above
+    let degrees = -x.signum() * settings.snap_angle;
+    for mut root in roots.iter_mut() {
+        turn_around_head(&mut root, head.translation, degrees);
+    }
+}
+
+/// Aims while the right stick is pushed forward, jumps there when it's let go
+fn teleport(
+    settings: Res<LocomotionSettings>,
+    mut state: ResMut<LocomotionState>,
+    actions: Query<&XRUtilsActionState, With<TurnAction>>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    controllers: Query<&GlobalTransform, With<XrControllerInputMethodData>>,
+    splats: Query<(
+        &GaussianCloudSettings,
+        &SplatSurface,
+        Option<&EditedCloud>,
+        Option<&SplatErasures>,
+        &InheritedVisibility,
+    )>,
+    mut roots: Query<&mut Transform, With<XrTrackingRoot>>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.teleport {
+        state.aiming = false;
+        state.teleport_target = None;
+        return;
+    }
+    let y = stick(&actions).y;
+    let (Some(head), Ok(mut root)) = (head_pose(&cameras), roots.get_single_mut()) else {
+        return;
+    };
+
+    if !state.aiming {
+        state.aiming = y > STICK_PRESS;
+        return;
+    }
+    if y < STICK_RELEASE {
+        state.aiming = false;
+        if let Some(target) = state.teleport_target.take() {
+            // Feet on the target, the head keeps its height above them
+            let feet = head.translation.with_y(root.translation.y);
+            root.translation += target - feet;
+        }
+        return;
+    }
+
+    // Thrown from the right hand controller
+    let Some(hand) = rightmost(&head, controllers.iter()) else {
+        return;
+    };
+
+    let floor = root.translation.y;
+    let locals: Vec<(Affine3A, &SplatSurface, Option<&EditedCloud>, Option<&SplatErasures>)> = splats
+        .iter()
+        .filter(|(.., visibility)| visibility.get())
+        .map(|(settings, surface, edited, erasures, _)| {
+            (settings.transform.compute_affine().inverse(), surface, edited, erasures)
+        })
+        .collect();
+
+    let mut path = Vec::new();
+    state.teleport_target = None;
+    for point in teleport_arc(hand.translation(), *hand.forward(), head.scale.x) {
+        path.push(point);
+        if path.len() < 2 {
+            continue;
+        }
+        // Cropped and erased gaussians aren't there to land on
+        let hits_splat = locals.iter().any(|(to_local, surface, edited, erasures)| {
+            surface.solid(to_local.transform_point3(point), |i| {
+                edited.map_or(true, |e| e.keeps(i)) && !erasures.is_some_and(|e| e.is_erased(i))
+            })
+        });
+        if hits_splat || point.y <= floor {
+            state.teleport_target = Some(if hits_splat { point } else { point.with_y(floor) });
+            break;
+        }
+    }
+
+    let color = match state.teleport_target {
+        Some(_) => Color::srgb(0.3, 0.9, 0.4),
+        None => Color::srgb(0.9, 0.3, 0.3),
+    };
+    gizmos.linestrip(path, color);
+    if let Some(target) = state.teleport_target {
+        gizmos.circle(target, Dir3::Y, 0.3 * head.scale.x, color);
+    }
+}
+
+/// Squeezing a controller where there's nothing to grab drags the world along with it
+fn grab_the_world(
+    settings: Res<LocomotionSettings>,
+    mut state: ResMut<LocomotionState>,
+    controllers: Query<(Entity, &GlobalTransform, &XrControllerInputMethodData)>,
+    handlers: Query<&InputHandlerCaptures>,
+    mut roots: Query<&mut Transform, With<XrTrackingRoot>>,
+) {
+    if !settings.world_grab {
+        state.world_grab = WorldGrab::default();
+        return;
+    }
+
+    if let Some(method) = state.world_grab.method {
+        let Ok((_, gt, controller)) = controllers.get(method) else {
+            state.world_grab = WorldGrab::default();
+            return;
+        };
+        if !controller.squeezed {
+            state.world_grab = WorldGrab::default();
+            return;
+        }
+        // Keep the controller over the point it grabbed
+        let offset = state.world_grab.anchor - gt.translation();
+        for mut root in roots.iter_mut() {
+            root.translation += offset;
+        }
+        return;
+    }
+
+    for (method, gt, controller) in controllers.iter() {
+        let holding = handlers.iter().any(|c| c.captured_methods.contains(&method));
+        if controller.squeezed && !holding {
+            state.world_grab = WorldGrab {
+                method: Some(method),
+                anchor: gt.translation(),
+            };
+            return;
+        }
+    }
+}
+
+
+/// Ring that's clear in the middle and black at the edges, drawn in front of the head
+fn vignette_mesh() -> Mesh {
+    const SEGMENTS: u32 = 48;
+    // Inner edge, start of full black and far outside the field of view
+    let rings = [(0.12, 0.0), (0.2, 1.0), (1.0, 1.0)];
+
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    for (radius, alpha) in rings {
+        for i in 0..SEGMENTS {
+            let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            positions.push([angle.cos() * radius, angle.sin() * radius, 0.0]);
+            colors.push([0.0, 0.0, 0.0, alpha]);
+        }
+    }
+    let mut indices = Vec::new();
+    for ring in 0..rings.len() as u32 - 1 {
+        for i in 0..SEGMENTS {
+            let a = ring * SEGMENTS + i;
+            let b = ring * SEGMENTS + (i + 1) % SEGMENTS;
+            let c = a + SEGMENTS;
+            let d = b + SEGMENTS;
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_indices(bevy::render::mesh::Indices::U32(indices))
+}
+
+fn spawn_vignette(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(vignette_mesh()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                cull_mode: None,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Name::new("Comfort Vignette"),
+        ComfortVignette,
+    ));
+}
+
+fn update_vignette(
+    settings: Res<LocomotionSettings>,
+    state: Res<LocomotionState>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    mut vignettes: Query<(&mut Transform, &mut Visibility), With<ComfortVignette>>,
+) {
+    let moving = state.moving || state.snap_vignette > 0.0 || state.world_grab.method.is_some();
+    let head = head_pose(&cameras);
+    for (mut transform, mut visibility) in vignettes.iter_mut() {
+        let Some(head) = head.filter(|_| settings.vignette && moving) else {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        // Smaller ring, bigger black border
+        let scale = (1.0 - settings.vignette_strength * 0.5) * head.scale.x;
+        *transform = Transform {
+            translation: head.translation + head.rotation * Vec3::NEG_Z * VIGNETTE_DISTANCE * head.scale.x,
+            rotation: head.rotation,
+            scale: Vec3::new(scale, scale, head.scale.x),
+        };
+    }
+}
+
+
+fn update_locomotion_ws(
+    windows: Query<Entity, With<LocomotionWS>>,
+    mut contexts: WindowContexts,
+    mut settings: ResMut<LocomotionSettings>,
+) {
+    for entity in windows.iter() {
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("Locomotion")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                let mut edited = settings.clone();
+
+                ui.label("Left stick moves, right stick turns, push it forward to aim a teleport");
+                ui.add(egui::Slider::new(&mut edited.move_speed, 0.25..=5.0).text("Speed (m/s)"));
+                ui.horizontal(|ui| {
+                    ui.label("Snap Turn");
+                    for angle in [15.0, 30.0, 45.0, 90.0] {
+                        ui.selectable_value(&mut edited.snap_angle, angle, format!("{}°", angle));
+                    }
+                });
+                ui.checkbox(&mut edited.teleport, "Teleport");
+                ui.checkbox(&mut edited.world_grab, "Squeeze in empty space to drag the world");
+                ui.checkbox(&mut edited.vignette, "Vignette while moving");
+                ui.add_enabled(
+                    edited.vignette,
+                    egui::Slider::new(&mut edited.vignette_strength, 0.0..=1.0).text("Strength"),
+                );
+
+                if edited != *settings {
+                    *settings = edited;
+                }
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stick_moves_relative_to_the_head() {
+        let facing_left = Quat::from_rotation_y(90f32.to_radians());
+        let forward = stick_direction(facing_left, Vec2::Y);
+        assert!(forward.distance(Vec3::NEG_X) < 1e-5, "{}", forward);
+        // Looking down still moves along the ground
+        let looking_down = Quat::from_rotation_x(-60f32.to_radians());
+        let forward = stick_direction(looking_down, Vec2::Y);
+        assert!(forward.distance(Vec3::NEG_Z) < 1e-5, "{}", forward);
+    }
+
+    #[test]
+    fn snap_turn_keeps_the_head_in_place() {
+        let mut root = Transform::from_xyz(1.0, 0.0, 2.0);
+        let head_local = Vec3::new(0.3, 1.7, -0.2);
+        let head = root.transform_point(head_local);
+        turn_around_head(&mut root, head, 30.0);
+        assert!(root.transform_point(head_local).distance(head) < 1e-5);
+        assert!(root.rotation.angle_between(Quat::from_rotation_y(30f32.to_radians())) < 1e-5);
+    }
+
+    #[test]
+    fn arc_lands_on_dense_cells_only() {
+        // A floor of points at y = 0, and a lone floater in the way
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        for x in 0..100 {
+            for z in 0..100 {
+                positions.push([x as f32 * 0.05, 0.0, z as f32 * -0.05]);
+            }
+        }
+        positions.push([1.0, 1.2, -0.5]);
+        let surface = SplatSurface::build(positions.iter(), AssetId::default());
+        assert!(!surface.solid(Vec3::new(1.0, 1.2, -0.5), |_| true));
+
+        let landing = teleport_arc(Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.0, 0.3, -1.0), 1.0)
+            .find(|p| surface.solid(*p, |_| true))
+            .expect("arc never landed");
+        assert!(landing.y.abs() < 0.1, "{}", landing);
+        assert!(landing.z < -1.0, "{}", landing);
+    }
+}
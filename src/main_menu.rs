@@ -1,20 +1,26 @@
 use std::sync::Arc;
 
-use bevy::{asset::embedded_asset, prelude::*, render::{camera::RenderTarget, render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages}, view::RenderLayers}, ui::update};
-use bevy_egui::{EguiContext, egui};
+use bevy::{asset::embedded_asset, ecs::{entity::Entities, system::SystemParam}, prelude::*, render::{camera::RenderTarget, render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages}, view::RenderLayers}, ui::update};
+use bevy_egui::egui;
 use bevy_gaussian_splatting::{GaussianCamera, GaussianCloudSettings, GaussianSplattingBundle};
-use bevy_mod_xr::camera::XrCamera;
-use bevy_spatial_egui::SpawnSpatialEguiWindowCommand;
 use bevy_suis::{Field, InputHandler};
 use egui_aesthetix::Aesthetix;
 //use space_editor::prelude::events_dispatcher::inspect;
 
-use crate::{gaussian::GaussianMarker, grabbing::{self, Grabble}, inspector_ws::InspectorWSMenu};
+use crate::{camera_path::CameraPathWS, edit_history::HistoryControls, file_browser::{FileBrowserWS, SplatFileChosen}, gaussian::{default_splat_settings, spawn_splat, SelectedSplat}, grabbing::{self, Grabble}, hand_cam_capture::{spawn_hand_cam_button, HandCamAction, HandCamGalleryWS, HandCamShutter}, hand_cam_lens::HandCamSettingsWS, hand_cam_recording::spawn_recording_light, inspector_ws::InspectorWSMenu, passthrough::PassthroughControls, refresh_rate::RefreshRateControls, scene_file::{default_scene_path, SceneFileEvent}, splat_crop::CropWS, splat_eraser::EraserWS, splat_grade::{grade_ui, SplatGrade}, splat_list::SplatListWS, transform_gizmo::{spawn_transform_gizmo, SplatTransformTool}, ui_window::{spawn_ui_window, WindowContexts}, window_placement::{NoCloseButton, WindowControls}, world_scale::{ScalesWithUser, WorldScaleControls}, xr_camera::XrGaussianCameraSettings};
+#[cfg(feature = "native")]
+use crate::locomotion::LocomotionWS;
 
 
 
 
 
+const MIN_WORLD_SCALE: f32 = 0.01;
+const MAX_WORLD_SCALE: f32 = 100.0;
+/// Multiplier on every gaussian's footprint, separate from the world scale of the cloud
+const MIN_GAUSSIAN_SIZE: f32 = 0.1;
+const MAX_GAUSSIAN_SIZE: f32 = 4.0;
+
 #[derive(Component)]
 pub struct MainMenu {
     splats_showing: bool,
@@ -22,8 +28,6 @@ pub struct MainMenu {
     slider_menu_showing: bool,
     base_file_path: String,
     splat_file_name: String,
-    splat_id: Option<Entity>,
-    cached_transform: Option<Transform>,  // Add this field to cache the splat transform
 }
 
 
@@ -47,75 +51,158 @@ fn setup_main_menu(
 
 
     let main_menu_window = commands.spawn((
+        Name::new("Main Menu".to_string()),
         MainMenu {
             splats_showing: false,
             insepctor_showing: false,
             slider_menu_showing: false,
             base_file_path: "embedded://".into(),
             splat_file_name: "cat1.gcloud".into(),
-            splat_id: None,
-            cached_transform: None,
         },
-        
+        // Everything else is opened from here
+        NoCloseButton,
     )).id();
-    commands.push(SpawnSpatialEguiWindowCommand {
-        target_entity: Some(main_menu_window),
-        position: Vec3::new(0.0, 2.0, -0.5),
-        rotation: Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
-        resolution: UVec2::splat(512),
-        height: 1.0,
-        unlit: true,
-    });
+    spawn_ui_window(
+        &mut commands,
+        main_menu_window,
+        Vec3::new(0.0, 2.0, -0.5),
+        Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+    );
 }
 
 
 #[derive(Default)]
 struct SplatMenuSettings {
     splat_transform_showing: bool,
-    splat_opacity: f32,
-    splat_size_scale: f32,
     splat_transform_id: Option<Entity>,
-    splat_showing: bool,
+    splat_list_showing: bool,
+    splat_list_window_id: Option<Entity>,
     inspector_showing: bool,
     temp_gaus_name: String,
+    scene_path: String,
     inspector_window_id: Option<Entity>,
 
+    file_browser_showing: bool,
+    file_browser_window_id: Option<Entity>,
+
     hand_cam_showing: bool,
     hand_cam_id: Option<Entity>,
+
+    gallery_showing: bool,
+    gallery_window_id: Option<Entity>,
+
+    hand_cam_settings_showing: bool,
+    hand_cam_settings_window_id: Option<Entity>,
+
+    camera_path_showing: bool,
+    camera_path_window_id: Option<Entity>,
+
+    crop_showing: bool,
+    crop_window_id: Option<Entity>,
+
+    eraser_showing: bool,
+    eraser_window_id: Option<Entity>,
+
+    locomotion_showing: bool,
+    locomotion_window_id: Option<Entity>,
+}
+
+impl SplatMenuSettings {
+    /// Windows closed with their own close button show up as off again
+    fn forget_closed_windows(&mut self, entities: &Entities) {
+        for (showing, window_id) in [
+            (&mut self.splat_list_showing, &mut self.splat_list_window_id),
+            (&mut self.inspector_showing, &mut self.inspector_window_id),
+            (&mut self.file_browser_showing, &mut self.file_browser_window_id),
+            (&mut self.gallery_showing, &mut self.gallery_window_id),
+            (&mut self.hand_cam_settings_showing, &mut self.hand_cam_settings_window_id),
+            (&mut self.camera_path_showing, &mut self.camera_path_window_id),
+            (&mut self.crop_showing, &mut self.crop_window_id),
+            (&mut self.eraser_showing, &mut self.eraser_window_id),
+            (&mut self.locomotion_showing, &mut self.locomotion_window_id),
+        ] {
+            if window_id.is_some_and(|id| !entities.contains(id)) {
+                *showing = false;
+                *window_id = None;
+            }
+        }
+    }
+
+    /// Tools removed or brought back by undo show up as off or on
+    fn follow_tools(&mut self, entities: &Entities, hand_cam: Option<Entity>, transform_tool: Option<Entity>) {
+        for (showing, tool_id, existing) in [
+            (&mut self.hand_cam_showing, &mut self.hand_cam_id, hand_cam),
+            (&mut self.splat_transform_showing, &mut self.splat_transform_id, transform_tool),
+        ] {
+            if tool_id.is_some_and(|id| !entities.contains(id)) {
+                *tool_id = None;
+            }
+            if tool_id.is_none() {
+                *tool_id = existing;
+            }
+            *showing = tool_id.is_some();
+        }
+    }
 }
 
 
 
+/// Menu sections that live in other modules
+#[derive(SystemParam)]
+struct MenuControls<'w, 's> {
+    history: HistoryControls<'w>,
+    passthrough: PassthroughControls<'w>,
+    refresh_rate: RefreshRateControls<'w>,
+    world_scale: WorldScaleControls<'w>,
+    windows: WindowControls<'w>,
+    entities: &'w Entities,
+    hand_cams: Query<'w, 's, Entity, With<HandCamTool>>,
+    transform_tools: Query<'w, 's, Entity, With<SplatTransformTool>>,
+}
+
 fn main_menu_ui(
-    mut ctxs: Query<(&mut bevy_egui::EguiContext, &mut MainMenu)>,
+    mut menus: Query<(Entity, &mut MainMenu)>,
+    mut contexts: WindowContexts,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    query_transforms: Query<&Transform, With<SplatTransformTool>>,
     mut query_gaussian_settings: Query<&mut GaussianCloudSettings>,
-    xr_cams: Query<Entity, (With<XrCamera>, Without<GaussianCamera>)>,
-    xr_cams2: Query<Entity, (With<XrCamera>, With<GaussianCamera>)>,
+    mut xr_gaussian_settings: ResMut<XrGaussianCameraSettings>,
     
     mut images: ResMut<Assets<Image>>,
+    mut chosen_files: EventReader<SplatFileChosen>,
+    mut selected: ResMut<SelectedSplat>,
+    mut scene_events: EventWriter<SceneFileEvent>,
+    mut shutter: EventWriter<HandCamShutter>,
+    mut controls: MenuControls,
+    grades: Query<&SplatGrade>,
 
     mut sms: Local<SplatMenuSettings>,
 
 ) {
-    for (mut ctx, mut menu) in ctxs.iter_mut() {
+    sms.forget_closed_windows(controls.entities);
+    sms.follow_tools(
+        controls.entities,
+        controls.hand_cams.iter().next(),
+        controls.transform_tools.iter().next(),
+    );
+
+    for (menu_entity, mut menu) in menus.iter_mut() {
         
-        let ctx: &mut EguiContext = &mut ctx;
+        let Some(ui_ctx) = contexts.get(menu_entity) else {
+            continue;
+        };
 
-        ctx.get_mut().set_style(
+        ui_ctx.ctx.set_style(
 			Arc::new(egui_aesthetix::themes::NordLight).custom_style(),
 		);
         
-        bevy_egui::egui::Window::new("Main Menu")
+        ui_ctx.window("Main Menu")
         .resizable(false)
         //.default_size([4000.0, 4000.0])
-        .movable(false)
         .scroll(true)
-        .show(ctx.get_mut(), |ui| {
+        .show(&ui_ctx.ctx, |ui| {
 
             //ui.allocate_space([512.0,512.0].into());
 
@@ -125,88 +212,200 @@ fn main_menu_ui(
             ui.label("Splat Name");
             ui.add(egui::TextEdit::singleline(&mut sms.temp_gaus_name));
 
-            if ui.button("splat fix for vr?").clicked() {
-                add_guassian_to_xr(
+            let mut show_in_headset = xr_gaussian_settings.enabled;
+            if ui.checkbox(&mut show_in_headset, "Show splats in headset").changed() {
+                xr_gaussian_settings.enabled = show_in_headset;
+            }
+
+            ui.collapsing("Passthrough", |ui| controls.passthrough.ui(ui));
+            ui.collapsing("Refresh Rate", |ui| controls.refresh_rate.ui(ui));
+            ui.collapsing("World Scale", |ui| controls.world_scale.ui(ui));
+            controls.windows.ui(ui);
+
+            if ui.button("Load Splat").clicked() {
+                if !sms.temp_gaus_name.is_empty() {
+                    menu.splat_file_name = (sms.temp_gaus_name.clone()).to_string();
+                }
+                let concat_string = format!("{}{}", &menu.base_file_path, &menu.splat_file_name);
+                selected.0 = Some(spawn_splat(
                     &mut commands,
-                    &xr_cams
+                    &asset_server,
+                    &concat_string,
+                    default_splat_settings(),
+                ));
+            }
+
+            ui.toggle_value(&mut sms.file_browser_showing, "Browse Files");
+            if sms.file_browser_showing && sms.file_browser_window_id == None {
+                let file_browser_window = commands.spawn((
+                    Name::new("File Browser".to_string()),
+                    FileBrowserWS::default(),
+                )).id();
+
+                spawn_ui_window(
+                    &mut commands,
+                    file_browser_window,
+                    Vec3::new(1.1, 2.0, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
                 );
+
+                sms.file_browser_window_id = Some(file_browser_window);
             }
 
-            if ui.button("undo vr fix").clicked() {
-                remove_gaussian_from_xr(&mut commands, &xr_cams2);
+            if !sms.file_browser_showing && sms.file_browser_window_id.is_some() {
+                if let Some(file_browser_id) = sms.file_browser_window_id {
+                    commands.entity(file_browser_id).despawn_recursive();
+                    sms.file_browser_window_id = None;
+                }
             }
 
-            if ui.button("Load Splat").clicked() {
-                menu.splat_file_name = (sms.temp_gaus_name.clone()).to_string();
+            // A file picked in the browser is loaded next to the others
+            for chosen in chosen_files.read() {
+                let (base, name) = split_asset_path(&chosen.asset_path);
+                menu.base_file_path = base;
+                menu.splat_file_name = name;
+
+                selected.0 = Some(spawn_splat(
+                    &mut commands,
+                    &asset_server,
+                    &chosen.asset_path,
+                    default_splat_settings(),
+                ));
             }
 
             ui.label(format!("File Path: {}{}", &menu.base_file_path, &menu.splat_file_name));
 
-            ui.toggle_value(&mut sms.splat_showing, "toggle splats");
-            if sms.splat_showing && menu.splat_id == None  {
-
-                let concat_string = format!("{}{}", &menu.base_file_path, &menu.splat_file_name);
+            if sms.scene_path.is_empty() {
+                sms.scene_path = default_scene_path().to_string_lossy().to_string();
+            }
+            ui.label("Scene File");
+            ui.add(egui::TextEdit::singleline(&mut sms.scene_path));
+            ui.horizontal(|ui| {
+                if ui.button("Save Scene").clicked() {
+                    scene_events.send(SceneFileEvent::Save(sms.scene_path.clone().into()));
+                }
+                if ui.button("Load Scene").clicked() {
+                    scene_events.send(SceneFileEvent::Load(sms.scene_path.clone().into()));
+                }
+            });
 
+            controls.history.ui(ui);
 
-                let gaussian_entity = commands.spawn((
-                    GaussianSplattingBundle {
-                        cloud: asset_server.load(concat_string),
-                        settings: GaussianCloudSettings {
-                            aabb: false,
-                            global_opacity: 1.0,
-                            global_scale: 1.0,
-                            transform: Transform::from_xyz(0.0, 0.0, 0.0).with_rotation(Quat::from_rotation_z(std::f32::consts::PI)),
-                            opacity_adaptive_radius: false,
-                            ..Default::default()
-                        },
-                        visibility: Visibility::Visible,
-                        ..Default::default()
-                    },
-                    GaussianMarker {
-                        prev_visibility: Visibility::Visible,
-                    },
-                    Name::new("Gaussian"),
-                    //RenderLayers::layer(2),
-                    //RenderLayers::layer(1),
+            ui.toggle_value(&mut sms.splat_list_showing, "Splat List");
+            if sms.splat_list_showing && sms.splat_list_window_id == None {
+                let splat_list_window = commands.spawn((
+                    Name::new("Splat List".to_string()),
+                    SplatListWS,
                 )).id();
 
-                menu.splat_id = Some(gaussian_entity);
+                spawn_ui_window(
+                    &mut commands,
+                    splat_list_window,
+                    Vec3::new(-1.1, 2.0, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
+
+                sms.splat_list_window_id = Some(splat_list_window);
             }
-            
-            if !sms.splat_showing && menu.splat_id.is_some() {
-                match menu.splat_id {
-                    Some(splat_id) => {
-                        commands.entity(splat_id).despawn_recursive();
-                        menu.splat_id = None;
-                    },
-                    None => {},
+
+            if !sms.splat_list_showing && sms.splat_list_window_id.is_some() {
+                if let Some(splat_list_id) = sms.splat_list_window_id {
+                    commands.entity(splat_list_id).despawn_recursive();
+                    sms.splat_list_window_id = None;
                 }
-                
             }
 
+            ui.toggle_value(&mut sms.crop_showing, "Crop Boxes");
+            if sms.crop_showing && sms.crop_window_id == None {
+                let crop_window = commands.spawn((
+                    Name::new("Crop Boxes".to_string()),
+                    CropWS,
+                )).id();
 
-            let slider = ui.add(egui::Slider::new(&mut sms.splat_opacity, 0.0..=1.0).text("Opacity"));
-            
-            if slider.changed() {
-                if let Some(splat_entity) = menu.splat_id {
-                    if let Ok(mut settings) = query_gaussian_settings.get_mut(splat_entity) {
-                        settings.global_opacity = sms.splat_opacity;
-                    }
+                spawn_ui_window(
+                    &mut commands,
+                    crop_window,
+                    Vec3::new(-1.1, 0.5, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
+
+                sms.crop_window_id = Some(crop_window);
+            }
+
+            if !sms.crop_showing && sms.crop_window_id.is_some() {
+                if let Some(crop_id) = sms.crop_window_id {
+                    commands.entity(crop_id).despawn_recursive();
+                    sms.crop_window_id = None;
                 }
             }
 
+            ui.toggle_value(&mut sms.eraser_showing, "Eraser");
+            if sms.eraser_showing && sms.eraser_window_id == None {
+                let eraser_window = commands.spawn((
+                    Name::new("Eraser".to_string()),
+                    EraserWS::default(),
+                )).id();
+
+                spawn_ui_window(
+                    &mut commands,
+                    eraser_window,
+                    Vec3::new(-1.1, 1.3, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
+
+                sms.eraser_window_id = Some(eraser_window);
+            }
+
+            if !sms.eraser_showing && sms.eraser_window_id.is_some() {
+                if let Some(eraser_id) = sms.eraser_window_id {
+                    commands.entity(eraser_id).despawn_recursive();
+                    sms.eraser_window_id = None;
+                }
+            }
 
-            let slider = ui.add(egui::Slider::new(&mut sms.splat_size_scale, 0.0..=1.0).text("Splat Size"));
+            // Locomotion only runs with controllers
+            #[cfg(feature = "native")]
+            {
+                ui.toggle_value(&mut sms.locomotion_showing, "Locomotion");
+                if sms.locomotion_showing && sms.locomotion_window_id == None {
+                    let locomotion_window = commands.spawn((
+                        Name::new("Locomotion".to_string()),
+                        LocomotionWS,
+                    )).id();
+
+                    spawn_ui_window(
+                        &mut commands,
+                        locomotion_window,
+                        Vec3::new(1.1, 0.3, -0.5),
+                        Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                    );
+
+                    sms.locomotion_window_id = Some(locomotion_window);
+                }
 
-            if slider.changed() {
-                if let Some(splat_entity) = menu.splat_id {
-                    if let Ok(mut settings) = query_gaussian_settings.get_mut(splat_entity) {
-                        let mut prev_transform = settings.transform.clone();
-                        let scale = Vec3::splat(sms.splat_size_scale);
-                        settings.transform = prev_transform.with_scale(scale);
+                if !sms.locomotion_showing && sms.locomotion_window_id.is_some() {
+                    if let Some(locomotion_id) = sms.locomotion_window_id {
+                        commands.entity(locomotion_id).despawn_recursive();
+                        sms.locomotion_window_id = None;
                     }
                 }
             }
+
+            match selected.0.and_then(|e| query_gaussian_settings.get_mut(e).ok()) {
+                Some(mut settings) => splat_settings_ui(ui, &mut settings),
+                None => {
+                    ui.label("Select a splat to adjust it");
+                }
+            }
+
+            if let Some(splat_entity) = selected.0 {
+                ui.collapsing("Colour", |ui| {
+                    let grade = grades.get(splat_entity).copied().unwrap_or_default();
+                    if let Some(grade) = grade_ui(ui, &grade) {
+                        commands.entity(splat_entity).insert(grade);
+                    }
+                });
+            }
             
             ui.toggle_value(&mut sms.inspector_showing, "Toggle Inspector");
             if sms.inspector_showing && sms.inspector_window_id == None {
@@ -216,14 +415,12 @@ fn main_menu_ui(
                     
                 )).id();
 
-                commands.push(SpawnSpatialEguiWindowCommand {
-                    target_entity: Some(inspector_window),
-                    position: Vec3::new(0.0, 1.0, -0.5),
-                    rotation: Quat::IDENTITY,
-                    resolution: UVec2::splat(512),
-                    height: 1.0,
-                    unlit: true,
-                });
+                spawn_ui_window(
+                    &mut commands,
+                    inspector_window,
+                    Vec3::new(0.0, 1.0, -0.5),
+                    Quat::IDENTITY,
+                );
 
                 sms.inspector_window_id = Some(inspector_window);
             }
@@ -261,70 +458,100 @@ fn main_menu_ui(
                     None => {},
                 }
             }
-            
-
-            ui.toggle_value(&mut sms.splat_transform_showing, "Toggle Splat Transform Tool");
 
-            if sms.splat_transform_showing && sms.splat_transform_id == None {
-                if let Some(splat_entity) = menu.splat_id {
-                    if let Ok(gaussian_settings) = query_gaussian_settings.get(splat_entity) {
-                        // Cache the splat's transform
-                        menu.cached_transform = Some(gaussian_settings.transform.clone());
-                    }
+            ui.horizontal(|ui| {
+                if ui.add_enabled(sms.hand_cam_id.is_some(), egui::Button::new("Take Photo")).clicked() {
+                    shutter.send(HandCamShutter);
                 }
-                
+                ui.toggle_value(&mut sms.gallery_showing, "Gallery");
+                ui.toggle_value(&mut sms.hand_cam_settings_showing, "Lens");
+                ui.toggle_value(&mut sms.camera_path_showing, "Camera Path");
+            });
+
+            if sms.camera_path_showing && sms.camera_path_window_id == None {
+                let camera_path_window = commands.spawn((
+                    Name::new("Camera Path".to_string()),
+                    CameraPathWS::default(),
+                )).id();
 
-                let sphere_mesh = meshes.add(Sphere::new(0.3));
+                spawn_ui_window(
+                    &mut commands,
+                    camera_path_window,
+                    Vec3::new(0.0, 0.5, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
 
-                let mut initial_transform = Transform::from_xyz(0.5, 0.5, 0.0);
-                if let Some(cached_transform) = menu.cached_transform.clone() {
-                    initial_transform = cached_transform; // Use cached transform if available
+                sms.camera_path_window_id = Some(camera_path_window);
+            }
+
+            if !sms.camera_path_showing && sms.camera_path_window_id.is_some() {
+                if let Some(camera_path_id) = sms.camera_path_window_id {
+                    commands.entity(camera_path_id).despawn_recursive();
+                    sms.camera_path_window_id = None;
                 }
+            }
 
+            if sms.hand_cam_settings_showing && sms.hand_cam_settings_window_id == None {
+                let settings_window = commands.spawn((
+                    Name::new("Hand Cam Settings".to_string()),
+                    HandCamSettingsWS,
+                )).id();
 
-                let splat_t_id = commands.spawn((
-                    PbrBundle {
-                        mesh: sphere_mesh.clone(),
-                        material: materials.add(StandardMaterial {
-                            base_color: Srgba::hex("#ffd891").unwrap().into(),
-                            unlit: true,
-                            ..default()
-                        }),
-                        transform: initial_transform,
-                        ..default()
-                    },
-                    Name::new("Splat Transform Tool"),
+                spawn_ui_window(
+                    &mut commands,
+                    settings_window,
+                    Vec3::new(-1.1, 1.0, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
+
+                sms.hand_cam_settings_window_id = Some(settings_window);
+            }
 
-                    SplatTransformTool,
-                    //bevy_transform_gizmo::GizmoTransformable,
-                    InputHandler::new(grabbing::capture_condition),
-                    Field::Sphere(0.3),
-                    Grabble,
+            if !sms.hand_cam_settings_showing && sms.hand_cam_settings_window_id.is_some() {
+                if let Some(settings_id) = sms.hand_cam_settings_window_id {
+                    commands.entity(settings_id).despawn_recursive();
+                    sms.hand_cam_settings_window_id = None;
+                }
+            }
+
+            if sms.gallery_showing && sms.gallery_window_id == None {
+                let gallery_window = commands.spawn((
+                    Name::new("Hand Cam Gallery".to_string()),
+                    HandCamGalleryWS::new(),
                 )).id();
 
-                sms.splat_transform_id = Some(splat_t_id);
+                spawn_ui_window(
+                    &mut commands,
+                    gallery_window,
+                    Vec3::new(1.1, 1.0, -0.5),
+                    Quat::from_axis_angle(Vec3::new(0.,1.,0.), 3.1415),
+                );
 
+                sms.gallery_window_id = Some(gallery_window);
             }
 
-            if sms.splat_transform_showing && sms.splat_transform_id.is_some(){
-                
-                if let Some(trans_entity) = sms.splat_transform_id {
-
-                    if let Ok(trans_tool) = query_transforms.get(trans_entity) {
-                        // Cache the splat's transform
-                        menu.cached_transform = Some(*trans_tool);
+            if !sms.gallery_showing && sms.gallery_window_id.is_some() {
+                if let Some(gallery_id) = sms.gallery_window_id {
+                    commands.entity(gallery_id).despawn_recursive();
+                    sms.gallery_window_id = None;
+                }
+            }
+            
 
+            ui.toggle_value(&mut sms.splat_transform_showing, "Toggle Splat Transform Tool");
 
-                        if sms.splat_showing && menu.splat_id.is_some() {
-                            if let Some(splat_id) = menu.splat_id {
-                                if let Ok(mut splat_settings) = query_gaussian_settings.get_mut(splat_id) {
-                                    splat_settings.transform.translation = trans_tool.translation;
-                                    splat_settings.transform.rotation = trans_tool.rotation;
-                                }
-                            }
-                        }
-                    }
+            if sms.splat_transform_showing && sms.splat_transform_id == None {
+                let mut initial_transform = Transform::from_xyz(0.5, 0.5, 0.0);
+                if let Some(settings) = selected.0.and_then(|e| query_gaussian_settings.get(e).ok()) {
+                    initial_transform = settings.transform;
                 }
+
+                sms.splat_transform_id = Some(spawn_transform_gizmo(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    initial_transform,
+                ));
             }
 
             if !sms.splat_transform_showing && sms.splat_transform_id.is_some() {
@@ -342,8 +569,37 @@ fn main_menu_ui(
 }
 
 
+/// Root of the hand cam hierarchy spawned by [`spawn_hand_cam`]
+#[derive(Component)]
+pub struct HandCamTool;
+
+/// The grabbable camera body
+#[derive(Component)]
+pub struct HandCamBox;
+
+/// The grabbable preview screen
+#[derive(Component)]
+pub struct HandCamScreen;
+
+/// The plane on the preview screen showing the lens's image
 #[derive(Component)]
-struct SplatTransformTool;
+pub struct HandCamScreenDisplay;
+
+/// Where `camera.glb` sits inside the hand cam box
+const HAND_CAM_MODEL_OFFSET: Vec3 = Vec3::new(0.035, 0.185, -0.492);
+
+/// The camera inside the hand cam, photos are taken from its point of view
+#[derive(Component)]
+pub struct HandCamLens;
+
+
+/// Splits `source://dir/file.ply` into `("source://dir/", "file.ply")`.
+fn split_asset_path(asset_path: &str) -> (String, String) {
+    match asset_path.rfind('/') {
+        Some(i) => (asset_path[..=i].to_string(), asset_path[i + 1..].to_string()),
+        None => (String::new(), asset_path.to_string()),
+    }
+}
 
 
 /* 
@@ -370,6 +626,70 @@ pub fn restore_visibility_system(
 
 
 
+/// Opacity, world scale and gaussian size of a splat. The controls read the splat's current settings every
+/// frame, so they follow the transform tool and everything else that changes them.
+fn splat_settings_ui(ui: &mut egui::Ui, settings: &mut Mut<GaussianCloudSettings>) {
+    // Only written when the user changes them, the undo history and transform tool watch for
+    // changes and values loaded from outside the slider ranges are kept as they are
+    let mut opacity = settings.global_opacity;
+    if ui
+        .add(
+            egui::Slider::new(&mut opacity, 0.0..=1.0)
+                .clamping(egui::SliderClamping::Edits)
+                .text("Opacity"),
+        )
+        .changed()
+    {
+        settings.global_opacity = opacity;
+    }
+
+    let mut scale = settings.transform.scale.x;
+    let mut scale_changed = false;
+    ui.horizontal(|ui| {
+        scale_changed |= ui
+            .add(
+                egui::Slider::new(&mut scale, MIN_WORLD_SCALE..=MAX_WORLD_SCALE)
+                    .logarithmic(true)
+                    .clamping(egui::SliderClamping::Edits)
+                    .show_value(false)
+                    .text("World Scale"),
+            )
+            .changed();
+        scale_changed |= ui
+            .add(
+                egui::DragValue::new(&mut scale)
+                    .range(MIN_WORLD_SCALE..=MAX_WORLD_SCALE)
+                    .clamp_existing_to_range(false)
+                    .speed(0.01)
+                    .suffix("x"),
+            )
+            .changed();
+        if ui.button("1:1").clicked() {
+            scale = 1.0;
+            scale_changed = true;
+        }
+    });
+    if scale_changed && settings.transform.scale.x != 0.0 {
+        // Scales all axes by the same factor, a non-uniform scale keeps its proportions
+        let factor = scale / settings.transform.scale.x;
+        settings.transform.scale *= factor;
+    }
+
+    let mut gaussian_size = settings.global_scale;
+    if ui
+        .add(
+            egui::Slider::new(&mut gaussian_size, MIN_GAUSSIAN_SIZE..=MAX_GAUSSIAN_SIZE)
+                .logarithmic(true)
+                .clamping(egui::SliderClamping::Edits)
+                .text("Gaussian Size"),
+        )
+        .changed()
+    {
+        settings.global_scale = gaussian_size;
+    }
+}
+
+
 pub fn spawn_hand_cam(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
@@ -381,6 +701,7 @@ pub fn spawn_hand_cam(
     let hand_cam_box = commands.spawn((
         Transform::from_xyz(0.0, 0.0, 0.0),
         Name::new("Hand Cam Box".to_string()),
+        HandCamBox,
         Visibility::Visible,
         InputHandler::new(grabbing::capture_condition),
         Field::Sphere(0.1),
@@ -394,7 +715,7 @@ pub fn spawn_hand_cam(
     let hand_cam_model = commands.spawn((
         SceneBundle {
             scene: camera_glb.clone(),
-            transform: Transform::from_xyz(0.035, 0.185, -0.492 ),
+            transform: Transform::from_translation(HAND_CAM_MODEL_OFFSET),
             ..Default::default()
         },
         Name::new("Hand Cam Model".to_string()), 
@@ -440,9 +761,39 @@ pub fn spawn_hand_cam(
             ..Default::default()
         },
         Name::new("Hand Cam Real".to_string()),   
+        HandCamLens,
         GaussianCamera { warmup: true },
     )).id();
 
+    let shutter_button = spawn_hand_cam_button(
+        commands,
+        meshes,
+        materials,
+        HandCamAction::Photo,
+        Vec3::new(0.0, 0.12, 0.0),
+    );
+    let record_button = spawn_hand_cam_button(
+        commands,
+        meshes,
+        materials,
+        HandCamAction::ToggleRecording,
+        Vec3::new(0.0, 0.12, 0.07),
+    );
+    let keyframe_button = spawn_hand_cam_button(
+        commands,
+        meshes,
+        materials,
+        HandCamAction::Keyframe,
+        Vec3::new(0.0, 0.12, -0.07),
+    );
+    // Parented to the model so it shows up on the camera body, placed relative to the box
+    let recording_light = spawn_recording_light(
+        commands,
+        meshes,
+        materials,
+        Vec3::new(0.05, 0.09, 0.0) - HAND_CAM_MODEL_OFFSET,
+    );
+
     let material_handle = materials.add(StandardMaterial{
         base_color_texture: Some(image_handle.clone()),
         reflectance: 0.02,
@@ -458,6 +809,7 @@ pub fn spawn_hand_cam(
                 .looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
         },
+        HandCamScreenDisplay,
     )).id();
 
     let screen_model = commands.spawn((
@@ -467,6 +819,7 @@ pub fn spawn_hand_cam(
     let screen_box = commands.spawn((
         Transform::from_xyz(0.0, 0.0, 0.0),
         Name::new("Screen Box".to_string()),
+        HandCamScreen,
         Visibility::Visible,
         Field::Cuboid(Cuboid::from_size(Vec3::new(1.,1.,0.2))),
         Grabble,
@@ -478,6 +831,8 @@ pub fn spawn_hand_cam(
     let hand_cam_tool_box = commands.spawn((
         Transform::from_xyz(0.0, 0.0, 0.0),
         Name::new("Hand Cam Tool".to_string()),
+        HandCamTool,
+        ScalesWithUser,
         Visibility::Visible,
         GlobalTransform::default(),
         InheritedVisibility::default(),
@@ -491,8 +846,13 @@ pub fn spawn_hand_cam(
     commands.entity(hand_cam_box).push_children(&[
         hand_cam_model,
         hand_cam_real,
+        shutter_button,
+        record_button,
+        keyframe_button,
     ]);
 
+    commands.entity(hand_cam_model).add_child(recording_light);
+
     commands.entity(hand_cam_tool_box).push_children(&[
         hand_cam_box,
         screen_box,
@@ -500,24 +860,3 @@ pub fn spawn_hand_cam(
 
     return hand_cam_tool_box
 }
-
-pub fn add_guassian_to_xr(
-    commands: &mut Commands,
-    xr_cams_entity: &Query<Entity, (With<XrCamera>, Without<GaussianCamera>)>,
-) {
-    for xr_cam in xr_cams_entity.iter() {
-        commands.entity(xr_cam).insert((
-            GaussianCamera { warmup: true},
-            Name::new("Xr Cam"),
-            //Tonemapping::None, makes thing look wonko
-        ));
-    }
-}
-pub fn remove_gaussian_from_xr(
-    commands: &mut Commands,
-    xr_cams_entity: &Query<Entity, (With<XrCamera>, With<GaussianCamera>)>,
-) {
-    for xr_cam in xr_cams_entity.iter() {
-        commands.entity(xr_cam).remove::<GaussianCamera>();
-    }
-}
\ No newline at end of file
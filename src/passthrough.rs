@@ -0,0 +1,371 @@
+// Passthrough and environment blend mode, with a backdrop for when the room is hidden
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::render_resource::Face;
+use bevy_egui::egui;
+use bevy_mod_openxr::resources::{OxrGraphicsInfo, OxrInstance, OxrSystemId};
+use bevy_mod_openxr::types::{EnvironmentBlendMode, ViewConfigurationType};
+use serde::{Deserialize, Serialize};
+
+use crate::scene_file::{save_settings_file, RonFile, SettingsFile};
+
+
+const BACKDROP_RADIUS: f32 = 500.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The room is hidden behind the backdrop
+    Opaque,
+    /// Passthrough shows wherever nothing is drawn
+    AlphaBlend,
+    /// Light from the scene is added to the room, for see-through displays
+    Additive,
+}
+
+impl BlendMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BlendMode::Opaque => "Opaque",
+            BlendMode::AlphaBlend => "Passthrough",
+            BlendMode::Additive => "Additive",
+        }
+    }
+
+    fn environment(&self) -> EnvironmentBlendMode {
+        match self {
+            BlendMode::Opaque => EnvironmentBlendMode::OPAQUE,
+            BlendMode::AlphaBlend => EnvironmentBlendMode::ALPHA_BLEND,
+            BlendMode::Additive => EnvironmentBlendMode::ADDITIVE,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Backdrop {
+    Solid([f32; 3]),
+    Gradient { top: [f32; 3], bottom: [f32; 3] },
+}
+
+/// Saved whenever it changes, so the last choice is back on the next launch
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct PassthroughSettings {
+    pub mode: BlendMode,
+    /// Shown in [`BlendMode::Opaque`]
+    pub backdrop: Backdrop,
+}
+
+impl Default for PassthroughSettings {
+    fn default() -> Self {
+        Self {
+            mode: BlendMode::AlphaBlend,
+            backdrop: Backdrop::Gradient {
+                top: [0.45, 0.6, 0.8],
+                bottom: [0.08, 0.08, 0.1],
+            },
+        }
+    }
+}
+
+impl RonFile for PassthroughSettings {}
+
+impl SettingsFile for PassthroughSettings {
+    const FILE_NAME: &'static str = "view.ron";
+}
+
+/// What the runtime can do, filled in once the XR instance is up. Without one only opaque works.
+#[derive(Resource, Clone, Debug)]
+pub struct SupportedBlendModes {
+    pub modes: Vec<BlendMode>,
+    /// `XR_FB_passthrough` draws the room under an opaque frame with a transparent clear
+    pub fb_passthrough: bool,
+    queried: bool,
+}
+
+impl Default for SupportedBlendModes {
+    fn default() -> Self {
+        Self {
+            modes: vec![BlendMode::Opaque],
+            fb_passthrough: false,
+            queried: false,
+        }
+    }
+}
+
+/// How a requested mode is actually shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolvedBlend {
+    pub mode: BlendMode,
+    /// What the frames are submitted with
+    pub environment: BlendMode,
+    pub transparent_clear: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct ActiveBlend(pub Option<ResolvedBlend>);
+
+/// Additive falls back to passthrough, passthrough to opaque
+pub fn resolve_blend_mode(requested: BlendMode, supported: &SupportedBlendModes) -> ResolvedBlend {
+    match requested {
+        BlendMode::Additive if supported.modes.contains(&BlendMode::Additive) => ResolvedBlend {
+            mode: BlendMode::Additive,
+            environment: BlendMode::Additive,
+            transparent_clear: true,
+        },
+        BlendMode::Additive | BlendMode::AlphaBlend if supported.modes.contains(&BlendMode::AlphaBlend) => ResolvedBlend {
+            mode: BlendMode::AlphaBlend,
+            environment: BlendMode::AlphaBlend,
+            transparent_clear: true,
+        },
+        BlendMode::Additive | BlendMode::AlphaBlend if supported.fb_passthrough => ResolvedBlend {
+            mode: BlendMode::AlphaBlend,
+            environment: BlendMode::Opaque,
+            transparent_clear: true,
+        },
+        _ => ResolvedBlend {
+            mode: BlendMode::Opaque,
+            environment: BlendMode::Opaque,
+            transparent_clear: false,
+        },
+    }
+}
+
+/// Inside out sphere behind everything in opaque mode
+#[derive(Component)]
+pub struct BackdropSphere;
+
+
+pub struct PassthroughPlugin;
+
+impl Plugin for PassthroughPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PassthroughSettings::load_or_default())
+            .init_resource::<SupportedBlendModes>()
+            .init_resource::<ActiveBlend>()
+            .add_systems(Startup, spawn_backdrop)
+            .add_systems(
+                Update,
+                (
+                    query_supported_blend_modes,
+                    apply_passthrough_settings,
+                    save_settings_file::<PassthroughSettings>,
+                ).chain(),
+            );
+    }
+}
+
+
+fn spawn_backdrop(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Sphere::new(BACKDROP_RADIUS).mesh().uv(32, 18)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                cull_mode: Some(Face::Front),
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Name::new("Backdrop"),
+        BackdropSphere,
+    ));
+}
+
+fn query_supported_blend_modes(
+    mut supported: ResMut<SupportedBlendModes>,
+    instance: Option<Res<OxrInstance>>,
+    system_id: Option<Res<OxrSystemId>>,
+) {
+    if supported.queried {
+        return;
+    }
+    let (Some(instance), Some(system_id)) = (instance, system_id) else {
+        return;
+    };
+    supported.queried = true;
+    // Requested in main for native builds
+    supported.fb_passthrough = cfg!(feature = "native");
+
+    match instance.enumerate_environment_blend_modes(**system_id, ViewConfigurationType::PRIMARY_STEREO) {
+        Ok(modes) => {
+            supported.modes = [BlendMode::Opaque, BlendMode::AlphaBlend, BlendMode::Additive]
+                .into_iter()
+                .filter(|mode| modes.contains(&mode.environment()))
+                .collect();
+            info!("environment blend modes: {:?}", supported.modes);
+        }
+        Err(e) => warn!("couldn't query environment blend modes: {}", e),
+    }
+}
+
+fn apply_passthrough_settings(
+    settings: Res<PassthroughSettings>,
+    supported: Res<SupportedBlendModes>,
+    mut active: ResMut<ActiveBlend>,
+    mut clear_color: ResMut<ClearColor>,
+    graphics_info: Option<ResMut<OxrGraphicsInfo>>,
+    mut backdrops: Query<(&mut Visibility, &Handle<Mesh>, &Handle<StandardMaterial>), With<BackdropSphere>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    new_backdrops: Query<(), Added<BackdropSphere>>,
+) {
+    if !settings.is_changed() && !supported.is_changed() && new_backdrops.is_empty() {
+        return;
+    }
+    let resolved = resolve_blend_mode(settings.mode, &supported);
+    if resolved.mode != settings.mode {
+        warn!("{} isn't supported here, using {}", settings.mode.label(), resolved.mode.label());
+    }
+    active.0 = Some(resolved);
+
+    if let Some(mut graphics_info) = graphics_info {
+        graphics_info.blend_mode = resolved.environment.environment();
+    }
+
+    let gradient = match (resolved.transparent_clear, settings.backdrop) {
+        (true, _) => {
+            clear_color.0 = Color::NONE;
+            None
+        }
+        (false, Backdrop::Solid(color)) => {
+            clear_color.0 = Color::srgb_from_array(color);
+            None
+        }
+        (false, Backdrop::Gradient { top, bottom }) => {
+            clear_color.0 = Color::srgb_from_array(bottom);
+            Some((top, bottom))
+        }
+    };
+
+    for (mut visibility, mesh, material) in backdrops.iter_mut() {
+        let Some((top, bottom)) = gradient else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = Color::WHITE;
+        }
+        let Some(mesh) = meshes.get_mut(mesh) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            continue;
+        };
+        let colors: Vec<[f32; 4]> = positions
+            .iter()
+            .map(|p| {
+                let t = (p[1] / BACKDROP_RADIUS * 0.5 + 0.5).clamp(0.0, 1.0);
+                let color = Vec3::from_array(bottom).lerp(Vec3::from_array(top), t);
+                color.extend(1.0).to_array()
+            })
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+}
+
+
+/// Blend mode and backdrop pickers for menus
+#[derive(SystemParam)]
+pub struct PassthroughControls<'w> {
+    settings: ResMut<'w, PassthroughSettings>,
+    supported: Res<'w, SupportedBlendModes>,
+    active: Res<'w, ActiveBlend>,
+}
+
+impl PassthroughControls<'_> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        // Edit a copy so the settings are only saved when something changed
+        let mut settings = *self.settings;
+
+        ui.horizontal(|ui| {
+            for mode in [BlendMode::Opaque, BlendMode::AlphaBlend, BlendMode::Additive] {
+                ui.selectable_value(&mut settings.mode, mode, mode.label());
+            }
+        });
+        if let Some(active) = self.active.0.filter(|a| a.mode != settings.mode) {
+            ui.label(format!("{} isn't supported here, using {}", settings.mode.label(), active.mode.label()));
+        }
+
+        let shows_backdrop = !resolve_blend_mode(settings.mode, &self.supported).transparent_clear;
+        if shows_backdrop {
+            let mut gradient = matches!(settings.backdrop, Backdrop::Gradient { .. });
+            ui.horizontal(|ui| {
+                ui.label("Backdrop");
+                ui.selectable_value(&mut gradient, false, "Solid");
+                ui.selectable_value(&mut gradient, true, "Gradient");
+            });
+            settings.backdrop = match (gradient, settings.backdrop) {
+                (false, Backdrop::Gradient { bottom, .. }) => Backdrop::Solid(bottom),
+                (true, Backdrop::Solid(color)) => Backdrop::Gradient { top: color, bottom: color },
+                (_, backdrop) => backdrop,
+            };
+            ui.horizontal(|ui| match &mut settings.backdrop {
+                Backdrop::Solid(color) => {
+                    ui.color_edit_button_rgb(color);
+                }
+                Backdrop::Gradient { top, bottom } => {
+                    ui.label("Top");
+                    ui.color_edit_button_rgb(top);
+                    ui.label("Bottom");
+                    ui.color_edit_button_rgb(bottom);
+                }
+            });
+        }
+
+        if settings != *self.settings {
+            *self.settings = settings;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supported(modes: &[BlendMode], fb_passthrough: bool) -> SupportedBlendModes {
+        SupportedBlendModes {
+            modes: modes.to_vec(),
+            fb_passthrough,
+            queried: true,
+        }
+    }
+
+    #[test]
+    fn unsupported_modes_fall_back() {
+        let quest = supported(&[BlendMode::Opaque], true);
+        let passthrough = resolve_blend_mode(BlendMode::AlphaBlend, &quest);
+        assert_eq!(passthrough.mode, BlendMode::AlphaBlend);
+        assert_eq!(passthrough.environment, BlendMode::Opaque);
+        assert!(passthrough.transparent_clear);
+        assert_eq!(resolve_blend_mode(BlendMode::Additive, &quest).mode, BlendMode::AlphaBlend);
+
+        let desktop = SupportedBlendModes::default();
+        let opaque = resolve_blend_mode(BlendMode::Additive, &desktop);
+        assert_eq!(opaque.mode, BlendMode::Opaque);
+        assert!(!opaque.transparent_clear);
+
+        let see_through = supported(&[BlendMode::Additive], false);
+        assert_eq!(resolve_blend_mode(BlendMode::Additive, &see_through).environment, BlendMode::Additive);
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = PassthroughSettings {
+            mode: BlendMode::Opaque,
+            backdrop: Backdrop::Solid([0.1, 0.2, 0.3]),
+        };
+        let loaded = PassthroughSettings::from_ron(&settings.to_ron().unwrap()).unwrap();
+        assert_eq!(settings, loaded);
+        assert_eq!(PassthroughSettings::from_ron("()").unwrap(), PassthroughSettings::default());
+    }
+}
@@ -0,0 +1,253 @@
+// Display refresh rate of the headset, picked from what the runtime supports
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_mod_openxr::session::OxrSession;
+
+
+pub const RATE_CHOICES: [f32; 4] = [72.0, 80.0, 90.0, 120.0];
+/// Frames this much slower than the refresh rate count as over budget
+const OVER_BUDGET_FACTOR: f32 = 1.15;
+/// How long frames have to stay over budget before the rate is lowered
+const OVER_BUDGET_SECONDS: f32 = 2.0;
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct RefreshRateSettings {
+    /// Applied whenever a session starts, `None` leaves the runtime's default
+    pub requested: Option<f32>,
+    /// Steps down to the next supported rate when frames can't keep up, like with large splats
+    pub auto_lower: bool,
+}
+
+impl Default for RefreshRateSettings {
+    fn default() -> Self {
+        Self {
+            requested: Some(72.0),
+            auto_lower: false,
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct RefreshRateState {
+    /// Empty until a session reports them, or if the runtime can't change rates
+    pub supported: Vec<f32>,
+    pub current: Option<f32>,
+    /// Lowered by [`RefreshRateSettings::auto_lower`], cleared when the user picks a rate
+    pub auto_rate: Option<f32>,
+    pub error: Option<String>,
+    queried: bool,
+    applied: Option<f32>,
+    budget: FrameBudget,
+}
+
+/// Tracks how long frames have been slower than the refresh rate allows
+#[derive(Default, Debug)]
+pub struct FrameBudget {
+    average: f32,
+    over_for: f32,
+}
+
+impl FrameBudget {
+    /// Returns true once frames have been over budget for long enough
+    pub fn update(&mut self, frame_time: f32, rate: f32) -> bool {
+        self.average = if self.average == 0.0 {
+            frame_time
+        } else {
+            self.average + (frame_time - self.average) * 0.1
+        };
+        if self.average > OVER_BUDGET_FACTOR / rate {
+            self.over_for += frame_time;
+        } else {
+            self.over_for = 0.0;
+        }
+        if self.over_for >= OVER_BUDGET_SECONDS {
+            *self = FrameBudget::default();
+            return true;
+        }
+        false
+    }
+}
+
+/// The supported rate nearest to `requested`, or `requested` itself if the runtime didn't list any
+pub fn closest_supported(requested: f32, supported: &[f32]) -> f32 {
+    supported
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - requested).abs().total_cmp(&(b - requested).abs()))
+        .unwrap_or(requested)
+}
+
+/// The next supported rate below `current`
+pub fn lower_rate(current: f32, supported: &[f32]) -> Option<f32> {
+    supported
+        .iter()
+        .copied()
+        .filter(|rate| *rate < current - 0.5)
+        .max_by(|a, b| a.total_cmp(b))
+}
+
+
+pub struct RefreshRatePlugin;
+
+impl Plugin for RefreshRatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RefreshRateSettings>()
+            .init_resource::<RefreshRateState>()
+            .add_systems(Update, (apply_refresh_rate, auto_lower_refresh_rate).chain());
+    }
+}
+
+
+fn apply_refresh_rate(
+    settings: Res<RefreshRateSettings>,
+    mut state: ResMut<RefreshRateState>,
+    session: Option<Res<OxrSession>>,
+) {
+    let Some(session) = session else {
+        // Applied again when the next session starts
+        if state.applied.is_some() || state.queried {
+            state.queried = false;
+            state.applied = None;
+        }
+        return;
+    };
+
+    if !state.queried {
+        state.queried = true;
+        match session.enumerate_display_refresh_rates() {
+            Ok(rates) => {
+                info!("display refresh rates: {:?}", rates);
+                state.supported = rates;
+            }
+            Err(e) => {
+                warn!("couldn't list display refresh rates: {}", e);
+                state.error = Some(format!("Refresh rates unavailable: {}", e));
+            }
+        }
+    }
+    if settings.is_changed() {
+        state.auto_rate = None;
+    }
+
+    let Some(requested) = state.auto_rate.or(settings.requested) else {
+        return;
+    };
+    let rate = closest_supported(requested, &state.supported);
+    if state.applied == Some(rate) {
+        return;
+    }
+    state.applied = Some(rate);
+    match session.request_display_refresh_rate(rate) {
+        Ok(()) => {
+            info!("requested {} Hz", rate);
+            state.error = None;
+        }
+        Err(e) => {
+            warn!("failed to request {} Hz: {}", rate, e);
+            state.error = Some(format!("Couldn't switch to {} Hz: {}", rate, e));
+        }
+    }
+    state.current = session.get_display_refresh_rate().ok();
+}
+
+fn auto_lower_refresh_rate(
+    settings: Res<RefreshRateSettings>,
+    mut state: ResMut<RefreshRateState>,
+    time: Res<Time<Real>>,
+) {
+    if !settings.auto_lower {
+        return;
+    }
+    let Some(current) = state.current.or(state.applied) else {
+        return;
+    };
+    if !state.budget.update(time.delta_seconds(), current) {
+        return;
+    }
+    if let Some(lower) = lower_rate(current, &state.supported) {
+        warn!("frames are over budget at {} Hz, lowering to {} Hz", current, lower);
+        state.auto_rate = Some(lower);
+    }
+}
+
+
+/// Rate picker for menus
+#[derive(SystemParam)]
+pub struct RefreshRateControls<'w> {
+    settings: ResMut<'w, RefreshRateSettings>,
+    state: Res<'w, RefreshRateState>,
+}
+
+impl RefreshRateControls<'_> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.settings.clone();
+
+        ui.horizontal(|ui| {
+            for rate in RATE_CHOICES {
+                let supported = self.state.supported.is_empty()
+                    || self.state.supported.iter().any(|r| (r - rate).abs() < 0.5);
+                ui.add_enabled_ui(supported, |ui| {
+                    ui.selectable_value(&mut settings.requested, Some(rate), format!("{} Hz", rate));
+                });
+            }
+        });
+        ui.checkbox(&mut settings.auto_lower, "Lower when frames can't keep up");
+
+        match (self.state.current, self.state.auto_rate) {
+            (Some(current), Some(_)) => {
+                ui.label(format!("Running at {} Hz, lowered to keep up", current));
+            }
+            (Some(current), None) => {
+                ui.label(format!("Running at {} Hz", current));
+            }
+            _ => {}
+        }
+        if let Some(error) = &self.state.error {
+            ui.label(error);
+        }
+
+        if settings != *self.settings {
+            *self.settings = settings;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_supported_rates() {
+        let quest = [72.0, 90.0, 120.0];
+        assert_eq!(closest_supported(80.0, &quest), 72.0);
+        assert_eq!(closest_supported(90.0, &quest), 90.0);
+        assert_eq!(closest_supported(80.0, &[]), 80.0);
+
+        assert_eq!(lower_rate(120.0, &quest), Some(90.0));
+        assert_eq!(lower_rate(72.0, &quest), None);
+    }
+
+    #[test]
+    fn budget_needs_sustained_slow_frames() {
+        let mut budget = FrameBudget::default();
+        // 90 fps frames at 90 Hz are fine
+        for _ in 0..1000 {
+            assert!(!budget.update(1.0 / 90.0, 90.0));
+        }
+
+        let mut budget = FrameBudget::default();
+        let mut lowered_after = None;
+        for frame in 0..1000 {
+            if budget.update(1.0 / 45.0, 90.0) {
+                lowered_after = Some(frame);
+                break;
+            }
+        }
+        // About two seconds of 45 fps
+        let frames = lowered_after.expect("never over budget");
+        assert!((85..=95).contains(&frames), "{}", frames);
+    }
+}
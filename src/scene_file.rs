@@ -0,0 +1,390 @@
+// Saving and loading splat scenes as RON files
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::gaussian::{default_splat_settings, spawn_splat, GaussianMarker, SelectedSplat, SplatSource};
+use crate::main_menu::{HandCamBox, HandCamScreen};
+use crate::splat_crop::SplatCrop;
+use crate::splat_grade::SplatGrade;
+
+
+/// Transform in a form that doesn't depend on bevy's `serialize` feature.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SceneTransform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl From<Transform> for SceneTransform {
+    fn from(t: Transform) -> Self {
+        Self {
+            translation: t.translation.to_array(),
+            rotation: t.rotation.to_array(),
+            scale: t.scale.to_array(),
+        }
+    }
+}
+
+impl From<SceneTransform> for Transform {
+    fn from(t: SceneTransform) -> Self {
+        Transform {
+            translation: Vec3::from_array(t.translation),
+            rotation: Quat::from_array(t.rotation).normalize(),
+            scale: Vec3::from_array(t.scale),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SceneSplat {
+    pub asset_path: String,
+    pub transform: SceneTransform,
+    pub opacity: f32,
+    pub scale: f32,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    #[serde(default)]
+    pub crop: SplatCrop,
+    #[serde(default)]
+    pub grade: SplatGrade,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct HandCamPlacement {
+    pub camera: SceneTransform,
+    pub screen: SceneTransform,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SceneWindow {
+    pub name: String,
+    pub transform: SceneTransform,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SplatScene {
+    pub splats: Vec<SceneSplat>,
+    #[serde(default)]
+    pub hand_cam: Option<HandCamPlacement>,
+    #[serde(default)]
+    pub windows: Vec<SceneWindow>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RonFile for SplatScene {}
+
+
+/// Saved as pretty printed RON, like scenes, camera paths and settings
+pub trait RonFile: Serialize + DeserializeOwned {
+    fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    fn from_ron(s: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(s)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let ron = self.to_ron().map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, ron).map_err(|e| e.to_string())
+    }
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let s = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_ron(&s).map_err(|e| e.to_string())
+    }
+}
+
+/// Settings resource that is loaded on startup and saved whenever it changes, see
+/// [`save_settings_file`]
+pub trait SettingsFile: RonFile + Resource + Default {
+    /// File name in [`splats_file_path`]
+    const FILE_NAME: &'static str;
+
+    fn path() -> PathBuf {
+        splats_file_path(Self::FILE_NAME)
+    }
+
+    /// The saved settings, or the defaults on the first launch
+    fn load_or_default() -> Self {
+        let path = Self::path();
+        match Self::load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                debug!("no settings at {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Saves `T` to its file after every change
+pub fn save_settings_file<T: SettingsFile>(settings: Res<T>) {
+    // The first change is the load itself
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    let path = T::path();
+    if let Err(e) = settings.save(&path) {
+        error!("failed to save settings to {:?}: {}", path, e);
+    }
+}
+
+/// `name` in the shared Splats folder on the Quest, next to the app elsewhere.
+pub fn splats_file_path(name: &str) -> PathBuf {
+    #[cfg(target_os = "android")]
+    return Path::new("/sdcard/Splats").join(name);
+
+    #[cfg(not(target_os = "android"))]
+    return PathBuf::from(name);
+}
+
+/// Where the main menu saves to unless told otherwise.
+pub fn default_scene_path() -> PathBuf {
+    splats_file_path("scene.ron")
+}
+
+/// `--scene <file>` on the command line, or the `SPLAT_SCENE` env var.
+pub fn startup_scene_path() -> Option<PathBuf> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--scene" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--scene=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os("SPLAT_SCENE").map(PathBuf::from)
+}
+
+
+#[derive(Event, Clone, Debug)]
+pub enum SceneFileEvent {
+    Save(PathBuf),
+    Load(PathBuf),
+}
+
+/// Hand cam placement from a loaded scene, applied once the hand cam is spawned.
+#[derive(Resource, Default)]
+pub struct PendingHandCamPlacement(pub Option<HandCamPlacement>);
+
+
+pub struct SceneFilePlugin;
+
+impl Plugin for SceneFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SceneFileEvent>()
+            .init_resource::<PendingHandCamPlacement>()
+            .add_systems(Startup, load_startup_scene)
+            .add_systems(Update, (handle_scene_file_events, apply_pending_hand_cam).chain());
+    }
+}
+
+
+fn load_startup_scene(mut events: EventWriter<SceneFileEvent>) {
+    if let Some(path) = startup_scene_path() {
+        info!("loading startup scene {:?}", path);
+        events.send(SceneFileEvent::Load(path));
+    }
+}
+
+pub fn handle_scene_file_events(
+    mut events: EventReader<SceneFileEvent>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    splats: Query<
+        (Entity, &SplatSource, &GaussianCloudSettings, &Visibility, Option<&SplatCrop>, Option<&SplatGrade>),
+        With<GaussianMarker>,
+    >,
+    mut hand_cam: Query<&mut Transform, (With<HandCamBox>, Without<HandCamScreen>)>,
+    mut hand_cam_screen: Query<&mut Transform, (With<HandCamScreen>, Without<HandCamBox>)>,
+    mut windows: Query<(&Name, &mut Transform), (With<EguiContext>, Without<HandCamBox>, Without<HandCamScreen>)>,
+    mut selected: ResMut<SelectedSplat>,
+    mut pending_hand_cam: ResMut<PendingHandCamPlacement>,
+) {
+    for event in events.read() {
+        match event {
+            SceneFileEvent::Save(path) => {
+                let scene = SplatScene {
+                    splats: splats
+                        .iter()
+                        .map(|(_, source, settings, visibility, crop, grade)| SceneSplat {
+                            asset_path: source.0.clone(),
+                            transform: settings.transform.into(),
+                            opacity: settings.global_opacity,
+                            scale: settings.global_scale,
+                            visible: *visibility != Visibility::Hidden,
+                            crop: crop.cloned().unwrap_or_default(),
+                            grade: grade.copied().unwrap_or_default(),
+                        })
+                        .collect(),
+                    hand_cam: hand_cam
+                        .get_single()
+                        .ok()
+                        .zip(hand_cam_screen.get_single().ok())
+                        .map(|(camera, screen)| HandCamPlacement {
+                            camera: (*camera).into(),
+                            screen: (*screen).into(),
+                        }),
+                    windows: windows
+                        .iter()
+                        .map(|(name, transform)| SceneWindow {
+                            name: name.to_string(),
+                            transform: (*transform).into(),
+                        })
+                        .collect(),
+                };
+
+                match scene.save(path) {
+                    Ok(()) => info!("saved scene to {:?}", path),
+                    Err(e) => error!("failed to save scene to {:?}: {}", path, e),
+                }
+            }
+            SceneFileEvent::Load(path) => {
+                let scene = match SplatScene::load(path) {
+                    Ok(scene) => scene,
+                    Err(e) => {
+                        error!("failed to load scene {:?}: {}", path, e);
+                        continue;
+                    }
+                };
+
+                for (entity, ..) in splats.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                selected.0 = None;
+
+                for splat in scene.splats.iter() {
+                    let settings = GaussianCloudSettings {
+                        transform: splat.transform.into(),
+                        global_opacity: splat.opacity,
+                        global_scale: splat.scale,
+                        ..default_splat_settings()
+                    };
+                    let entity = spawn_splat(&mut commands, &asset_server, &splat.asset_path, settings);
+                    if !splat.visible {
+                        commands.entity(entity).insert(Visibility::Hidden);
+                    }
+                    if !splat.crop.boxes.is_empty() {
+                        commands.entity(entity).insert(splat.crop.clone());
+                    }
+                    if !splat.grade.is_identity() {
+                        commands.entity(entity).insert(splat.grade);
+                    }
+                    selected.0 = Some(entity);
+                }
+
+                for (name, mut transform) in windows.iter_mut() {
+                    if let Some(window) = scene.windows.iter().find(|w| w.name == name.as_str()) {
+                        *transform = window.transform.into();
+                    }
+                }
+
+                pending_hand_cam.0 = scene.hand_cam;
+                info!("loaded scene {:?} with {} splats", path, scene.splats.len());
+            }
+        }
+    }
+}
+
+/// Moves the hand cam to the placement from the last loaded scene, whenever it exists.
+pub fn apply_pending_hand_cam(
+    mut pending_hand_cam: ResMut<PendingHandCamPlacement>,
+    mut hand_cam: Query<&mut Transform, (With<HandCamBox>, Without<HandCamScreen>)>,
+    mut hand_cam_screen: Query<&mut Transform, (With<HandCamScreen>, Without<HandCamBox>)>,
+) {
+    let Some(placement) = pending_hand_cam.0 else {
+        return;
+    };
+    let (Ok(mut camera), Ok(mut screen)) = (hand_cam.get_single_mut(), hand_cam_screen.get_single_mut()) else {
+        return;
+    };
+    *camera = placement.camera.into();
+    *screen = placement.screen.into();
+    pending_hand_cam.0 = None;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat_crop::CropBox;
+
+    #[test]
+    fn scene_round_trip() {
+        let scene = SplatScene {
+            splats: vec![
+                SceneSplat {
+                    asset_path: "embedded://cat1.gcloud".into(),
+                    transform: Transform::from_xyz(1.0, 2.0, 3.0)
+                        .with_rotation(Quat::from_rotation_z(std::f32::consts::PI))
+                        .with_scale(Vec3::splat(2.0))
+                        .into(),
+                    opacity: 0.5,
+                    scale: 1.0,
+                    visible: true,
+                    crop: SplatCrop::default(),
+                    grade: SplatGrade {
+                        exposure: 0.5,
+                        tint: [1.0, 0.9, 0.8],
+                        ..default()
+                    },
+                },
+                SceneSplat {
+                    asset_path: "fs://sdcard/Splats/room.ply".into(),
+                    transform: Transform::IDENTITY.into(),
+                    opacity: 1.0,
+                    scale: 0.25,
+                    visible: false,
+                    crop: SplatCrop {
+                        boxes: vec![
+                            CropBox::new(Transform::from_xyz(0.0, 1.0, 0.0).with_scale(Vec3::new(2.0, 1.0, 2.0)), false),
+                            CropBox::new(Transform::from_xyz(0.5, 1.0, 0.0).with_scale(Vec3::splat(0.2)), true),
+                        ],
+                    },
+                    grade: SplatGrade::default(),
+                },
+            ],
+            hand_cam: Some(HandCamPlacement {
+                camera: Transform::from_xyz(0.0, 1.5, -0.3).into(),
+                screen: Transform::from_xyz(0.2, 1.5, -0.3).into(),
+            }),
+            windows: vec![SceneWindow {
+                name: "Main Menu".into(),
+                transform: Transform::from_xyz(0.0, 2.0, -0.5).into(),
+            }],
+        };
+
+        let ron = scene.to_ron().unwrap();
+        let loaded = SplatScene::from_ron(&ron).unwrap();
+        assert_eq!(scene, loaded);
+    }
+
+    #[test]
+    fn optional_fields_default() {
+        let loaded = SplatScene::from_ron(
+            r#"(splats: [(asset_path: "embedded://cat1.gcloud", transform: (translation: (0.0, 0.0, 0.0), rotation: (0.0, 0.0, 0.0, 1.0), scale: (1.0, 1.0, 1.0)), opacity: 1.0, scale: 1.0)])"#,
+        )
+        .unwrap();
+        assert_eq!(loaded.splats.len(), 1);
+        assert!(loaded.splats[0].visible);
+        assert!(loaded.splats[0].crop.boxes.is_empty());
+        assert!(loaded.splats[0].grade.is_identity());
+        assert!(loaded.hand_cam.is_none());
+        assert!(loaded.windows.is_empty());
+    }
+}
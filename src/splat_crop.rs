@@ -0,0 +1,567 @@
+// Crop boxes, hide the gaussians of a splat outside (or inside) grabbable boxes
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_gaussian_splatting::{GaussianCloud, GaussianCloudSettings};
+use bevy_suis::{Field, InputHandler};
+use serde::{Deserialize, Serialize};
+
+use crate::edit_history::NoHistory;
+use crate::gaussian::{GaussianMarker, SelectedSplat};
+use crate::grabbing::{self, Grabbed, Grabble};
+use crate::scene_file::SceneTransform;
+use crate::splat_eraser::SplatErasures;
+use crate::splat_grade::SplatGrade;
+use crate::ui_window::WindowContexts;
+
+
+const KEEP_COLOR: Color = Color::srgb(0.2, 0.9, 0.3);
+const REMOVE_COLOR: Color = Color::srgb(0.95, 0.2, 0.2);
+/// Smallest box edge, so a box can't be squashed flat
+const MIN_BOX_SIZE: f32 = 0.01;
+/// Seconds between rebuilds of a splat's edited copy, so dragging a grade slider previews live
+/// without copying the whole cloud every frame
+const REBUILD_INTERVAL: f64 = 0.1;
+
+/// A box in the splat's local frame, the transform's scale is the box size
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CropBox {
+    pub transform: SceneTransform,
+    /// Removes what's inside instead of keeping it
+    #[serde(default)]
+    pub invert: bool,
+}
+
+impl CropBox {
+    pub fn new(transform: Transform, invert: bool) -> Self {
+        Self {
+            transform: transform.into(),
+            invert,
+        }
+    }
+
+    /// Inverse of the box transform, points inside land in the unit cube around the origin
+    fn to_unit_cube(&self) -> bevy::math::Affine3A {
+        Transform::from(self.transform).compute_affine().inverse()
+    }
+}
+
+/// Crop boxes of a splat, in its local frame. A gaussian is shown if it's inside any keep box (or
+/// there are none) and outside every inverted box. The loaded cloud is kept, the splat draws a
+/// cropped copy of it.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SplatCrop {
+    pub boxes: Vec<CropBox>,
+}
+
+impl SplatCrop {
+    /// Indices of the positions the crop keeps
+    pub fn kept_indices<'a>(&self, positions: impl Iterator<Item = &'a [f32; 3]>) -> Vec<usize> {
+        let boxes: Vec<(bevy::math::Affine3A, bool)> = self
+            .boxes
+            .iter()
+            .map(|b| (b.to_unit_cube(), b.invert))
+            .collect();
+        let has_keep_box = boxes.iter().any(|(_, invert)| !invert);
+
+        positions
+            .enumerate()
+            .filter(|(_, position)| {
+                let position = Vec3::from_array(**position);
+                let mut kept = !has_keep_box;
+                for (to_unit, invert) in boxes.iter() {
+                    let inside = to_unit.transform_point3(position).abs().cmple(Vec3::splat(0.5)).all();
+                    if inside && *invert {
+                        return false;
+                    }
+                    kept |= inside && !invert;
+                }
+                kept
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn keeps(&self, position: Vec3) -> bool {
+        !self.kept_indices(std::iter::once(&position.to_array())).is_empty()
+    }
+}
+
+/// The cloud the splat was loaded with, while it draws an edited copy
+#[derive(Component, Clone, Debug)]
+pub struct OriginalCloud(pub Handle<GaussianCloud>);
+
+/// Copy of [`OriginalCloud`] with the crop, erasures and grade applied, the splat's own so other
+/// splats of the same file don't see its edits
+#[derive(Component, Clone, Debug)]
+pub struct EditedCloud {
+    pub handle: Handle<GaussianCloud>,
+    /// Which gaussians of the original made it into the copy
+    kept: Vec<bool>,
+    built_at: f64,
+}
+
+impl EditedCloud {
+    /// Whether the gaussian at `index` of the original is drawn
+    pub fn keeps(&self, index: usize) -> bool {
+        self.kept.get(index).copied().unwrap_or(false)
+    }
+}
+
+/// The crop, erasures or grade changed and the drawn cloud needs rebuilding, waits for the cloud to load
+#[derive(Component)]
+pub struct EditsDirty;
+
+/// Grabbable box for one of the selected splat's crop boxes
+#[derive(Component)]
+pub struct CropBoxTool {
+    splat: Entity,
+    index: usize,
+    /// Box size in world units, the tool's own scale is folded into this when a grab ends
+    size: Vec3,
+    moved: bool,
+}
+
+#[derive(Component, Default)]
+pub struct CropWS;
+
+
+pub struct SplatCropPlugin;
+
+impl Plugin for SplatCropPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                update_crop_ws,
+                sync_crop_tools,
+                mark_edits_dirty,
+                rebuild_edited_clouds,
+                draw_crop_boxes,
+            ).chain(),
+        );
+    }
+}
+
+
+/// Edits changed, or the cloud the splat was loaded with did
+fn mark_edits_dirty(
+    changed: Query<Entity, Or<(Changed<SplatCrop>, Changed<SplatErasures>, Changed<SplatGrade>)>>,
+    originals: Query<(Entity, &OriginalCloud)>,
+    mut cloud_events: EventReader<AssetEvent<GaussianCloud>>,
+    mut commands: Commands,
+) {
+    for entity in changed.iter() {
+        commands.entity(entity).insert(EditsDirty);
+    }
+    for event in cloud_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        for (entity, original) in originals.iter() {
+            if original.0.id() == *id {
+                commands.entity(entity).insert(EditsDirty);
+            }
+        }
+    }
+}
+
+/// Indices of the original cloud the splat still draws
+pub fn kept_indices(
+    cloud: &GaussianCloud,
+    crop: Option<&SplatCrop>,
+    erasures: Option<&SplatErasures>,
+) -> Vec<usize> {
+    let mut kept = match crop {
+        Some(crop) => crop.kept_indices(cloud.position_iter()),
+        None => (0..cloud.len()).collect(),
+    };
+    if let Some(erasures) = erasures {
+        kept.retain(|i| !erasures.is_erased(*i));
+    }
+    kept
+}
+
+/// The kept gaussians of `cloud` with the grade applied
+pub fn edited_cloud(cloud: &GaussianCloud, kept: &[usize], grade: Option<&SplatGrade>) -> GaussianCloud {
+    let subset = cloud.subset(kept);
+    match grade {
+        Some(grade) if !grade.is_identity() => grade.apply_to_cloud(&subset),
+        _ => subset,
+    }
+}
+
+/// Gives cropped and graded splats their own copy of the cloud they were loaded with, only erasures
+/// are written into the loaded cloud. Splats without a crop or grade draw the loaded cloud. An
+/// existing copy is rebuilt at most every [`REBUILD_INTERVAL`], the last change still lands.
+pub fn rebuild_edited_clouds(
+    mut splats: Query<
+        (
+            Entity,
+            Option<&SplatCrop>,
+            Option<&SplatErasures>,
+            Option<&SplatGrade>,
+            &mut Handle<GaussianCloud>,
+            Option<&OriginalCloud>,
+            Option<&mut EditedCloud>,
+        ),
+        With<EditsDirty>,
+    >,
+    mut clouds: ResMut<Assets<GaussianCloud>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, crop, erasures, grade, mut handle, original, edited) in splats.iter_mut() {
+        // Known before the handle is swapped, the copy is made next frame
+        let Some(original) = original.map(|o| o.0.clone()) else {
+            commands.entity(entity).insert(OriginalCloud(handle.clone()));
+            continue;
+        };
+
+        // Erasures are already in the loaded cloud
+        let unedited = crop.map_or(true, |c| c.boxes.is_empty()) && grade.map_or(true, |g| g.is_identity());
+        if unedited {
+            if *handle != original {
+                *handle = original;
+            }
+            commands.entity(entity).remove::<(EditedCloud, EditsDirty)>();
+            continue;
+        }
+        // Stays dirty until it's time
+        if edited.as_ref().is_some_and(|e| now - e.built_at < REBUILD_INTERVAL) {
+            continue;
+        }
+
+        let Some(cloud) = clouds.get(&original) else {
+            continue;
+        };
+        let kept = kept_indices(cloud, crop, erasures);
+        commands.entity(entity).remove::<EditsDirty>();
+        if kept.is_empty() {
+            warn!("edits would hide every gaussian of {:?}, showing the whole splat", entity);
+            *handle = original;
+            commands.entity(entity).remove::<EditedCloud>();
+            continue;
+        }
+        debug!("drawing {} of {} gaussians", kept.len(), cloud.len());
+
+        let mut kept_mask = vec![false; cloud.len()];
+        for i in kept.iter() {
+            kept_mask[*i] = true;
+        }
+        let edited_cloud = edited_cloud(cloud, &kept, grade);
+        match edited {
+            Some(mut edited) => {
+                clouds.insert(edited.handle.id(), edited_cloud);
+                edited.kept = kept_mask;
+                edited.built_at = now;
+                if *handle != edited.handle {
+                    *handle = edited.handle.clone();
+                }
+            }
+            None => {
+                let edited = clouds.add(edited_cloud);
+                *handle = edited.clone();
+                commands.entity(entity).insert(EditedCloud {
+                    handle: edited,
+                    kept: kept_mask,
+                    built_at: now,
+                });
+            }
+        }
+    }
+}
+
+
+fn spawn_crop_box_tool(
+    commands: &mut Commands,
+    splat: Entity,
+    index: usize,
+    world: Transform,
+) -> Entity {
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_translation(world.translation).with_rotation(world.rotation)),
+        Name::new("Crop Box"),
+        CropBoxTool {
+            splat,
+            index,
+            size: world.scale,
+            moved: false,
+        },
+        InputHandler::new(grabbing::capture_condition),
+        Field::Cuboid(Cuboid::from_size(world.scale)),
+        Grabble,
+        NoHistory,
+    )).id()
+}
+
+/// Keeps a tool per crop box of the selected splat while the crop window is open. Moving a tool
+/// writes its box back once the grab ends, otherwise the tools follow the splat.
+pub fn sync_crop_tools(
+    windows: Query<(), With<CropWS>>,
+    selected: Res<SelectedSplat>,
+    mut splats: Query<(&GaussianCloudSettings, &mut SplatCrop)>,
+    mut tools: Query<(Entity, &mut Transform, &mut CropBoxTool, &mut Field, Has<Grabbed>)>,
+    mut commands: Commands,
+) {
+    let target = selected.0.filter(|e| !windows.is_empty() && splats.contains(*e));
+    let box_count = target
+        .and_then(|e| splats.get(e).ok())
+        .map_or(0, |(_, crop)| crop.boxes.len());
+    let tools_match = tools.iter().count() == box_count
+        && tools.iter().all(|(_, _, tool, _, _)| Some(tool.splat) == target && tool.index < box_count);
+    if !tools_match {
+        for (entity, ..) in tools.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        let Some(splat) = target else {
+            return;
+        };
+        let Ok((settings, crop)) = splats.get(splat) else {
+            return;
+        };
+        for (index, crop_box) in crop.boxes.iter().enumerate() {
+            let world = settings.transform * Transform::from(crop_box.transform);
+            spawn_crop_box_tool(&mut commands, splat, index, world);
+        }
+        return;
+    }
+
+    for (_, mut transform, mut tool, mut field, grabbed) in tools.iter_mut() {
+        let Ok((settings, mut crop)) = splats.get_mut(tool.splat) else {
+            continue;
+        };
+        if grabbed {
+            tool.moved = true;
+            continue;
+        }
+
+        if tool.moved {
+            tool.moved = false;
+            tool.size = (tool.size * transform.scale).max(Vec3::splat(MIN_BOX_SIZE));
+            transform.scale = Vec3::ONE;
+            *field = Field::Cuboid(Cuboid::from_size(tool.size));
+
+            let world = Transform {
+                scale: tool.size,
+                ..*transform
+            };
+            let local = GlobalTransform::from(world).reparented_to(&GlobalTransform::from(settings.transform));
+            if Transform::from(crop.boxes[tool.index].transform) != local {
+                crop.boxes[tool.index].transform = local.into();
+            }
+            continue;
+        }
+
+        let world = settings.transform * Transform::from(crop.boxes[tool.index].transform);
+        if transform.translation != world.translation || transform.rotation != world.rotation {
+            transform.translation = world.translation;
+            transform.rotation = world.rotation;
+        }
+        if tool.size != world.scale {
+            tool.size = world.scale;
+            *field = Field::Cuboid(Cuboid::from_size(tool.size));
+        }
+    }
+}
+
+fn draw_crop_boxes(
+    tools: Query<(&Transform, &CropBoxTool)>,
+    splats: Query<&SplatCrop>,
+    mut gizmos: Gizmos,
+) {
+    for (transform, tool) in tools.iter() {
+        let invert = splats
+            .get(tool.splat)
+            .ok()
+            .and_then(|crop| crop.boxes.get(tool.index))
+            .is_some_and(|b| b.invert);
+        let color = if invert { REMOVE_COLOR } else { KEEP_COLOR };
+        gizmos.cuboid(
+            Transform {
+                scale: tool.size * transform.scale,
+                ..*transform
+            },
+            color,
+        );
+    }
+}
+
+
+pub fn update_crop_ws(
+    windows: Query<Entity, With<CropWS>>,
+    mut contexts: WindowContexts,
+    selected: Res<SelectedSplat>,
+    mut splats: Query<(&Name, Option<&mut SplatCrop>, &Handle<GaussianCloud>), With<GaussianMarker>>,
+    clouds: Res<Assets<GaussianCloud>>,
+    mut commands: Commands,
+) {
+    for window in windows.iter() {
+        let Some(ui_ctx) = contexts.get(window) else {
+            continue;
+        };
+        ui_ctx.window("Crop Boxes")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                let Some((splat, (name, crop, handle))) = selected.0.and_then(|e| splats.get_mut(e).ok().map(|s| (e, s))) else {
+                    ui.label("Select a splat to crop it");
+                    return;
+                };
+                ui.label(format!("Cropping {}", name));
+
+                let Some(mut crop) = crop else {
+                    if ui.button("Add Box").clicked() {
+                        commands.entity(splat).insert(SplatCrop {
+                            boxes: vec![CropBox::new(Transform::IDENTITY, false)],
+                        });
+                    }
+                    return;
+                };
+
+                if let Some(cloud) = clouds.get(handle) {
+                    ui.label(format!("Showing {} gaussians", cloud.len()));
+                }
+
+                let mut set_invert = None;
+                let mut remove = None;
+                for (i, crop_box) in crop.boxes.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Box {}", i + 1));
+                        let mut invert = crop_box.invert;
+                        if ui.checkbox(&mut invert, "Remove inside").changed() {
+                            set_invert = Some((i, invert));
+                        }
+                        if ui.button("X").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some((i, invert)) = set_invert {
+                    crop.boxes[i].invert = invert;
+                }
+                if let Some(i) = remove {
+                    crop.boxes.remove(i);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add Box").clicked() {
+                        crop.boxes.push(CropBox::new(Transform::IDENTITY, false));
+                    }
+                    if ui.button("Clear").clicked() {
+                        crop.boxes.clear();
+                    }
+                });
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn unit_box(center: Vec3, invert: bool) -> CropBox {
+        CropBox::new(Transform::from_translation(center), invert)
+    }
+
+    #[test]
+    fn keep_and_remove_boxes() {
+        let empty = SplatCrop::default();
+        assert!(empty.keeps(Vec3::splat(10.0)));
+
+        let crop = SplatCrop {
+            boxes: vec![
+                unit_box(Vec3::ZERO, false),
+                unit_box(Vec3::new(3.0, 0.0, 0.0), false),
+                unit_box(Vec3::new(0.4, 0.0, 0.0), true),
+            ],
+        };
+        assert!(crop.keeps(Vec3::new(-0.3, 0.0, 0.0)));
+        assert!(crop.keeps(Vec3::new(3.2, 0.0, 0.0)));
+        // Outside every keep box
+        assert!(!crop.keeps(Vec3::new(1.5, 0.0, 0.0)));
+        // Inside a keep box but also a remove box
+        assert!(!crop.keeps(Vec3::new(0.3, 0.0, 0.0)));
+
+        // Only remove boxes keep everything else
+        let invert_only = SplatCrop {
+            boxes: vec![unit_box(Vec3::ZERO, true)],
+        };
+        assert!(!invert_only.keeps(Vec3::ZERO));
+        assert!(invert_only.keeps(Vec3::new(2.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn boxes_rotate_and_scale() {
+        let crop = SplatCrop {
+            boxes: vec![CropBox::new(
+                Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+                    .with_scale(Vec3::new(4.0, 1.0, 1.0)),
+                false,
+            )],
+        };
+        // The long side now points along Y
+        assert!(crop.keeps(Vec3::new(0.0, 1.9, 0.0)));
+        assert!(!crop.keeps(Vec3::new(1.9, 0.0, 0.0)));
+
+        let positions = [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, -1.5, 0.2]];
+        assert_eq!(crop.kept_indices(positions.iter()), vec![0, 2]);
+    }
+
+    #[test]
+    fn crops_draw_a_copy_and_leave_the_loaded_cloud() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<GaussianCloud>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(0.05)))
+            .add_systems(Update, (mark_edits_dirty, rebuild_edited_clouds).chain());
+
+        let gaussians = [0.0, 2.0].map(|x| {
+            let mut gaussian = bevy_gaussian_splatting::Gaussian::default();
+            gaussian.position_visibility.position = [x, 0.0, 0.0];
+            gaussian
+        });
+        let loaded = app
+            .world_mut()
+            .resource_mut::<Assets<GaussianCloud>>()
+            .add(GaussianCloud::from_gaussians(gaussians.to_vec()));
+        let crop = SplatCrop {
+            boxes: vec![unit_box(Vec3::ZERO, false)],
+        };
+        let cropped = app.world_mut().spawn((GaussianMarker, loaded.clone(), crop)).id();
+        // Same file loaded twice
+        let other = app.world_mut().spawn((GaussianMarker, loaded.clone())).id();
+        let drawn_len = |app: &App, splat: Entity| {
+            let handle = app.world().get::<Handle<GaussianCloud>>(splat).unwrap();
+            app.world().resource::<Assets<GaussianCloud>>().get(handle).unwrap().len()
+        };
+
+        // Original recorded, then the copy made
+        app.update();
+        app.update();
+        assert_ne!(app.world().get::<Handle<GaussianCloud>>(cropped).unwrap(), &loaded);
+        assert_eq!(drawn_len(&app, cropped), 1);
+        assert_eq!(app.world().resource::<Assets<GaussianCloud>>().get(&loaded).unwrap().len(), 2);
+        assert_eq!(drawn_len(&app, other), 2);
+        let edited = app.world().get::<EditedCloud>(cropped).unwrap();
+        assert!(edited.keeps(0) && !edited.keeps(1));
+
+        // The copy waits out the rebuild interval, then catches up
+        app.world_mut().get_mut::<SplatCrop>(cropped).unwrap().boxes.push(unit_box(Vec3::X * 2.0, false));
+        app.update();
+        assert_eq!(drawn_len(&app, cropped), 1);
+        app.update();
+        app.update();
+        assert_eq!(drawn_len(&app, cropped), 2);
+
+        app.world_mut().get_mut::<SplatCrop>(cropped).unwrap().boxes.clear();
+        app.update();
+        assert_eq!(app.world().get::<Handle<GaussianCloud>>(cropped).unwrap(), &loaded);
+        assert!(app.world().get::<EditedCloud>(cropped).is_none());
+    }
+}
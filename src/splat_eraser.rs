@@ -0,0 +1,675 @@
+// Eraser brush for deleting floaters, and exporting the cleaned up splat
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{block_on, IoTaskPool, Task};
+use bevy_egui::egui;
+use bevy_gaussian_splatting::{Gaussian, GaussianCloud, GaussianCloudSettings};
+use bevy_suis::{Field, InputHandler};
+use serde::Serialize;
+
+use crate::edit_history::{Edit, EditHistory, HistoryControls, NoHistory};
+use crate::gaussian::{GaussianMarker, SelectedSplat};
+use crate::grabbing::{self, Grabbed, Grabble};
+use crate::splat_crop::{edited_cloud, kept_indices, EditedCloud, OriginalCloud, SplatCrop};
+use crate::splat_grade::SplatGrade;
+use crate::splat_surface::SplatSurface;
+use crate::ui_window::WindowContexts;
+
+
+const DEFAULT_BRUSH_RADIUS: f32 = 0.05;
+const MIN_BRUSH_RADIUS: f32 = 0.005;
+const MAX_BRUSH_RADIUS: f32 = 1.0;
+/// Seconds between writing a held brush's erasures into the splats, each write re-uploads the cloud
+const ERASE_WRITE_INTERVAL: f64 = 0.2;
+
+/// Gaussians erased from a splat, as indices into its loaded cloud. They're deleted from the loaded
+/// cloud itself by zeroing their opacity, the opacity they had is kept here so undo can bring them back.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct SplatErasures {
+    erased: Vec<bool>,
+    count: usize,
+    /// Cloud the erasures were written into, and the opacity each erased gaussian had in it
+    written: Option<AssetId<GaussianCloud>>,
+    loaded_opacity: HashMap<usize, f32>,
+    /// Erased or restored since the last write
+    unwritten: bool,
+}
+
+impl SplatErasures {
+    pub fn is_erased(&self, index: usize) -> bool {
+        self.erased.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns false if it was already erased
+    pub fn erase(&mut self, index: usize) -> bool {
+        if self.erased.len() <= index {
+            self.erased.resize(index + 1, false);
+        }
+        if self.erased[index] {
+            return false;
+        }
+        self.erased[index] = true;
+        self.count += 1;
+        self.unwritten = true;
+        true
+    }
+
+    pub fn restore(&mut self, indices: &[usize]) {
+        for index in indices {
+            if let Some(erased) = self.erased.get_mut(*index) {
+                if *erased {
+                    *erased = false;
+                    self.count -= 1;
+                    self.unwritten = true;
+                }
+            }
+        }
+    }
+
+    /// Whether the erasures still have to be written into the cloud `id`
+    fn needs_write(&self, id: AssetId<GaussianCloud>) -> bool {
+        self.unwritten || self.written != Some(id)
+    }
+
+    /// Zeroes the opacity of newly erased gaussians in `cloud` and gives restored ones back theirs
+    fn write_into(&mut self, cloud: &mut GaussianCloud, id: AssetId<GaussianCloud>) {
+        if self.written != Some(id) {
+            // A cloud loaded again comes without the erasures
+            self.written = Some(id);
+            self.loaded_opacity.clear();
+        }
+        let mut gaussians: Vec<Gaussian> = cloud.gaussian_iter().collect();
+        self.loaded_opacity.retain(|i, opacity| {
+            let erased = self.erased.get(*i).copied().unwrap_or(false);
+            if !erased {
+                gaussians[*i].scale_opacity.opacity = *opacity;
+            }
+            erased
+        });
+        for (i, gaussian) in gaussians.iter_mut().enumerate() {
+            if self.is_erased(i) && !self.loaded_opacity.contains_key(&i) {
+                self.loaded_opacity.insert(i, gaussian.scale_opacity.opacity);
+                gaussian.scale_opacity.opacity = 0.0;
+            }
+        }
+        *cloud = GaussianCloud::from_gaussians(gaussians);
+        self.unwritten = false;
+    }
+}
+
+/// Sphere that erases the gaussians it touches while held. Two handed grabs resize it.
+#[derive(Component)]
+pub struct EraserBrush {
+    pub radius: f32,
+    /// Erasures of the grab in progress written so far, per splat
+    stroke: Vec<(Entity, Vec<usize>)>,
+    /// Touched since the last write, per splat
+    pending: Vec<(Entity, HashSet<usize>)>,
+    written_at: f64,
+}
+
+#[derive(Resource, Default)]
+struct SplatExports(Vec<Task<Result<PathBuf, String>>>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Ply,
+    Gcloud,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ply => "ply",
+            ExportFormat::Gcloud => "gcloud",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct EraserWS {
+    armed: bool,
+    folder: String,
+    status: String,
+}
+
+impl Default for EraserWS {
+    fn default() -> Self {
+        Self {
+            armed: true,
+            folder: default_export_folder().to_string_lossy().to_string(),
+            status: String::new(),
+        }
+    }
+}
+
+
+pub struct SplatEraserPlugin;
+
+impl Plugin for SplatEraserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SplatExports>()
+            .add_systems(
+                Update,
+                (
+                    update_eraser_ws,
+                    sync_eraser_brush,
+                    erase_with_brush,
+                    write_erasures,
+                    poll_exports,
+                ).chain(),
+            );
+    }
+}
+
+
+pub fn default_export_folder() -> PathBuf {
+    // Next to the splats so the file browser finds them
+    #[cfg(target_os = "android")]
+    return PathBuf::from("/sdcard/Splats");
+
+    #[cfg(not(target_os = "android"))]
+    return PathBuf::from("exports");
+}
+
+/// `room.ply` -> `room_edited.gcloud`
+pub fn export_file_name(splat_name: &str, format: ExportFormat) -> String {
+    let stem = Path::new(splat_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("splat");
+    format!("{}_edited.{}", stem, format.extension())
+}
+
+fn logit(p: f32) -> f32 {
+    let p = p.clamp(1e-6, 1.0 - 1e-6);
+    (p / (1.0 - p)).ln()
+}
+
+/// Writes the standard 3D gaussian splatting layout, opacity and scale go back to the logit and
+/// log space the ply loader expects. Only the DC colour is written, the viewer is built with `sh0`.
+pub fn write_ply(cloud: &GaussianCloud, path: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let mut header = format!("ply\nformat binary_little_endian 1.0\nelement vertex {}\n", cloud.len());
+    for property in [
+        "x", "y", "z", "f_dc_0", "f_dc_1", "f_dc_2", "opacity", "scale_0", "scale_1", "scale_2",
+        "rot_0", "rot_1", "rot_2", "rot_3",
+    ] {
+        header.push_str(&format!("property float {}\n", property));
+    }
+    header.push_str("end_header\n");
+    writer.write_all(header.as_bytes()).map_err(|e| e.to_string())?;
+
+    for gaussian in cloud.gaussian_iter() {
+        let position = gaussian.position_visibility.position;
+        let color = &gaussian.spherical_harmonic.coefficients[..3];
+        let scale = gaussian.scale_opacity.scale;
+        let values = [
+            position[0],
+            position[1],
+            position[2],
+            color[0],
+            color[1],
+            color[2],
+            logit(gaussian.scale_opacity.opacity),
+            scale[0].max(f32::MIN_POSITIVE).ln(),
+            scale[1].max(f32::MIN_POSITIVE).ln(),
+            scale[2].max(f32::MIN_POSITIVE).ln(),
+            gaussian.rotation.rotation[0],
+            gaussian.rotation.rotation[1],
+            gaussian.rotation.rotation[2],
+            gaussian.rotation.rotation[3],
+        ];
+        for value in values {
+            writer.write_all(&value.to_le_bytes()).map_err(|e| e.to_string())?;
+        }
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// The flexbuffers format the `.gcloud` loader reads
+pub fn write_gcloud(cloud: &GaussianCloud, path: &Path) -> Result<(), String> {
+    let mut serializer = flexbuffers::FlexbufferSerializer::new();
+    cloud.serialize(&mut serializer).map_err(|e| e.to_string())?;
+    std::fs::write(path, serializer.view()).map_err(|e| e.to_string())
+}
+
+fn export_cloud(cloud: GaussianCloud, path: PathBuf, format: ExportFormat) -> Result<PathBuf, String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    match format {
+        ExportFormat::Ply => write_ply(&cloud, &path)?,
+        ExportFormat::Gcloud => write_gcloud(&cloud, &path)?,
+    }
+    Ok(path)
+}
+
+
+/// Keeps one brush around while the eraser window is open
+fn sync_eraser_brush(
+    windows: Query<(), With<EraserWS>>,
+    mut brushes: Query<(Entity, &mut Transform, &mut EraserBrush, &mut Field, Has<Grabbed>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    if windows.is_empty() {
+        for (entity, ..) in brushes.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if brushes.is_empty() {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(1.0)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::srgba(1.0, 0.3, 0.3, 0.35),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_xyz(0.3, 1.2, -0.3).with_scale(Vec3::splat(DEFAULT_BRUSH_RADIUS)),
+                ..default()
+            },
+            Name::new("Eraser Brush"),
+            EraserBrush {
+                radius: DEFAULT_BRUSH_RADIUS,
+                stroke: Vec::new(),
+                pending: Vec::new(),
+                written_at: 0.0,
+            },
+            InputHandler::new(grabbing::capture_condition),
+            Field::Sphere(DEFAULT_BRUSH_RADIUS),
+            Grabble,
+            NoHistory,
+        ));
+        return;
+    }
+
+    for (_, mut transform, mut brush, mut field, grabbed) in brushes.iter_mut() {
+        if grabbed {
+            continue;
+        }
+        // Radius changes from the window or a two handed grab
+        if transform.scale != Vec3::splat(brush.radius) {
+            if transform.scale.x != transform.scale.y || transform.scale.y != transform.scale.z {
+                brush.radius = (transform.scale.max_element()).clamp(MIN_BRUSH_RADIUS, MAX_BRUSH_RADIUS);
+            }
+            transform.scale = Vec3::splat(brush.radius);
+            *field = Field::Sphere(brush.radius);
+        }
+    }
+}
+
+/// Erases the visible gaussians inside held brushes. What a brush touches is written into the
+/// splats a few times a second and when it's let go, a stroke ends on release.
+pub fn erase_with_brush(
+    windows: Query<&EraserWS>,
+    mut brushes: Query<(&GlobalTransform, &mut EraserBrush, Has<Grabbed>)>,
+    splats: Query<
+        (
+            Entity,
+            &GaussianCloudSettings,
+            &InheritedVisibility,
+            &Handle<GaussianCloud>,
+            Option<&OriginalCloud>,
+            Option<&EditedCloud>,
+            &SplatSurface,
+        ),
+        With<GaussianMarker>,
+    >,
+    mut erasures: Query<&mut SplatErasures>,
+    clouds: Res<Assets<GaussianCloud>>,
+    mut history: ResMut<EditHistory>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let armed = windows.iter().any(|w| w.armed);
+    let now = time.elapsed_seconds_f64();
+    for (brush_gt, mut brush, grabbed) in brushes.iter_mut() {
+        if armed && grabbed {
+            // A two handed grab scales the brush, the scale is folded into the radius on release
+            let (scale, _, center) = brush_gt.to_scale_rotation_translation();
+            let radius = scale.max_element();
+
+            for (entity, settings, visibility, handle, original, edited, surface) in splats.iter() {
+                if !visibility.get() {
+                    continue;
+                }
+                let Some(cloud) = clouds.get(original.map_or(handle, |o| &o.0)) else {
+                    continue;
+                };
+
+                let to_local = settings.transform.compute_affine().inverse();
+                let local_center = to_local.transform_point3(center);
+                let local_radius = radius / settings.transform.scale.max_element().max(f32::EPSILON);
+                let local_radius_squared = local_radius * local_radius;
+
+                // Only the cells the brush overlaps, gaussians the crop hides stay as they are
+                let erased = erasures.get(entity).ok();
+                let touched = surface.indices_near(local_center, local_radius).filter(|i| {
+                    Vec3::from_array(*cloud.position(*i)).distance_squared(local_center) <= local_radius_squared
+                        && edited.map_or(true, |e| e.keeps(*i))
+                        && !erased.is_some_and(|e| e.is_erased(*i))
+                });
+                match brush.pending.iter_mut().find(|(e, _)| *e == entity) {
+                    Some((_, pending)) => pending.extend(touched),
+                    None => {
+                        let pending: HashSet<usize> = touched.collect();
+                        if !pending.is_empty() {
+                            brush.pending.push((entity, pending));
+                        }
+                    }
+                }
+            }
+        }
+
+        if grabbed && now - brush.written_at < ERASE_WRITE_INTERVAL {
+            continue;
+        }
+        brush.written_at = now;
+        for (entity, touched) in std::mem::take(&mut brush.pending) {
+            let touched: Vec<usize> = touched.into_iter().collect();
+            match erasures.get_mut(entity) {
+                Ok(mut erasures) => {
+                    for i in touched.iter() {
+                        erasures.erase(*i);
+                    }
+                }
+                Err(_) => {
+                    let Some(mut splat) = commands.get_entity(entity) else {
+                        continue;
+                    };
+                    let mut erasures = SplatErasures::default();
+                    for i in touched.iter() {
+                        erasures.erase(*i);
+                    }
+                    splat.insert(erasures);
+                }
+            }
+            match brush.stroke.iter_mut().find(|(e, _)| *e == entity) {
+                Some((_, stroke)) => stroke.extend(touched),
+                None => brush.stroke.push((entity, touched)),
+            }
+        }
+        if !grabbed && !brush.stroke.is_empty() {
+            let stroke = std::mem::take(&mut brush.stroke);
+            history.push(stroke.into_iter().map(|(entity, indices)| Edit::Erase { entity, indices }).collect());
+        }
+    }
+}
+
+/// Deletes erased gaussians from the clouds the splats were loaded with, and brings back restored ones
+fn write_erasures(
+    mut splats: Query<(&Handle<GaussianCloud>, Option<&OriginalCloud>, &mut SplatErasures)>,
+    mut clouds: ResMut<Assets<GaussianCloud>>,
+) {
+    for (handle, original, mut erasures) in splats.iter_mut() {
+        let handle = original.map_or(handle, |o| &o.0);
+        if !erasures.needs_write(handle.id()) {
+            continue;
+        }
+        let Some(cloud) = clouds.get_mut(handle) else {
+            continue;
+        };
+        // Marked changed, so the history's copy knows what was written
+        erasures.write_into(cloud, handle.id());
+    }
+}
+
+fn poll_exports(mut exports: ResMut<SplatExports>, mut windows: Query<&mut EraserWS>) {
+    exports.0.retain_mut(|task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        let status = match result {
+            Ok(path) => {
+                info!("exported splat to {:?}", path);
+                format!("Exported {}", path.to_string_lossy())
+            }
+            Err(e) => {
+                error!("failed to export splat: {}", e);
+                format!("Export failed: {}", e)
+            }
+        };
+        for mut window in windows.iter_mut() {
+            window.status = status.clone();
+        }
+        false
+    });
+}
+
+pub fn update_eraser_ws(
+    mut windows: Query<(Entity, &mut EraserWS)>,
+    mut contexts: WindowContexts,
+    mut brushes: Query<&mut EraserBrush>,
+    mut history: HistoryControls,
+    erasures: Query<&SplatErasures>,
+    selected: Res<SelectedSplat>,
+    splats: Query<
+        (&Name, &Handle<GaussianCloud>, Option<&OriginalCloud>, Option<&SplatCrop>, Option<&SplatGrade>),
+        With<GaussianMarker>,
+    >,
+    clouds: Res<Assets<GaussianCloud>>,
+    mut exports: ResMut<SplatExports>,
+) {
+    for (entity, mut window) in windows.iter_mut() {
+        let Some(ui_ctx) = contexts.get(entity) else {
+            continue;
+        };
+        ui_ctx.window("Eraser")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                ui.checkbox(&mut window.armed, "Erase while holding the brush");
+                for mut brush in brushes.iter_mut() {
+                    let mut radius = brush.radius;
+                    if ui
+                        .add(egui::Slider::new(&mut radius, MIN_BRUSH_RADIUS..=MAX_BRUSH_RADIUS).logarithmic(true).text("Radius"))
+                        .changed()
+                    {
+                        brush.radius = radius;
+                    }
+                }
+
+                // Strokes are steps of the edit history
+                history.ui(ui);
+
+                ui.separator();
+                let Some(splat) = selected.0 else {
+                    ui.label("Select a splat to export it");
+                    return;
+                };
+                let Ok((name, handle, original, crop, grade)) = splats.get(splat) else {
+                    return;
+                };
+                let splat_erasures = erasures.get(splat).ok();
+                ui.label(format!(
+                    "{}: {} erased",
+                    name,
+                    splat_erasures.map_or(0, |e| e.len())
+                ));
+
+                ui.label("Export Folder");
+                ui.add(egui::TextEdit::singleline(&mut window.folder));
+                let mut export = None;
+                ui.horizontal(|ui| {
+                    if ui.button("Export .ply").clicked() {
+                        export = Some(ExportFormat::Ply);
+                    }
+                    if ui.button("Export .gcloud").clicked() {
+                        export = Some(ExportFormat::Gcloud);
+                    }
+                });
+                if let Some(format) = export {
+                    let original = original.map_or(handle, |o| &o.0);
+                    match clouds.get(original) {
+                        Some(cloud) => {
+                            // Crop, erasures and grade applied, like the splat's drawn copy
+                            let kept = kept_indices(cloud, crop, splat_erasures);
+                            if kept.is_empty() {
+                                // The splat falls back to drawing everything, an empty file helps nobody
+                                window.status = "Nothing to export, the crop and erasures remove every gaussian".into();
+                            } else {
+                                let edited = edited_cloud(cloud, &kept, grade);
+                                let path = PathBuf::from(&window.folder).join(export_file_name(name.as_str(), format));
+                                window.status = format!("Exporting {} gaussians...", kept.len());
+                                exports.0.push(IoTaskPool::get().spawn(async move { export_cloud(edited, path, format) }));
+                            }
+                        }
+                        None => window.status = "The splat hasn't loaded yet".into(),
+                    }
+                }
+                if !window.status.is_empty() {
+                    ui.label(&window.status);
+                }
+            });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erasures_count_and_restore() {
+        let mut erasures = SplatErasures::default();
+        assert!(erasures.is_empty());
+        assert!(erasures.erase(5));
+        assert!(erasures.erase(2));
+        assert!(!erasures.erase(5));
+        assert_eq!(erasures.len(), 2);
+        assert!(erasures.is_erased(5));
+        assert!(!erasures.is_erased(3));
+        assert!(!erasures.is_erased(100));
+
+        erasures.restore(&[5, 7]);
+        assert_eq!(erasures.len(), 1);
+        assert!(!erasures.is_erased(5));
+    }
+
+    #[test]
+    fn erasures_are_written_into_the_cloud_and_restored() {
+        let gaussians = (0..3)
+            .map(|_| {
+                let mut gaussian = Gaussian::default();
+                gaussian.scale_opacity.opacity = 0.8;
+                gaussian
+            })
+            .collect();
+        let mut cloud = GaussianCloud::from_gaussians(gaussians);
+        let id = AssetId::default();
+        let opacities = |cloud: &GaussianCloud| -> Vec<f32> {
+            cloud.gaussian_iter().map(|g| g.scale_opacity.opacity).collect()
+        };
+
+        let mut erasures = SplatErasures::default();
+        assert!(erasures.needs_write(id));
+        erasures.erase(1);
+        erasures.write_into(&mut cloud, id);
+        assert!(!erasures.needs_write(id));
+        assert_eq!(opacities(&cloud), vec![0.8, 0.0, 0.8]);
+
+        erasures.restore(&[1]);
+        assert!(erasures.needs_write(id));
+        erasures.write_into(&mut cloud, id);
+        assert_eq!(opacities(&cloud), vec![0.8, 0.8, 0.8]);
+    }
+
+    #[test]
+    fn strokes_are_written_at_intervals_and_on_release() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<GaussianCloud>>()
+            .init_resource::<EditHistory>()
+            .add_systems(Update, erase_with_brush);
+
+        let positions: Vec<[f32; 3]> = (0..100).map(|i| [i as f32 * 0.01, 0.0, 0.0]).collect();
+        let gaussians = positions
+            .iter()
+            .map(|p| {
+                let mut gaussian = bevy_gaussian_splatting::Gaussian::default();
+                gaussian.position_visibility.position = *p;
+                gaussian
+            })
+            .collect();
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<GaussianCloud>>()
+            .add(GaussianCloud::from_gaussians(gaussians));
+        let splat = app
+            .world_mut()
+            .spawn((
+                GaussianMarker,
+                GaussianCloudSettings::default(),
+                InheritedVisibility::VISIBLE,
+                SplatSurface::build(positions.iter(), handle.id()),
+                handle,
+            ))
+            .id();
+        app.world_mut().spawn(EraserWS::default());
+        let brush = app
+            .world_mut()
+            .spawn((
+                GlobalTransform::from(Transform::from_xyz(0.1, 0.0, 0.0).with_scale(Vec3::splat(0.045))),
+                EraserBrush {
+                    radius: 0.045,
+                    stroke: Vec::new(),
+                    pending: Vec::new(),
+                    written_at: 0.0,
+                },
+                Grabbed {
+                    method: Entity::PLACEHOLDER,
+                    offset: Transform::IDENTITY,
+                    second: None,
+                },
+            ))
+            .id();
+        let advance = |app: &mut App, seconds: f32| {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(std::time::Duration::from_secs_f32(seconds));
+            app.update();
+        };
+
+        advance(&mut app, 0.05);
+        assert!(app.world().get::<SplatErasures>(splat).is_none());
+        advance(&mut app, 0.2);
+        assert_eq!(app.world().get::<SplatErasures>(splat).unwrap().len(), 9);
+        assert!(!app.world().resource::<EditHistory>().can_undo());
+
+        *app.world_mut().get_mut::<GlobalTransform>(brush).unwrap() =
+            GlobalTransform::from(Transform::from_xyz(0.3, 0.0, 0.0).with_scale(Vec3::splat(0.045)));
+        advance(&mut app, 0.05);
+        app.world_mut().entity_mut(brush).remove::<Grabbed>();
+        advance(&mut app, 0.05);
+        assert_eq!(app.world().get::<SplatErasures>(splat).unwrap().len(), 18);
+        assert!(app.world().resource::<EditHistory>().can_undo());
+    }
+
+    #[test]
+    fn export_names_and_ply_space() {
+        assert_eq!(export_file_name("room.ply", ExportFormat::Gcloud), "room_edited.gcloud");
+        assert_eq!(export_file_name("cat1.gcloud", ExportFormat::Ply), "cat1_edited.ply");
+        // The loader applies a sigmoid to opacity
+        let opacity: f32 = 0.8;
+        assert!((1.0 / (1.0 + (-logit(opacity)).exp()) - opacity).abs() < 1e-5);
+    }
+}
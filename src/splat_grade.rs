@@ -0,0 +1,147 @@
+// Per splat colour grading, for matching captures taken under different lighting
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_gaussian_splatting::{Gaussian, GaussianCloud};
+use serde::{Deserialize, Serialize};
+
+
+/// Zeroth spherical harmonic band, colour = 0.5 + SH_C0 * dc
+const SH_C0: f32 = 0.282_094_8;
+
+/// Applied to the colour of every gaussian in the splat's drawn cloud, in this order
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct SplatGrade {
+    /// Stops, +1 doubles the brightness
+    pub exposure: f32,
+    /// Multiplier applied after exposure, white leaves colours alone
+    pub tint: [f32; 3],
+    /// Around mid grey
+    pub contrast: f32,
+    pub saturation: f32,
+    /// Degrees around the grey axis
+    pub hue: f32,
+    pub gamma: f32,
+}
+
+impl Default for SplatGrade {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tint: [1.0; 3],
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl SplatGrade {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let mut color = color * 2f32.powf(self.exposure) * Vec3::from_array(self.tint);
+
+        color = (color - 0.5) * self.contrast + 0.5;
+
+        let luma = color.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+        color = Vec3::splat(luma).lerp(color, self.saturation);
+
+        if self.hue != 0.0 {
+            // Rodrigues rotation around (1, 1, 1)
+            let axis = Vec3::ONE.normalize();
+            let (sin, cos) = self.hue.to_radians().sin_cos();
+            color = color * cos + axis.cross(color) * sin + axis * axis.dot(color) * (1.0 - cos);
+        }
+
+        color.max(Vec3::ZERO).powf(1.0 / self.gamma.max(0.01))
+    }
+
+    /// Only the DC colour is graded, the higher bands are view dependent detail on top of it
+    pub fn apply_to_gaussian(&self, gaussian: &mut Gaussian) {
+        let dc = &mut gaussian.spherical_harmonic.coefficients;
+        let color = Vec3::new(dc[0], dc[1], dc[2]) * SH_C0 + 0.5;
+        let graded = (self.apply(color) - 0.5) / SH_C0;
+        dc[0] = graded.x;
+        dc[1] = graded.y;
+        dc[2] = graded.z;
+    }
+
+    pub fn apply_to_cloud(&self, cloud: &GaussianCloud) -> GaussianCloud {
+        let gaussians: Vec<Gaussian> = cloud
+            .gaussian_iter()
+            .map(|mut gaussian| {
+                self.apply_to_gaussian(&mut gaussian);
+                gaussian
+            })
+            .collect();
+        GaussianCloud::from_gaussians(gaussians)
+    }
+}
+
+/// Sliders for a grade, returns the edited copy if anything changed
+pub fn grade_ui(ui: &mut egui::Ui, grade: &SplatGrade) -> Option<SplatGrade> {
+    let mut edited = *grade;
+    ui.add(egui::Slider::new(&mut edited.exposure, -3.0..=3.0).text("Exposure"));
+    ui.add(egui::Slider::new(&mut edited.contrast, 0.0..=2.0).text("Contrast"));
+    ui.add(egui::Slider::new(&mut edited.saturation, 0.0..=2.0).text("Saturation"));
+    ui.add(egui::Slider::new(&mut edited.hue, -180.0..=180.0).text("Hue"));
+    ui.add(egui::Slider::new(&mut edited.gamma, 0.2..=3.0).text("Gamma"));
+    ui.horizontal(|ui| {
+        ui.label("Tint");
+        ui.color_edit_button_rgb(&mut edited.tint);
+        if ui.button("Reset").clicked() {
+            edited = SplatGrade::default();
+        }
+    });
+    (edited != *grade).then_some(edited)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    #[test]
+    fn default_grade_changes_nothing() {
+        let grade = SplatGrade::default();
+        assert!(grade.is_identity());
+        for color in [Vec3::ZERO, Vec3::new(0.2, 0.5, 0.9), Vec3::ONE] {
+            assert!(close(grade.apply(color), color));
+        }
+    }
+
+    #[test]
+    fn grade_controls() {
+        let color = Vec3::new(0.2, 0.4, 0.1);
+        let brighter = SplatGrade { exposure: 1.0, ..default() };
+        assert!(close(brighter.apply(color), color * 2.0));
+
+        let grey = SplatGrade { saturation: 0.0, ..default() }.apply(color);
+        assert!((grey.x - grey.y).abs() < 1e-5 && (grey.y - grey.z).abs() < 1e-5);
+
+        let flat = SplatGrade { contrast: 0.0, ..default() }.apply(color);
+        assert!(close(flat, Vec3::splat(0.5)));
+
+        // A third of the way around the wheel, red becomes green
+        let shifted = SplatGrade { hue: 120.0, ..default() }.apply(Vec3::X);
+        assert!(close(shifted, Vec3::Y), "{}", shifted);
+
+        let tinted = SplatGrade { tint: [1.0, 0.5, 0.0], ..default() }.apply(Vec3::ONE);
+        assert!(close(tinted, Vec3::new(1.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn grade_deserializes_missing_fields() {
+        let grade: SplatGrade = ron::from_str("(exposure: 0.5)").unwrap();
+        assert_eq!(grade, SplatGrade { exposure: 0.5, ..default() });
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+
+use crate::gaussian::{clear_despawned_selection, spawn_splat, GaussianMarker, SelectedSplat, SplatSource};
+use crate::splat_crop::SplatCrop;
+use crate::splat_grade::SplatGrade;
+use crate::ui_window::WindowContexts;
+
+
+#[derive(Component, Reflect, Default)]
+pub struct SplatListWS;
+
+
+pub struct SplatListPlugin;
+
+impl Plugin for SplatListPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedSplat>()
+            .add_systems(Update, (clear_despawned_selection, update_splat_list_ws).chain());
+    }
+}
+
+
+pub fn update_splat_list_ws(
+    windows: Query<Entity, With<SplatListWS>>,
+    mut contexts: WindowContexts,
+    mut splats: Query<
+        (
+            Entity,
+            &Name,
+            &SplatSource,
+            &mut GaussianCloudSettings,
+            &mut Visibility,
+            Option<&SplatCrop>,
+            Option<&SplatGrade>,
+        ),
+        With<GaussianMarker>,
+    >,
+    mut selected: ResMut<SelectedSplat>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    for window in windows.iter() {
+        let Some(ui_ctx) = contexts.get(window) else {
+            continue;
+        };
+        ui_ctx.window("Splats")
+            .resizable(false)
+            .show(&ui_ctx.ctx, |ui| {
+                let mut duplicate = None;
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("splat_list").striped(true).show(ui, |ui| {
+                        ui.label("Splat");
+                        ui.label("Shown");
+                        ui.label("Opacity");
+                        ui.label("Scale");
+                        // Duplicate and Remove buttons
+                        ui.label("");
+                        ui.label("");
+                        ui.end_row();
+
+                        for (entity, name, source, mut settings, mut visibility, crop, grade) in splats.iter_mut() {
+                            let is_selected = selected.0 == Some(entity);
+                            if ui.selectable_label(is_selected, name.as_str()).clicked() {
+                                selected.0 = Some(entity);
+                            }
+
+                            let mut shown = *visibility != Visibility::Hidden;
+                            if ui.checkbox(&mut shown, "").changed() {
+                                *visibility = if shown { Visibility::Visible } else { Visibility::Hidden };
+                            }
+
+                            let mut opacity = settings.global_opacity;
+                            if ui.add(egui::DragValue::new(&mut opacity).range(0.0..=1.0).speed(0.01)).changed() {
+                                settings.global_opacity = opacity;
+                            }
+
+                            let mut scale = settings.transform.scale.x;
+                            if ui.add(egui::DragValue::new(&mut scale).range(0.01..=100.0).speed(0.01)).changed() {
+                                settings.transform.scale = Vec3::splat(scale);
+                            }
+
+                            if ui.button("Duplicate").clicked() {
+                                duplicate = Some((source.0.clone(), settings.clone(), crop.cloned(), grade.copied()));
+                            }
+
+                            if ui.button("Remove").clicked() {
+                                commands.entity(entity).despawn_recursive();
+                                if is_selected {
+                                    selected.0 = None;
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                    if splats.is_empty() {
+                        ui.label("No splats loaded");
+                    }
+                });
+
+                if let Some((asset_path, mut settings, crop, grade)) = duplicate {
+                    // Offset the copy so it doesn't sit inside the original
+                    settings.transform.translation += Vec3::X * 0.5;
+                    let copy = spawn_splat(&mut commands, &asset_server, &asset_path, settings);
+                    if let Some(crop) = crop {
+                        commands.entity(copy).insert(crop);
+                    }
+                    if let Some(grade) = grade {
+                        commands.entity(copy).insert(grade);
+                    }
+                    selected.0 = Some(copy);
+                }
+            });
+    }
+}
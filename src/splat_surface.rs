@@ -0,0 +1,137 @@
+// Gaussians of each splat bucketed into cells, for teleports landing on the surface and the eraser
+// finding what its brush touches without scanning the whole splat
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_gaussian_splatting::GaussianCloud;
+
+use crate::gaussian::GaussianMarker;
+use crate::splat_crop::OriginalCloud;
+
+
+/// Cells the splat surface is split into along its longest side
+const SURFACE_RESOLUTION: f32 = 200.0;
+/// Fewer gaussians than this in a cell is a floater, not a surface
+const SURFACE_MIN_POINTS: usize = 4;
+
+/// Indices of the splat's loaded gaussians bucketed into cells in its local space. Edits never move
+/// gaussians, so this is only built once per loaded cloud.
+#[derive(Component, Debug)]
+pub struct SplatSurface {
+    cell: f32,
+    cells: HashMap<IVec3, Vec<u32>>,
+    source: AssetId<GaussianCloud>,
+}
+
+impl SplatSurface {
+    pub fn build<'a>(positions: impl Iterator<Item = &'a [f32; 3]>, source: AssetId<GaussianCloud>) -> Self {
+        let positions: Vec<Vec3> = positions.map(|p| Vec3::from_array(*p)).collect();
+        let (min, max) = positions
+            .iter()
+            .fold((Vec3::MAX, Vec3::MIN), |(min, max), p| (min.min(*p), max.max(*p)));
+        let cell = ((max - min).max_element() / SURFACE_RESOLUTION).max(1e-3);
+
+        let mut cells: HashMap<IVec3, Vec<u32>> = HashMap::new();
+        for (i, p) in positions.iter().enumerate() {
+            cells.entry((*p / cell).floor().as_ivec3()).or_default().push(i as u32);
+        }
+        Self { cell, cells, source }
+    }
+
+    /// Whether a point in the splat's local space is inside its surface, only gaussians `shown`
+    /// says are drawn count
+    pub fn solid(&self, local: Vec3, shown: impl Fn(usize) -> bool) -> bool {
+        self.cells
+            .get(&(local / self.cell).floor().as_ivec3())
+            .is_some_and(|indices| {
+                let shown = indices
+                    .iter()
+                    .filter(|i| shown(**i as usize))
+                    .take(SURFACE_MIN_POINTS)
+                    .count();
+                shown >= SURFACE_MIN_POINTS
+            })
+    }
+
+    /// Gaussians in the cells a sphere in the splat's local space overlaps, the ones inside it and
+    /// some around it
+    pub fn indices_near(&self, center: Vec3, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let min = ((center - radius) / self.cell).floor();
+        let max = ((center + radius) / self.cell).floor();
+        // A sphere covering more cells than the splat has checks the splat's cells instead
+        let near: Vec<&Vec<u32>> = if (max - min + 1.0).element_product() > self.cells.len() as f32 {
+            let (min, max) = (min.as_ivec3(), max.as_ivec3());
+            self.cells
+                .iter()
+                .filter(|(cell, _)| cell.cmpge(min).all() && cell.cmple(max).all())
+                .map(|(_, indices)| indices)
+                .collect()
+        } else {
+            let (min, max) = (min.as_ivec3(), max.as_ivec3());
+            (min.x..=max.x)
+                .flat_map(|x| (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z))))
+                .filter_map(|cell| self.cells.get(&cell))
+                .collect()
+        };
+        near.into_iter().flatten().map(|i| *i as usize)
+    }
+}
+
+
+pub struct SplatSurfacePlugin;
+
+impl Plugin for SplatSurfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, build_splat_surfaces);
+    }
+}
+
+
+pub fn build_splat_surfaces(
+    splats: Query<
+        (Entity, &Handle<GaussianCloud>, Option<&OriginalCloud>, Option<&SplatSurface>),
+        With<GaussianMarker>,
+    >,
+    clouds: Res<Assets<GaussianCloud>>,
+    mut commands: Commands,
+) {
+    for (entity, handle, original, surface) in splats.iter() {
+        // Indices point into the loaded cloud, not an edited copy
+        let handle = original.map_or(handle, |o| &o.0);
+        if surface.is_some_and(|s| s.source == handle.id()) {
+            continue;
+        }
+        let Some(cloud) = clouds.get(handle) else {
+            continue;
+        };
+        commands.entity(entity).insert(SplatSurface::build(cloud.position_iter(), handle.id()));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_covers_the_sphere() {
+        let positions: Vec<[f32; 3]> = (0..1000).map(|i| [i as f32 * 0.01, 0.0, 0.0]).collect();
+        let surface = SplatSurface::build(positions.iter(), AssetId::default());
+
+        let center = Vec3::new(5.0, 0.0, 0.0);
+        let near: Vec<usize> = surface.indices_near(center, 0.1).collect();
+        let inside = positions
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| Vec3::from_array(**p).distance(center) <= 0.1)
+            .map(|(i, _)| i);
+        for i in inside {
+            assert!(near.contains(&i), "{} missing", i);
+        }
+        assert!(near.len() < 100, "{}", near.len());
+
+        // Bigger than the splat
+        assert_eq!(surface.indices_near(center, 100.0).count(), positions.len());
+    }
+}
@@ -0,0 +1,321 @@
+// Translate / rotate / scale gizmo for the selected splat
+
+use bevy::prelude::*;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use bevy_suis::{
+    window_pointers::MouseInputMethodData, xr::HandInputMethodData,
+    xr_controllers::XrControllerInputMethodData, Field, InputHandler, InputHandlerCaptures,
+};
+
+use crate::edit_history::NoHistory;
+use crate::gaussian::SelectedSplat;
+use crate::grabbing::{self, Grabbed, Grabble};
+use crate::world_scale::WorldScale;
+
+
+const ARROW_LENGTH: f32 = 0.3;
+const RING_RADIUS: f32 = 0.22;
+const HANDLE_SIZE: f32 = 0.04;
+
+/// Root of the gizmo, its translation and rotation plus `scale` get written to the selected splat.
+/// The root itself is the free move handle. A two handed grab scales the root, that gets folded
+/// into `scale` on release so the gizmo returns to its normal size.
+#[derive(Component)]
+pub struct SplatTransformTool {
+    pub scale: f32,
+    /// Root scale when nothing is scaling it, follows [`WorldScale`] so the gizmo keeps its size to the user
+    rest: f32,
+    target: Option<Entity>,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum GizmoHandle {
+    /// Move along the axis, given in the gizmo's local space
+    Translate(Vec3),
+    /// Rotate around the axis, given in the gizmo's local space
+    Rotate(Vec3),
+    /// Uniform scale by distance from the gizmo centre
+    Scale,
+}
+
+/// State captured when a handle is first grabbed
+#[derive(Component, Clone, Copy)]
+pub struct GizmoDrag {
+    root_start: Transform,
+    scale_start: f32,
+    point_start: Vec3,
+    /// Grab point in the input method's local space, so lasers and the mouse drag a point along their ray
+    method_offset: Vec3,
+}
+
+
+pub struct TransformGizmoPlugin;
+
+impl Plugin for TransformGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (drag_gizmo_handles, sync_transform_tool).chain());
+    }
+}
+
+
+pub fn spawn_transform_gizmo(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    initial_transform: Transform,
+) -> Entity {
+    let mut unlit = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            unlit: true,
+            ..default()
+        })
+    };
+    let centre_material = unlit(Srgba::hex("#ffd891").unwrap().into());
+    let axis_materials = [
+        (Vec3::X, unlit(Color::srgb(0.9, 0.2, 0.2))),
+        (Vec3::Y, unlit(Color::srgb(0.2, 0.9, 0.2))),
+        (Vec3::Z, unlit(Color::srgb(0.2, 0.4, 0.95))),
+    ];
+    let scale_material = unlit(Color::WHITE);
+
+    let shaft_mesh = meshes.add(Cylinder::new(HANDLE_SIZE * 0.25, ARROW_LENGTH));
+    let tip_mesh = meshes.add(Cone { radius: HANDLE_SIZE * 0.6, height: HANDLE_SIZE * 1.5 });
+    let ring_mesh = meshes.add(Torus::new(RING_RADIUS - 0.005, RING_RADIUS + 0.005));
+    let knob_mesh = meshes.add(Sphere::new(HANDLE_SIZE * 0.5));
+    let scale_mesh = meshes.add(Cuboid::from_size(Vec3::splat(HANDLE_SIZE * 1.5)));
+
+    let root = commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Sphere::new(HANDLE_SIZE * 1.5)),
+            material: centre_material,
+            transform: Transform::from_translation(initial_transform.translation)
+                .with_rotation(initial_transform.rotation),
+            ..default()
+        },
+        Name::new("Splat Transform Tool"),
+        SplatTransformTool {
+            scale: initial_transform.scale.x,
+            rest: 1.0,
+            target: None,
+        },
+        InputHandler::new(grabbing::capture_condition),
+        Field::Sphere(HANDLE_SIZE * 1.5),
+        Grabble,
+        // Its edits are recorded as changes to the splat
+        NoHistory,
+    )).id();
+
+    for (axis, material) in axis_materials {
+        // Meshes are built along Y, turn them onto the axis
+        let to_axis = Quat::from_rotation_arc(Vec3::Y, axis);
+
+        let arrow = commands.spawn((
+            PbrBundle {
+                mesh: shaft_mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(axis * (ARROW_LENGTH * 0.5 + HANDLE_SIZE))
+                    .with_rotation(to_axis),
+                ..default()
+            },
+            Name::new("Gizmo Arrow"),
+            GizmoHandle::Translate(axis),
+            InputHandler::new(grabbing::capture_condition),
+            Field::Cuboid(Cuboid::new(HANDLE_SIZE, ARROW_LENGTH, HANDLE_SIZE)),
+        )).with_children(|arrow| {
+            arrow.spawn(PbrBundle {
+                mesh: tip_mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(0.0, ARROW_LENGTH * 0.5, 0.0),
+                ..default()
+            });
+        }).id();
+
+        let ring = commands.spawn((
+            PbrBundle {
+                mesh: ring_mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_rotation(to_axis),
+                ..default()
+            },
+            Name::new("Gizmo Ring"),
+        )).with_children(|ring| {
+            // The knob sits on the ring, grabbing it spins the gizmo around the ring's axis
+            ring.spawn((
+                PbrBundle {
+                    mesh: knob_mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(RING_RADIUS, 0.0, 0.0),
+                    ..default()
+                },
+                Name::new("Gizmo Ring Knob"),
+                GizmoHandle::Rotate(axis),
+                InputHandler::new(grabbing::capture_condition),
+                Field::Sphere(HANDLE_SIZE),
+            ));
+        }).id();
+
+        commands.entity(root).push_children(&[arrow, ring]);
+    }
+
+    let scale_handle = commands.spawn((
+        PbrBundle {
+            mesh: scale_mesh,
+            material: scale_material,
+            transform: Transform::from_translation(Vec3::splat(ARROW_LENGTH * 0.5)),
+            ..default()
+        },
+        Name::new("Gizmo Scale"),
+        GizmoHandle::Scale,
+        InputHandler::new(grabbing::capture_condition),
+        Field::Cuboid(Cuboid::from_size(Vec3::splat(HANDLE_SIZE * 1.5))),
+    )).id();
+    commands.entity(root).add_child(scale_handle);
+
+    root
+}
+
+
+/// Applies handle drags to the gizmo root, each handle only changes its own axis
+pub fn drag_gizmo_handles(
+    handles: Query<(
+        Entity,
+        &GizmoHandle,
+        &InputHandlerCaptures,
+        &GlobalTransform,
+        Option<&GizmoDrag>,
+    )>,
+    parents: Query<&Parent>,
+    mut roots: Query<(&mut Transform, &mut SplatTransformTool)>,
+    method_query: Query<(
+        &GlobalTransform,
+        Option<&HandInputMethodData>,
+        Option<&XrControllerInputMethodData>,
+        Option<&MouseInputMethodData>,
+    )>,
+    mut cmds: Commands,
+) {
+    for (handle_entity, handle, captures, handle_gt, drag) in handles.iter() {
+        let Some(root_entity) = parents.iter_ancestors(handle_entity).find(|e| roots.contains(*e)) else {
+            continue;
+        };
+        let Ok((mut root_transform, mut tool)) = roots.get_mut(root_entity) else {
+            continue;
+        };
+
+        let Some((method_gt, hand_data, controller_data, mouse_data)) = captures
+            .captured_methods
+            .first()
+            .and_then(|v| method_query.get(*v).ok())
+        else {
+            if drag.is_some() {
+                cmds.entity(handle_entity).remove::<GizmoDrag>();
+            }
+            continue;
+        };
+
+        if !grabbing::method_grabbing(handle_gt, hand_data, controller_data, mouse_data) {
+            if drag.is_some() {
+                cmds.entity(handle_entity).remove::<GizmoDrag>();
+            }
+            continue;
+        }
+
+        let Some(drag) = drag else {
+            let point_start = handle_gt.translation();
+            cmds.entity(handle_entity).insert(GizmoDrag {
+                root_start: *root_transform,
+                scale_start: tool.scale,
+                point_start,
+                method_offset: method_gt.affine().inverse().transform_point3(point_start),
+            });
+            continue;
+        };
+
+        let point = method_gt.transform_point(drag.method_offset);
+        let centre = drag.root_start.translation;
+
+        match *handle {
+            GizmoHandle::Translate(axis) => {
+                let axis = drag.root_start.rotation * axis;
+                let distance = (point - drag.point_start).dot(axis);
+                root_transform.translation = centre + axis * distance;
+            }
+            GizmoHandle::Rotate(axis) => {
+                let axis = drag.root_start.rotation * axis;
+                let from = (drag.point_start - centre).reject_from(axis);
+                let to = (point - centre).reject_from(axis);
+                if from.length_squared() < 1e-6 || to.length_squared() < 1e-6 {
+                    continue;
+                }
+                let angle = from.angle_between(to) * from.cross(to).dot(axis).signum();
+                root_transform.rotation = Quat::from_axis_angle(axis, angle) * drag.root_start.rotation;
+            }
+            GizmoHandle::Scale => {
+                let start_distance = drag.point_start.distance(centre);
+                if start_distance < 1e-4 {
+                    continue;
+                }
+                let factor = point.distance(centre) / start_distance;
+                tool.scale = (drag.scale_start * factor).clamp(0.01, 100.0);
+            }
+        }
+    }
+}
+
+/// Keeps the gizmo and the selected splat in step. Edits on the gizmo are pushed to the splat,
+/// otherwise the gizmo follows the splat so other tools can move it too.
+pub fn sync_transform_tool(
+    selected: Res<SelectedSplat>,
+    world_scale: Res<WorldScale>,
+    mut tools: Query<(&mut Transform, &mut SplatTransformTool, Has<Grabbed>)>,
+    mut splats: Query<&mut GaussianCloudSettings>,
+) {
+    for (mut tool_transform, mut tool, grabbed) in tools.iter_mut() {
+        // Resized with the user, none of it is a scale edit
+        if tool.rest != world_scale.scale {
+            tool_transform.scale *= world_scale.scale / tool.rest;
+            tool.rest = world_scale.scale;
+        }
+        let rest = Vec3::splat(tool.rest);
+        if !grabbed && tool_transform.scale != rest {
+            tool.scale *= tool_transform.scale.x / tool.rest;
+            tool_transform.scale = rest;
+        }
+        let grab_scale = tool_transform.scale.x / tool.rest;
+
+        let Some(splat) = selected.0 else {
+            tool.bypass_change_detection().target = None;
+            continue;
+        };
+        let Ok(mut settings) = splats.get_mut(splat) else {
+            continue;
+        };
+
+        let tool_changed = tool_transform.is_changed() || tool.is_changed();
+        if tool.target == Some(splat) && tool_changed {
+            let transform = Transform {
+                translation: tool_transform.translation,
+                rotation: tool_transform.rotation,
+                scale: Vec3::splat(tool.scale * grab_scale),
+            };
+            if settings.transform != transform {
+                settings.transform = transform;
+            }
+            continue;
+        }
+
+        // Newly selected splat, or the splat was changed by something else
+        tool.bypass_change_detection().target = Some(splat);
+        let scale = settings.transform.scale.x / grab_scale;
+        if tool.scale != scale {
+            tool.scale = scale;
+        }
+        if tool_transform.translation != settings.transform.translation
+            || tool_transform.rotation != settings.transform.rotation
+        {
+            tool_transform.translation = settings.transform.translation;
+            tool_transform.rotation = settings.transform.rotation;
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Menu windows, floating in the headset or as panels on the screen in desktop mode
+
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContext};
+use bevy_mod_xr::session::XrTrackingRoot;
+use bevy_spatial_egui::SpawnSpatialEguiWindowCommand;
+
+
+/// Where menu windows go
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiMode {
+    /// Each window renders to its own texture in the world
+    Spatial,
+    /// Windows are drawn into the primary window's egui context
+    Screen,
+}
+
+impl Default for UiMode {
+    fn default() -> Self {
+        match cfg!(feature = "desktop") {
+            true => UiMode::Screen,
+            false => UiMode::Spatial,
+        }
+    }
+}
+
+/// A window floating in the world, its entity carries the window's transform
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SpatialWindow;
+
+/// A window drawn on the primary window instead of in the world
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ScreenSpaceWindow {
+    pub default_pos: egui::Pos2,
+}
+
+
+pub struct UiWindowPlugin;
+
+impl Plugin for UiWindowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiMode>();
+    }
+}
+
+
+/// Spawns the window for `target_entity` the way the current [`UiMode`] wants it
+pub struct SpawnUiWindowCommand {
+    pub target_entity: Entity,
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Command for SpawnUiWindowCommand {
+    fn apply(self, world: &mut World) {
+        match world.get_resource::<UiMode>().copied().unwrap_or_default() {
+            UiMode::Spatial => {
+                SpawnSpatialEguiWindowCommand {
+                    target_entity: Some(self.target_entity),
+                    position: self.position,
+                    rotation: self.rotation,
+                    resolution: UVec2::splat(512),
+                    height: 1.0,
+                    unlit: true,
+                }
+                .apply(world);
+                // Positions are around the user, who may have moved or scaled the world since startup
+                let root = world
+                    .query_filtered::<&Transform, With<XrTrackingRoot>>()
+                    .get_single(world)
+                    .ok()
+                    .copied();
+                if let Some(mut entity) = world.get_entity_mut(self.target_entity) {
+                    entity.insert(SpatialWindow);
+                    if let (Some(root), Some(mut transform)) = (root, entity.get_mut::<Transform>()) {
+                        *transform = root.mul_transform(*transform);
+                    }
+                }
+            }
+            UiMode::Screen => {
+                if let Some(mut entity) = world.get_entity_mut(self.target_entity) {
+                    entity.insert(ScreenSpaceWindow {
+                        default_pos: screen_position(self.position),
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub fn spawn_ui_window(commands: &mut Commands, target_entity: Entity, position: Vec3, rotation: Quat) {
+    commands.push(SpawnUiWindowCommand {
+        target_entity,
+        position,
+        rotation,
+    });
+}
+
+/// Lays screen space windows out roughly the way they sit around the main menu in the headset
+fn screen_position(position: Vec3) -> egui::Pos2 {
+    egui::pos2(
+        20.0 + (position.x + 1.1) * 400.0,
+        20.0 + (2.0 - position.y).max(0.0) * 300.0,
+    )
+}
+
+
+/// The egui context a window draws into
+pub struct UiContext {
+    pub ctx: egui::Context,
+    /// Set for screen space windows
+    pub default_pos: Option<egui::Pos2>,
+}
+
+impl UiContext {
+    /// A window filling its spatial texture, or a movable panel on the screen
+    pub fn window(&self, title: impl Into<egui::WidgetText>) -> egui::Window<'static> {
+        let window = egui::Window::new(title);
+        match self.default_pos {
+            Some(pos) => window.default_pos(pos).movable(true),
+            None => window.movable(false),
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct WindowContexts<'w, 's> {
+    contexts: Query<'w, 's, &'static mut EguiContext, Without<PrimaryWindow>>,
+    primary: Query<'w, 's, &'static mut EguiContext, With<PrimaryWindow>>,
+    screen_space: Query<'w, 's, &'static ScreenSpaceWindow>,
+}
+
+impl WindowContexts<'_, '_> {
+    pub fn get(&mut self, window: Entity) -> Option<UiContext> {
+        if let Ok(screen) = self.screen_space.get(window) {
+            let mut ctx = self.primary.get_single_mut().ok()?;
+            return Some(UiContext {
+                ctx: ctx.get_mut().clone(),
+                default_pos: Some(screen.default_pos),
+            });
+        }
+        let mut ctx = self.contexts.get_mut(window).ok()?;
+        Some(UiContext {
+            ctx: ctx.get_mut().clone(),
+            default_pos: None,
+        })
+    }
+}
+
+/// [`WindowContexts::get`] for exclusive systems
+pub fn world_window_context(world: &mut World, window: Entity) -> Option<UiContext> {
+    let default_pos = world.get::<ScreenSpaceWindow>(window).map(|s| s.default_pos);
+    let ctx_entity = match default_pos {
+        Some(_) => world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .get_single(world)
+            .ok()?,
+        None => window,
+    };
+    let mut ctx = world.get_mut::<EguiContext>(ctx_entity)?;
+    Some(UiContext {
+        ctx: ctx.get_mut().clone(),
+        default_pos,
+    })
+}
@@ -0,0 +1,568 @@
+// Moving, pinning, summoning and closing spatial windows, with where they were put remembered
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_mod_xr::camera::XrCamera;
+use bevy_mod_xr::session::XrTrackingRoot;
+use bevy_suis::{
+    window_pointers::MouseInputMethodData, xr::HandInputMethodData,
+    xr_controllers::XrControllerInputMethodData, Field, InputHandler, InputHandlerCaptures,
+};
+use bevy_xr_utils::xr_utils_actions::{
+    ActionType, ActiveSet, XRUtilsAction, XRUtilsActionSet, XRUtilsActionState, XRUtilsBinding,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::edit_history::NoHistory;
+use crate::grabbing::{self, Grabbed, Grabble, GRAB_SEPARATION};
+use crate::locomotion::head_pose;
+use crate::scene_file::{save_settings_file, RonFile, SceneTransform, SettingsFile};
+use crate::ui_window::SpatialWindow;
+
+
+/// Spatial windows are a 1m square, the bar sits under the bottom edge
+const GRAB_BAR_OFFSET: Vec3 = Vec3::new(0.0, -0.56, 0.0);
+const CLOSE_OFFSET: Vec3 = Vec3::new(0.46, 0.56, 0.0);
+const PIN_OFFSET: Vec3 = Vec3::new(0.36, 0.56, 0.0);
+/// How far in front of the head summoned windows are centred
+const SUMMON_DISTANCE: f32 = 1.2;
+/// Seconds both hands have to pinch empty space to summon
+const SUMMON_HOLD: f32 = 0.6;
+/// How quickly following windows catch up with the head, per second
+const FOLLOW_RATE: f32 = 4.0;
+
+/// Where a window was left, in tracking space or relative to the head when following
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SavedWindow {
+    pub transform: SceneTransform,
+    #[serde(default)]
+    pub follow: bool,
+}
+
+/// Window placements by window name, restored whenever a window with that name opens
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct WindowLayout {
+    pub windows: BTreeMap<String, SavedWindow>,
+}
+
+impl RonFile for WindowLayout {}
+
+impl SettingsFile for WindowLayout {
+    const FILE_NAME: &'static str = "windows.ron";
+}
+
+/// Brings every spatial window back in front of the head
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct SummonWindows;
+
+/// Windows nothing else can reopen, like the main menu, get no close button
+#[derive(Component, Clone, Copy, Default)]
+pub struct NoCloseButton;
+
+/// Keeps the window at `offset` from the head, turning with it around the vertical axis
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FollowHead {
+    pub offset: Transform,
+}
+
+/// Handle under a window that drags the window around
+#[derive(Component)]
+pub struct WindowGrabBar {
+    pub window: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowAction {
+    Close,
+    TogglePin,
+}
+
+#[derive(Component)]
+pub struct WindowButton {
+    pub window: Entity,
+    pub action: WindowAction,
+    pressed: bool,
+}
+
+#[derive(Resource)]
+struct WindowMaterials {
+    bar: Handle<StandardMaterial>,
+    close: Handle<StandardMaterial>,
+    pinned: Handle<StandardMaterial>,
+    following: Handle<StandardMaterial>,
+    bar_mesh: Handle<Mesh>,
+    button_mesh: Handle<Mesh>,
+}
+
+#[derive(Component)]
+struct SummonAction;
+
+
+pub struct WindowPlacementPlugin;
+
+impl Plugin for WindowPlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WindowLayout::load_or_default())
+            .add_event::<SummonWindows>()
+            .add_systems(Startup, (setup_window_materials, create_summon_action))
+            .add_systems(
+                Update,
+                (
+                    decorate_new_windows,
+                    drag_windows,
+                    press_window_buttons,
+                    summon_gestures,
+                    summon_windows,
+                    follow_head,
+                    save_settings_file::<WindowLayout>,
+                ).chain().after(grabbing::move_grabble),
+            );
+    }
+}
+
+
+fn setup_window_materials(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut unlit = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            unlit: true,
+            ..default()
+        })
+    };
+    commands.insert_resource(WindowMaterials {
+        bar: unlit(Color::srgb(0.85, 0.85, 0.9)),
+        close: unlit(Color::srgb(0.9, 0.2, 0.2)),
+        pinned: unlit(Color::srgb(0.6, 0.6, 0.6)),
+        following: unlit(Color::srgb(0.2, 0.5, 0.95)),
+        bar_mesh: meshes.add(Capsule3d::new(0.015, 0.3).mesh()),
+        button_mesh: meshes.add(Sphere::new(0.025)),
+    });
+}
+
+fn create_summon_action(mut commands: Commands) {
+    let set = commands.spawn((
+        XRUtilsActionSet {
+            name: "windows".into(),
+            pretty_name: "Windows".into(),
+            priority: u32::MIN,
+        },
+        ActiveSet,
+    )).id();
+
+    let action = commands.spawn((
+        XRUtilsAction {
+            action_name: "summon".into(),
+            localized_name: "Summon windows".into(),
+            action_type: ActionType::Bool,
+        },
+        SummonAction,
+    )).id();
+
+    // The left menu button on Touch, Index has none so it's B there
+    for (profile, binding) in [
+        ("/interaction_profiles/oculus/touch_controller", "/user/hand/left/input/menu/click"),
+        ("/interaction_profiles/valve/index_controller", "/user/hand/left/input/b/click"),
+    ] {
+        let binding = commands.spawn(XRUtilsBinding {
+            profile: profile.into(),
+            binding: binding.into(),
+        }).id();
+        commands.entity(action).add_child(binding);
+    }
+    commands.entity(set).add_child(action);
+}
+
+
+/// Angle around the vertical axis from -Z to `direction`
+fn yaw(direction: Vec3) -> f32 {
+    f32::atan2(-direction.x, -direction.z)
+}
+
+/// The head with only its heading, so following windows don't tilt when looking up or down
+fn head_yaw_frame(head: &Transform) -> Transform {
+    Transform {
+        translation: head.translation,
+        rotation: Quat::from_rotation_y(yaw(head.rotation * Vec3::NEG_Z)),
+        scale: head.scale,
+    }
+}
+
+/// `b` in the space of `a`
+fn relative(a: &Transform, b: &Transform) -> Transform {
+    Transform::from_matrix(a.compute_matrix().inverse() * b.compute_matrix())
+}
+
+/// Rigid move that swings a group of windows centred on `centre` round to straight ahead of the head,
+/// keeping how they're laid out relative to each other
+pub fn summon_transform(head: &Transform, centre: Vec3) -> Transform {
+    let frame = head_yaw_frame(head);
+    let target = head.translation + frame.rotation * Vec3::NEG_Z * SUMMON_DISTANCE * head.scale.x;
+    let to_centre = centre - head.translation;
+    let turn = match to_centre.xz().length_squared() > 1e-6 {
+        true => Quat::from_rotation_y(yaw(frame.rotation * Vec3::NEG_Z) - yaw(to_centre)),
+        false => Quat::IDENTITY,
+    };
+    Transform {
+        translation: target - turn * centre,
+        rotation: turn,
+        scale: Vec3::ONE,
+    }
+}
+
+/// What gets saved for a window, following windows keep their offset from the head
+fn saved_window(
+    transform: &Transform,
+    follow: Option<&FollowHead>,
+    root: Option<&Transform>,
+) -> SavedWindow {
+    match follow {
+        Some(follow) => SavedWindow {
+            transform: follow.offset.into(),
+            follow: true,
+        },
+        None => SavedWindow {
+            transform: root.map_or(*transform, |root| relative(root, transform)).into(),
+            follow: false,
+        },
+    }
+}
+
+
+/// Gives new spatial windows their grab bar and buttons, and puts them back where they were left
+fn decorate_new_windows(
+    mut commands: Commands,
+    mut windows: Query<(Entity, &Name, &mut Transform, Has<NoCloseButton>), (With<SpatialWindow>, Added<SpatialWindow>)>,
+    roots: Query<&Transform, (With<XrTrackingRoot>, Without<SpatialWindow>)>,
+    layout: Res<WindowLayout>,
+    materials: Option<Res<WindowMaterials>>,
+) {
+    let Some(materials) = materials else {
+        return;
+    };
+    let root = roots.get_single().ok();
+
+    for (window, name, mut transform, no_close) in windows.iter_mut() {
+        let saved = layout.windows.get(name.as_str());
+        if let Some(saved) = saved {
+            let placement = Transform::from(saved.transform);
+            if saved.follow {
+                commands.entity(window).insert(FollowHead { offset: placement });
+            } else {
+                *transform = root.map_or(placement, |root| root.mul_transform(placement));
+            }
+        }
+
+        let bar = commands.spawn((
+            PbrBundle {
+                mesh: materials.bar_mesh.clone(),
+                material: materials.bar.clone(),
+                transform: Transform::from_translation(GRAB_BAR_OFFSET)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+                ..default()
+            },
+            Name::new("Window Grab Bar"),
+            WindowGrabBar { window },
+            InputHandler::new(grabbing::capture_condition),
+            Field::Cuboid(Cuboid::new(0.06, 0.36, 0.06)),
+            Grabble,
+            // Window layout is saved on its own
+            NoHistory,
+        )).id();
+        commands.entity(window).add_child(bar);
+
+        let following = saved.is_some_and(|saved| saved.follow);
+        let mut buttons = vec![(WindowAction::TogglePin, PIN_OFFSET, match following {
+            true => materials.following.clone(),
+            false => materials.pinned.clone(),
+        })];
+        if !no_close {
+            buttons.push((WindowAction::Close, CLOSE_OFFSET, materials.close.clone()));
+        }
+        for (action, offset, material) in buttons {
+            let button = commands.spawn((
+                PbrBundle {
+                    mesh: materials.button_mesh.clone(),
+                    material,
+                    transform: Transform::from_translation(offset),
+                    ..default()
+                },
+                Name::new(match action {
+                    WindowAction::Close => "Window Close",
+                    WindowAction::TogglePin => "Window Pin",
+                }),
+                WindowButton { window, action, pressed: false },
+                InputHandler::new(grabbing::capture_condition),
+                Field::Sphere(0.035),
+            )).id();
+            commands.entity(window).add_child(button);
+        }
+    }
+}
+
+/// Moves the window along with its grab bar, the bar stays put under it
+fn drag_windows(
+    mut bars: Query<(&WindowGrabBar, &mut Transform), With<Grabbed>>,
+    mut windows: Query<(&Name, &mut Transform, Option<&mut FollowHead>), (With<SpatialWindow>, Without<WindowGrabBar>)>,
+    mut released: RemovedComponents<Grabbed>,
+    released_bars: Query<&WindowGrabBar>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    roots: Query<&Transform, (With<XrTrackingRoot>, Without<WindowGrabBar>, Without<SpatialWindow>)>,
+    mut layout: ResMut<WindowLayout>,
+) {
+    let rest = Transform::from_translation(GRAB_BAR_OFFSET)
+        .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+
+    for (bar, mut bar_transform) in bars.iter_mut() {
+        let Ok((_, mut window, _)) = windows.get_mut(bar.window) else {
+            continue;
+        };
+        // Moved and turned only, two handed grabs don't resize windows
+        let held = bar_transform.with_scale(Vec3::ONE);
+        let scale = window.scale;
+        *window = Transform::from_matrix(
+            window.compute_matrix() * held.compute_matrix() * rest.compute_matrix().inverse(),
+        ).with_scale(scale);
+        *bar_transform = rest;
+    }
+
+    let head = head_pose(&cameras).map(|head| head_yaw_frame(&head));
+    let root = roots.get_single().ok();
+    for bar in released.read() {
+        let Ok(bar) = released_bars.get(bar) else {
+            continue;
+        };
+        let Ok((name, window, follow)) = windows.get_mut(bar.window) else {
+            continue;
+        };
+        let follow = follow.map(|mut follow| {
+            if let Some(head) = &head {
+                follow.offset = relative(head, &window);
+            }
+            *follow
+        });
+        layout.windows.insert(name.to_string(), saved_window(&window, follow.as_ref(), root));
+    }
+}
+
+fn press_window_buttons(
+    mut commands: Commands,
+    mut buttons: Query<(&InputHandlerCaptures, &GlobalTransform, &mut WindowButton, &mut Handle<StandardMaterial>)>,
+    method_query: Query<(
+        Option<&HandInputMethodData>,
+        Option<&XrControllerInputMethodData>,
+        Option<&MouseInputMethodData>,
+    )>,
+    windows: Query<(&Name, &Transform, Option<&FollowHead>)>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    roots: Query<&Transform, With<XrTrackingRoot>>,
+    materials: Option<Res<WindowMaterials>>,
+    mut layout: ResMut<WindowLayout>,
+) {
+    let Some(materials) = materials else {
+        return;
+    };
+    for (captures, button_gt, mut button, mut material) in buttons.iter_mut() {
+        let pressed = captures.captured_methods.iter().any(|method| {
+            method_query.get(*method).is_ok_and(|(hand, controller, mouse)| {
+                grabbing::method_grabbing(button_gt, hand, controller, mouse)
+            })
+        });
+        let clicked = pressed && !button.pressed;
+        button.pressed = pressed;
+        if !clicked {
+            continue;
+        }
+
+        match button.action {
+            WindowAction::Close => {
+                commands.entity(button.window).despawn_recursive();
+            }
+            WindowAction::TogglePin => {
+                let Ok((name, transform, follow)) = windows.get(button.window) else {
+                    continue;
+                };
+                let saved = match follow {
+                    Some(_) => {
+                        commands.entity(button.window).remove::<FollowHead>();
+                        *material = materials.pinned.clone();
+                        saved_window(transform, None, roots.get_single().ok())
+                    }
+                    None => {
+                        let Some(head) = head_pose(&cameras) else {
+                            continue;
+                        };
+                        let follow = FollowHead {
+                            offset: relative(&head_yaw_frame(&head), transform),
+                        };
+                        commands.entity(button.window).insert(follow);
+                        *material = materials.following.clone();
+                        saved_window(transform, Some(&follow), None)
+                    }
+                };
+                layout.windows.insert(name.to_string(), saved);
+            }
+        }
+    }
+}
+
+/// Left menu button, or both hands pinching empty space for a moment
+fn summon_gestures(
+    actions: Query<&XRUtilsActionState, With<SummonAction>>,
+    hands: Query<(Entity, &HandInputMethodData)>,
+    handlers: Query<&InputHandlerCaptures>,
+    time: Res<Time<Real>>,
+    mut button_down: Local<bool>,
+    mut pinching_since: Local<Option<f32>>,
+    mut summon: EventWriter<SummonWindows>,
+) {
+    let pressed = actions.iter().any(|state| match state {
+        XRUtilsActionState::Bool(state) => state.current_state,
+        _ => false,
+    });
+    if pressed && !*button_down {
+        summon.send(SummonWindows);
+    }
+    *button_down = pressed;
+
+    let pinching = hands
+        .iter()
+        .filter(|(method, hand)| {
+            let holding = handlers.iter().any(|c| c.captured_methods.contains(method));
+            let hand = hand.get_in_relative_space(&GlobalTransform::IDENTITY);
+            !holding && grabbing::finger_separation(&hand, GRAB_SEPARATION)
+        })
+        .count();
+    if pinching < 2 {
+        *pinching_since = None;
+        return;
+    }
+    let now = time.elapsed_seconds();
+    match *pinching_since {
+        None => *pinching_since = Some(now),
+        Some(since) if now - since >= SUMMON_HOLD => {
+            summon.send(SummonWindows);
+            // Once per pinch, the hands have to let go first
+            *pinching_since = Some(f32::INFINITY);
+        }
+        Some(_) => {}
+    }
+}
+
+fn summon_windows(
+    mut events: EventReader<SummonWindows>,
+    mut windows: Query<(&Name, &mut Transform, Option<&mut FollowHead>), With<SpatialWindow>>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    roots: Query<&Transform, (With<XrTrackingRoot>, Without<SpatialWindow>)>,
+    mut layout: ResMut<WindowLayout>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(head) = head_pose(&cameras) else {
+        return;
+    };
+    let count = windows.iter().count();
+    if count == 0 {
+        return;
+    }
+    let centre = windows.iter().map(|(_, t, _)| t.translation).sum::<Vec3>() / count as f32;
+    let summon = summon_transform(&head, centre);
+    let frame = head_yaw_frame(&head);
+    let root = roots.get_single().ok();
+
+    for (name, mut transform, follow) in windows.iter_mut() {
+        *transform = summon.mul_transform(*transform);
+        let follow = follow.map(|mut follow| {
+            follow.offset = relative(&frame, &transform);
+            *follow
+        });
+        layout.windows.insert(name.to_string(), saved_window(&transform, follow.as_ref(), root));
+    }
+}
+
+fn follow_head(
+    mut windows: Query<(&mut Transform, &FollowHead, &Children)>,
+    grabbed_bars: Query<(), (With<WindowGrabBar>, With<Grabbed>)>,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    time: Res<Time>,
+) {
+    let Some(head) = head_pose(&cameras) else {
+        return;
+    };
+    let frame = head_yaw_frame(&head);
+    let t = 1.0 - (-FOLLOW_RATE * time.delta_seconds()).exp();
+
+    for (mut transform, follow, children) in windows.iter_mut() {
+        // Being dragged, the offset is taken again on release
+        if children.iter().any(|c| grabbed_bars.contains(*c)) {
+            continue;
+        }
+        let target = frame.mul_transform(follow.offset);
+        transform.translation = transform.translation.lerp(target.translation, t);
+        transform.rotation = transform.rotation.slerp(target.rotation, t);
+        transform.scale = target.scale;
+    }
+}
+
+/// Summon button for menus
+#[derive(SystemParam)]
+pub struct WindowControls<'w> {
+    summon: EventWriter<'w, SummonWindows>,
+}
+
+impl WindowControls<'_> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Summon Windows").clicked() {
+            self.summon.send(SummonWindows);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summon_brings_windows_in_front() {
+        let head = Transform::from_xyz(2.0, 1.6, 1.0).with_rotation(Quat::from_rotation_y(0.7));
+        let windows = [Vec3::new(-3.0, 1.5, 4.0), Vec3::new(-2.0, 2.0, 4.0)];
+        let centre = (windows[0] + windows[1]) * 0.5;
+
+        let summon = summon_transform(&head, centre);
+        let ahead = head.translation + head.rotation * Vec3::NEG_Z * SUMMON_DISTANCE;
+        let moved = summon.transform_point(centre);
+        assert!(moved.distance(ahead) < 1e-4, "{} {}", moved, ahead);
+        // Moved as a group
+        let spacing = summon.transform_point(windows[0]).distance(summon.transform_point(windows[1]));
+        assert!((spacing - windows[0].distance(windows[1])).abs() < 1e-4);
+    }
+
+    #[test]
+    fn following_ignores_head_pitch() {
+        let head = Transform::from_xyz(0.0, 1.6, 0.0)
+            .with_rotation(Quat::from_rotation_y(0.5) * Quat::from_rotation_x(-0.8));
+        let frame = head_yaw_frame(&head);
+        assert!(frame.rotation.angle_between(Quat::from_rotation_y(0.5)) < 1e-4);
+    }
+
+    #[test]
+    fn layout_round_trip() {
+        let mut layout = WindowLayout::default();
+        layout.windows.insert("Main Menu".into(), SavedWindow {
+            transform: Transform::from_xyz(0.0, 1.5, -0.8).into(),
+            follow: true,
+        });
+        let loaded = WindowLayout::from_ron(&layout.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded, layout);
+        assert_eq!(WindowLayout::from_ron("()").unwrap(), WindowLayout::default());
+    }
+}
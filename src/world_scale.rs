@@ -0,0 +1,246 @@
+// Shrinks the scene to a tabletop model or grows it back to life size, by scaling the tracking root
+// about a pivot. Menus and hand tools are scaled along with the user so they keep their size.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use bevy_mod_xr::camera::XrCamera;
+use bevy_mod_xr::session::XrTrackingRoot;
+use bevy_suis::{xr::HandInputMethodData, xr_controllers::XrControllerInputMethodData};
+
+use crate::gaussian::SelectedSplat;
+use crate::grabbing::Grabbed;
+use crate::locomotion::{head_pose, rightmost};
+use crate::ui_window::SpatialWindow;
+
+
+pub const LIFE_SIZE: f32 = 1.0;
+/// 1:50, a room fits on a table
+pub const TABLETOP: f32 = 50.0;
+const MAX_SCALE: f32 = 200.0;
+const TRANSITION_SECONDS: f32 = 0.8;
+
+/// Point that stays put while the world is scaled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScalePivot {
+    /// The floor under the head, you stay where you are in the scene
+    #[default]
+    Feet,
+    /// Put a hand on a spot in a tabletop model and grow into it
+    Hand,
+    SelectedSplat,
+}
+
+/// How much bigger the user is than the scene, 50 shows it at 1:50
+#[derive(Resource, Debug)]
+pub struct WorldScale {
+    /// Where the tracking root is right now
+    pub scale: f32,
+    /// Animated towards when it differs from `scale`
+    pub target: f32,
+    pub pivot: ScalePivot,
+    transition: Option<ScaleTransition>,
+}
+
+impl Default for WorldScale {
+    fn default() -> Self {
+        Self {
+            scale: LIFE_SIZE,
+            target: LIFE_SIZE,
+            pivot: ScalePivot::default(),
+            transition: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ScaleTransition {
+    from: f32,
+    to: f32,
+    pivot: Vec3,
+    elapsed: f32,
+}
+
+impl ScaleTransition {
+    /// Eased in log space so each halving takes as long as each doubling
+    fn scale_at(&self, elapsed: f32) -> f32 {
+        let t = (elapsed / TRANSITION_SECONDS).clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        self.from * (self.to / self.from).powf(eased)
+    }
+}
+
+/// Root level entities that ride along with the user when the world is scaled, like the hand cam.
+/// Spatial windows are included without it.
+#[derive(Component, Clone, Copy, Default)]
+pub struct ScalesWithUser;
+
+
+pub struct WorldScalePlugin;
+
+impl Plugin for WorldScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldScale>()
+            .add_systems(Update, (place_new_riders, scale_world).chain());
+    }
+}
+
+
+/// Scales `transform` about `pivot`, a point there stays where it is
+pub fn scale_about(transform: &mut Transform, pivot: Vec3, ratio: f32) {
+    transform.translation = pivot + (transform.translation - pivot) * ratio;
+    transform.scale *= ratio;
+}
+
+/// Riders are spawned around the user, who may have moved or scaled the world since startup
+fn place_new_riders(
+    roots: Query<&Transform, With<XrTrackingRoot>>,
+    mut riders: Query<&mut Transform, (Added<ScalesWithUser>, Without<XrTrackingRoot>, Without<Parent>)>,
+) {
+    let Ok(root) = roots.get_single() else {
+        return;
+    };
+    for mut transform in riders.iter_mut() {
+        *transform = root.mul_transform(*transform);
+    }
+}
+
+fn scale_world(
+    mut world_scale: ResMut<WorldScale>,
+    mut roots: Query<&mut Transform, With<XrTrackingRoot>>,
+    mut riders: Query<
+        &mut Transform,
+        (
+            Or<(With<ScalesWithUser>, With<SpatialWindow>)>,
+            Without<Grabbed>,
+            Without<XrTrackingRoot>,
+            Without<Parent>,
+        ),
+    >,
+    cameras: Query<&GlobalTransform, With<XrCamera>>,
+    hands: Query<&GlobalTransform, Or<(With<HandInputMethodData>, With<XrControllerInputMethodData>)>>,
+    selected: Res<SelectedSplat>,
+    splats: Query<&GaussianCloudSettings>,
+    time: Res<Time>,
+) {
+    let Ok(mut root) = roots.get_single_mut() else {
+        return;
+    };
+
+    let retarget = match world_scale.transition {
+        Some(transition) => transition.to != world_scale.target,
+        None => (world_scale.target - world_scale.scale).abs() > 1e-4,
+    };
+    if retarget {
+        let Some(head) = head_pose(&cameras) else {
+            return;
+        };
+        let feet = head.translation.with_y(root.translation.y);
+        let pivot = match world_scale.pivot {
+            ScalePivot::Feet => Some(feet),
+            ScalePivot::Hand => rightmost(&head, hands.iter()).map(|gt| gt.translation()),
+            ScalePivot::SelectedSplat => selected
+                .0
+                .and_then(|e| splats.get(e).ok())
+                .map(|settings| settings.transform.translation),
+        };
+        // Keep going about the same point if the target changes halfway
+        let pivot = world_scale.transition.map(|t| t.pivot).or(pivot).unwrap_or(feet);
+        world_scale.transition = Some(ScaleTransition {
+            from: world_scale.scale,
+            to: world_scale.target,
+            pivot,
+            elapsed: 0.0,
+        });
+    }
+
+    let Some(mut transition) = world_scale.transition else {
+        return;
+    };
+    transition.elapsed += time.delta_seconds();
+    let done = transition.elapsed >= TRANSITION_SECONDS;
+    let scale = match done {
+        true => transition.to,
+        false => transition.scale_at(transition.elapsed),
+    };
+    let ratio = scale / world_scale.scale;
+
+    scale_about(&mut root, transition.pivot, ratio);
+    for mut transform in riders.iter_mut() {
+        scale_about(&mut transform, transition.pivot, ratio);
+    }
+
+    world_scale.scale = scale;
+    world_scale.transition = (!done).then_some(transition);
+}
+
+
+/// Scale presets and pivot for menus
+#[derive(SystemParam)]
+pub struct WorldScaleControls<'w> {
+    world_scale: ResMut<'w, WorldScale>,
+}
+
+impl WorldScaleControls<'_> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut target = self.world_scale.target;
+        let mut pivot = self.world_scale.pivot;
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut target, TABLETOP, format!("Tabletop 1:{}", TABLETOP));
+            ui.selectable_value(&mut target, LIFE_SIZE, "Life Size 1:1");
+        });
+        ui.add(
+            egui::Slider::new(&mut target, LIFE_SIZE..=MAX_SCALE)
+                .logarithmic(true)
+                .prefix("1:"),
+        );
+        ui.horizontal(|ui| {
+            ui.label("Around");
+            ui.selectable_value(&mut pivot, ScalePivot::Feet, "Feet");
+            ui.selectable_value(&mut pivot, ScalePivot::Hand, "Hand");
+            ui.selectable_value(&mut pivot, ScalePivot::SelectedSplat, "Selected Splat");
+        });
+
+        if target != self.world_scale.target {
+            self.world_scale.target = target;
+        }
+        if pivot != self.world_scale.pivot {
+            self.world_scale.pivot = pivot;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pivot_stays_put() {
+        let pivot = Vec3::new(1.0, 0.0, -2.0);
+        let mut root = Transform::from_xyz(3.0, 0.0, 1.0);
+        let local = root.compute_affine().inverse().transform_point3(pivot);
+
+        scale_about(&mut root, pivot, TABLETOP);
+        assert_eq!(root.scale, Vec3::splat(TABLETOP));
+        // Same spot in tracking space, so it looks like it hasn't moved
+        assert!(root.transform_point(local).distance(pivot) < 1e-4);
+    }
+
+    #[test]
+    fn transition_eases_between_scales() {
+        let transition = ScaleTransition {
+            from: LIFE_SIZE,
+            to: TABLETOP,
+            pivot: Vec3::ZERO,
+            elapsed: 0.0,
+        };
+        assert_eq!(transition.scale_at(0.0), LIFE_SIZE);
+        assert!((transition.scale_at(TRANSITION_SECONDS) - TABLETOP).abs() < 1e-3);
+        // Halfway in log space is the geometric mean
+        let halfway = transition.scale_at(TRANSITION_SECONDS * 0.5);
+        assert!((halfway - TABLETOP.sqrt()).abs() < 1e-3, "{}", halfway);
+    }
+}
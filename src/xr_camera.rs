@@ -0,0 +1,116 @@
+// Keeps the XR cameras rendering splats
+
+use bevy::prelude::*;
+use bevy_gaussian_splatting::GaussianCamera;
+use bevy_mod_xr::camera::XrCamera;
+
+
+/// Whether XR cameras get a [`GaussianCamera`]. The XR cameras are recreated whenever the
+/// session restarts, so this is checked every time a new one shows up.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct XrGaussianCameraSettings {
+    pub enabled: bool,
+}
+
+impl Default for XrGaussianCameraSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+
+pub struct XrGaussianCameraPlugin;
+
+impl Plugin for XrGaussianCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrGaussianCameraSettings>()
+            .add_systems(
+                PostUpdate,
+                (
+                    apply_xr_gaussian_settings.run_if(resource_changed::<XrGaussianCameraSettings>),
+                    add_gaussian_to_new_xr_cams,
+                ).chain(),
+            );
+    }
+}
+
+
+/// Attaches a [`GaussianCamera`] to XR cameras as they get spawned
+pub fn add_gaussian_to_new_xr_cams(
+    mut commands: Commands,
+    settings: Res<XrGaussianCameraSettings>,
+    new_xr_cams: Query<Entity, (Added<XrCamera>, Without<GaussianCamera>)>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    for xr_cam in new_xr_cams.iter() {
+        add_gaussian_to_xr(&mut commands, xr_cam);
+    }
+}
+
+/// Brings the existing XR cameras in line when the setting is toggled
+pub fn apply_xr_gaussian_settings(
+    mut commands: Commands,
+    settings: Res<XrGaussianCameraSettings>,
+    xr_cams: Query<(Entity, Has<GaussianCamera>), With<XrCamera>>,
+) {
+    for (xr_cam, has_gaussian) in xr_cams.iter() {
+        match (settings.enabled, has_gaussian) {
+            (true, false) => add_gaussian_to_xr(&mut commands, xr_cam),
+            (false, true) => {
+                commands.entity(xr_cam).remove::<GaussianCamera>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_gaussian_to_xr(commands: &mut Commands, xr_cam: Entity) {
+    commands.entity(xr_cam).insert((
+        GaussianCamera { warmup: true },
+        Name::new("Xr Cam"),
+        //Tonemapping::None, makes thing look wonko
+    ));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(XrGaussianCameraPlugin);
+        app
+    }
+
+    #[test]
+    fn new_xr_cameras_get_gaussian_camera() {
+        let mut app = test_app();
+        let first = app.world_mut().spawn(XrCamera(0)).id();
+        app.update();
+        assert!(app.world().get::<GaussianCamera>(first).is_some());
+
+        // Session restart, the old cameras go away and new ones are spawned
+        app.world_mut().despawn(first);
+        let second = app.world_mut().spawn(XrCamera(0)).id();
+        app.update();
+        assert!(app.world().get::<GaussianCamera>(second).is_some());
+    }
+
+    #[test]
+    fn opting_out_removes_gaussian_camera() {
+        let mut app = test_app();
+        let cam = app.world_mut().spawn(XrCamera(0)).id();
+        app.update();
+
+        app.world_mut().resource_mut::<XrGaussianCameraSettings>().enabled = false;
+        app.update();
+        assert!(app.world().get::<GaussianCamera>(cam).is_none());
+
+        let new_cam = app.world_mut().spawn(XrCamera(1)).id();
+        app.update();
+        assert!(app.world().get::<GaussianCamera>(new_cam).is_none());
+    }
+}
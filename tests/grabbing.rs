@@ -0,0 +1,145 @@
+mod harness;
+
+use bevy::prelude::*;
+use bevy_gaussian_oxr_simple::edit_history::HistoryAction;
+use bevy_gaussian_oxr_simple::grabbing::Grabbed;
+use harness::*;
+
+
+#[test]
+fn controller_squeeze_grabs_and_moves() {
+    let mut app = test_app();
+    let controller = spawn_controller(&mut app, Transform::from_xyz(0.0, 1.0, 0.0));
+    let handler = spawn_grabbable(&mut app, Transform::from_xyz(0.0, 1.0, -0.2), &[controller]);
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_none());
+
+    set_squeezed(&mut app, controller, true);
+    app.update();
+    let grabbed = app.world().get::<Grabbed>(handler).expect("not grabbed");
+    assert_eq!(grabbed.method, controller);
+    assert!(grabbed.second.is_none());
+
+    // Transforms propagate at the end of the frame, the handler follows on the next
+    move_method(&mut app, controller, Vec3::new(0.5, 1.5, 0.0));
+    app.update();
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(0.5, 1.5, -0.2)) < 1e-4, "{}", translation);
+
+    set_squeezed(&mut app, controller, false);
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_none());
+    move_method(&mut app, controller, Vec3::new(2.0, 0.0, 0.0));
+    app.update();
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(0.5, 1.5, -0.2)) < 1e-4, "{}", translation);
+}
+
+#[test]
+fn mouse_button_grabs() {
+    let mut app = test_app();
+    let mouse = spawn_mouse(&mut app, Transform::from_xyz(0.0, 1.0, 1.0));
+    let handler = spawn_grabbable(&mut app, Transform::from_xyz(0.0, 1.0, 0.0), &[mouse]);
+    app.update();
+
+    set_mouse_pressed(&mut app, mouse, true);
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_some());
+
+    move_method(&mut app, mouse, Vec3::new(1.0, 1.0, 1.0));
+    app.update();
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-4, "{}", translation);
+
+    set_mouse_pressed(&mut app, mouse, false);
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_none());
+}
+
+#[test]
+fn two_controllers_scale() {
+    let mut app = test_app();
+    let left = spawn_controller(&mut app, Transform::from_xyz(-0.5, 1.0, 0.0));
+    let right = spawn_controller(&mut app, Transform::from_xyz(0.5, 1.0, 0.0));
+    let handler = spawn_grabbable(&mut app, Transform::from_xyz(0.0, 1.0, 0.0), &[left, right]);
+    app.update();
+
+    set_squeezed(&mut app, left, true);
+    set_squeezed(&mut app, right, true);
+    app.update();
+    let grabbed = app.world().get::<Grabbed>(handler).expect("not grabbed");
+    assert!(grabbed.second.is_some_and(|s| s.method == right));
+
+    // Hands twice as far apart
+    move_method(&mut app, left, Vec3::new(-1.0, 1.0, 0.0));
+    move_method(&mut app, right, Vec3::new(1.0, 1.0, 0.0));
+    app.update();
+    app.update();
+    let transform = app.world().get::<Transform>(handler).unwrap();
+    assert!((transform.scale - Vec3::splat(2.0)).length() < 1e-4, "{}", transform.scale);
+    assert!(transform.translation.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-4);
+}
+
+#[test]
+fn undo_puts_a_grab_back() {
+    let mut app = test_app();
+    let controller = spawn_controller(&mut app, Transform::from_xyz(0.0, 1.0, 0.0));
+    let handler = spawn_grabbable(&mut app, Transform::from_xyz(0.0, 1.0, -0.2), &[controller]);
+    app.update();
+
+    // One entry for the whole grab, however many frames it moved for
+    set_squeezed(&mut app, controller, true);
+    app.update();
+    for x in [0.1, 0.2, 0.3] {
+        move_method(&mut app, controller, Vec3::new(x, 1.0, 0.0));
+        app.update();
+        app.update();
+    }
+    set_squeezed(&mut app, controller, false);
+    app.update();
+    app.update();
+
+    app.world_mut().send_event(HistoryAction::Undo);
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(0.0, 1.0, -0.2)) < 1e-4, "{}", translation);
+
+    app.world_mut().send_event(HistoryAction::Redo);
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(0.3, 1.0, -0.2)) < 1e-4, "{}", translation);
+}
+
+#[test]
+fn pinch_captures_and_grabs() {
+    let mut app = test_app();
+    let hand = spawn_hand(&mut app, Transform::from_xyz(0.0, 1.0, 0.0));
+    let handler = spawn_capturing_grabbable(&mut app, Transform::from_xyz(0.0, 1.0, -0.05));
+    app.update();
+
+    // An open hand next to it doesn't take it
+    assert!(!try_capture(&mut app, handler, hand, 0.03));
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_none());
+
+    set_pinched(&mut app, hand, true);
+    assert!(try_capture(&mut app, handler, hand, 0.03));
+    // Already holding on, no second capture by the same hand
+    assert!(!try_capture(&mut app, handler, hand, 0.03));
+    app.update();
+    let grabbed = app.world().get::<Grabbed>(handler).expect("not grabbed");
+    assert_eq!(grabbed.method, hand);
+
+    move_hand(&mut app, hand, Vec3::new(0.3, 1.2, 0.0), true);
+    app.update();
+    app.update();
+    let translation = app.world().get::<Transform>(handler).unwrap().translation;
+    assert!(translation.distance(Vec3::new(0.3, 1.2, -0.05)) < 1e-4, "{}", translation);
+
+    set_pinched(&mut app, hand, false);
+    app.update();
+    assert!(app.world().get::<Grabbed>(handler).is_none());
+}
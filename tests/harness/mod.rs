@@ -0,0 +1,328 @@
+// Headless app for driving the menus, grabbing and keyboard without a GPU or headset.
+//
+// egui contexts live on plain entities and are stepped by `begin_egui_frames` / `end_egui_frames`
+// instead of bevy_egui, clicks are simulated by finding a widget's text in the last frame's shapes.
+// Input methods are plain entities with suis method data. Most tests capture them by writing
+// `InputHandlerCaptures` directly, `try_capture` runs a handler's real capture condition instead.
+// Hands are a minimal joint set, only the thumb and index tips move for pinching.
+
+#![allow(dead_code)]
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContext, EguiInput};
+use bevy_gaussian_oxr_simple::edit_history::EditHistoryPlugin;
+use bevy_gaussian_oxr_simple::file_browser::SplatFileChosen;
+use bevy_gaussian_oxr_simple::gaussian::SelectedSplat;
+use bevy_gaussian_oxr_simple::grabbing::{self, Grabble};
+use bevy_gaussian_oxr_simple::hand_cam_capture::HandCamShutter;
+use bevy_gaussian_oxr_simple::keyboard::KeybaordWSPlugin;
+use bevy_gaussian_oxr_simple::main_menu::MainMenuPlugin;
+use bevy_gaussian_oxr_simple::passthrough::{ActiveBlend, PassthroughSettings, SupportedBlendModes};
+use bevy_gaussian_oxr_simple::refresh_rate::{RefreshRateSettings, RefreshRateState};
+use bevy_gaussian_oxr_simple::scene_file::SceneFileEvent;
+use bevy_gaussian_oxr_simple::ui_window::UiMode;
+use bevy_gaussian_oxr_simple::window_placement::SummonWindows;
+use bevy_gaussian_oxr_simple::world_scale::WorldScale;
+use bevy_gaussian_oxr_simple::xr_camera::XrGaussianCameraSettings;
+use bevy_gaussian_splatting::GaussianCloud;
+use bevy::ecs::system::RunSystemOnce;
+use bevy_suis::{
+    window_pointers::MouseInputMethodData,
+    xr::{Finger, Hand, HandInputMethodData, Joint, Thumb},
+    xr_controllers::XrControllerInputMethodData,
+    CaptureContext, InputHandlerCaptures,
+};
+
+
+pub const SCREEN_SIZE: f32 = 2048.0;
+
+/// What the context on this entity drew last frame
+#[derive(Component, Default)]
+pub struct LastEguiOutput(pub egui::FullOutput);
+
+
+/// Menus in screen space on a fake primary window, the keyboard plugin and grabbing
+pub fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .init_asset::<Image>()
+    .init_asset::<GaussianCloud>()
+    .add_event::<SplatFileChosen>()
+    .add_event::<SceneFileEvent>()
+    .add_event::<KeyboardInput>()
+    .add_event::<HandCamShutter>()
+    .add_event::<SummonWindows>()
+    .init_resource::<SelectedSplat>()
+    .init_resource::<XrGaussianCameraSettings>()
+    .init_resource::<PassthroughSettings>()
+    .init_resource::<SupportedBlendModes>()
+    .init_resource::<ActiveBlend>()
+    .init_resource::<RefreshRateSettings>()
+    .init_resource::<RefreshRateState>()
+    .init_resource::<WorldScale>()
+    .insert_resource(UiMode::Screen)
+    .add_plugins((MainMenuPlugin, KeybaordWSPlugin, EditHistoryPlugin))
+    .add_systems(PreUpdate, begin_egui_frames)
+    .add_systems(Update, grabbing::move_grabble)
+    .add_systems(PostUpdate, end_egui_frames);
+
+    app.world_mut().spawn((
+        Name::new("Primary Window"),
+        PrimaryWindow,
+        egui_bundle(),
+    ));
+
+    // Startup, then let new egui windows get past their invisible sizing pass
+    for _ in 0..3 {
+        app.update();
+    }
+    app
+}
+
+pub fn egui_bundle() -> (EguiContext, EguiInput, LastEguiOutput) {
+    (EguiContext::default(), EguiInput::default(), LastEguiOutput::default())
+}
+
+pub fn primary_window(app: &mut App) -> Entity {
+    app.world_mut()
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .single(app.world())
+}
+
+
+fn begin_egui_frames(mut ctxs: Query<(&mut EguiContext, &mut EguiInput)>) {
+    for (mut ctx, mut input) in ctxs.iter_mut() {
+        let mut raw_input = std::mem::take(&mut input.0);
+        raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(SCREEN_SIZE, SCREEN_SIZE),
+        ));
+        ctx.get_mut().begin_frame(raw_input);
+    }
+}
+
+fn end_egui_frames(mut ctxs: Query<(&mut EguiContext, &mut LastEguiOutput)>) {
+    for (mut ctx, mut output) in ctxs.iter_mut() {
+        output.0 = ctx.get_mut().end_frame();
+    }
+}
+
+
+/// Screen rect of the first text drawn last frame that reads exactly `text`
+pub fn find_text(app: &App, ctx_entity: Entity, text: &str) -> Option<egui::Rect> {
+    fn find(shape: &egui::Shape, text: &str) -> Option<egui::Rect> {
+        match shape {
+            egui::Shape::Text(shape) if shape.galley.text() == text => Some(
+                egui::Rect::from_min_size(shape.pos, shape.galley.size()),
+            ),
+            egui::Shape::Vec(shapes) => shapes.iter().find_map(|s| find(s, text)),
+            _ => None,
+        }
+    }
+
+    let output = app.world().get::<LastEguiOutput>(ctx_entity)?;
+    output.0.shapes.iter().find_map(|clipped| find(&clipped.shape, text))
+}
+
+pub fn push_egui_events(app: &mut App, ctx_entity: Entity, events: impl IntoIterator<Item = egui::Event>) {
+    let mut input = app
+        .world_mut()
+        .get_mut::<EguiInput>(ctx_entity)
+        .expect("entity has no EguiInput");
+    input.0.events.extend(events);
+}
+
+fn pointer_button(pos: egui::Pos2, pressed: bool) -> egui::Event {
+    egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Primary,
+        pressed,
+        modifiers: egui::Modifiers::default(),
+    }
+}
+
+/// Presses the pointer on the widget showing `text`, one frame
+pub fn press_text(app: &mut App, ctx_entity: Entity, text: &str) -> egui::Pos2 {
+    let pos = find_text(app, ctx_entity, text)
+        .unwrap_or_else(|| panic!("no {:?} drawn", text))
+        .center();
+    push_egui_events(app, ctx_entity, [egui::Event::PointerMoved(pos), pointer_button(pos, true)]);
+    app.update();
+    pos
+}
+
+/// Lets go of the pointer, one frame
+pub fn release_pointer(app: &mut App, ctx_entity: Entity, pos: egui::Pos2) {
+    push_egui_events(app, ctx_entity, [pointer_button(pos, false)]);
+    app.update();
+}
+
+/// Press and release on the widget showing `text`
+pub fn click_text(app: &mut App, ctx_entity: Entity, text: &str) {
+    let pos = press_text(app, ctx_entity, text);
+    release_pointer(app, ctx_entity, pos);
+}
+
+
+/// A grabbable handler with the given methods already captured
+pub fn spawn_grabbable(app: &mut App, transform: Transform, methods: &[Entity]) -> Entity {
+    let mut captures = InputHandlerCaptures::default();
+    captures.captured_methods.extend_from_slice(methods);
+    app.world_mut()
+        .spawn((
+            SpatialBundle::from_transform(transform),
+            Grabble,
+            captures,
+        ))
+        .id()
+}
+
+pub fn spawn_controller(app: &mut App, transform: Transform) -> Entity {
+    app.world_mut()
+        .spawn((
+            SpatialBundle::from_transform(transform),
+            XrControllerInputMethodData {
+                squeezed: false,
+                ..Default::default()
+            },
+        ))
+        .id()
+}
+
+pub fn set_squeezed(app: &mut App, controller: Entity, squeezed: bool) {
+    app.world_mut()
+        .get_mut::<XrControllerInputMethodData>(controller)
+        .unwrap()
+        .squeezed = squeezed;
+}
+
+pub fn spawn_mouse(app: &mut App, transform: Transform) -> Entity {
+    app.world_mut()
+        .spawn((
+            SpatialBundle::from_transform(transform),
+            MouseInputMethodData::default(),
+        ))
+        .id()
+}
+
+pub fn set_mouse_pressed(app: &mut App, mouse: Entity, pressed: bool) {
+    app.world_mut()
+        .get_mut::<MouseInputMethodData>(mouse)
+        .unwrap()
+        .left_button
+        .pressed = pressed;
+}
+
+pub fn move_method(app: &mut App, method: Entity, translation: Vec3) {
+    app.world_mut()
+        .get_mut::<Transform>(method)
+        .unwrap()
+        .translation = translation;
+}
+
+
+/// A grabbable handler with a sphere field that nothing has captured yet
+pub fn spawn_capturing_grabbable(app: &mut App, transform: Transform) -> Entity {
+    app.world_mut()
+        .spawn((
+            SpatialBundle::from_transform(transform),
+            Grabble,
+            InputHandlerCaptures::default(),
+        ))
+        .id()
+}
+
+/// Runs `grabbing::capture_condition` for the method against a sphere field of `radius` on the
+/// handler, like the suis pipeline does, and records the capture if it passes
+pub fn try_capture(app: &mut App, handler: Entity, method: Entity, radius: f32) -> bool {
+    let handler_location = *app.world().get::<GlobalTransform>(handler).unwrap();
+    let input_method_location = *app.world().get::<GlobalTransform>(method).unwrap();
+    let center = handler_location.translation();
+    let closest_point =
+        center + (input_method_location.translation() - center).clamp_length_max(radius);
+    let ctx = CaptureContext {
+        handler,
+        input_method: method,
+        closest_point,
+        input_method_location,
+        handler_location,
+    };
+
+    let captured = app.world_mut().run_system_once_with(ctx, grabbing::capture_condition);
+    if captured {
+        app.world_mut()
+            .get_mut::<InputHandlerCaptures>(handler)
+            .unwrap()
+            .captured_methods
+            .push(method);
+    }
+    captured
+}
+
+/// Fingers of the fake hand, in world space around the hand's position
+fn fake_hand(position: Vec3, pinched: bool) -> Hand {
+    let joint = |offset: Vec3| Joint {
+        pos: position + offset,
+        ori: Quat::IDENTITY,
+        radius: 0.008,
+    };
+    let finger = |x: f32, tip: Vec3| Finger {
+        tip: joint(tip),
+        distal: joint(Vec3::new(x, 0.0, -0.07)),
+        intermediate: joint(Vec3::new(x, 0.0, -0.05)),
+        proximal: joint(Vec3::new(x, 0.0, -0.03)),
+        metacarpal: joint(Vec3::new(x, 0.0, 0.0)),
+    };
+    // Touching tips when pinched, a few centimetres apart when open
+    let thumb_tip = match pinched {
+        true => Vec3::new(0.0, 0.0, -0.09),
+        false => Vec3::new(-0.05, 0.0, -0.06),
+    };
+    Hand {
+        thumb: Thumb {
+            tip: joint(thumb_tip),
+            distal: joint(Vec3::new(-0.04, 0.0, -0.04)),
+            proximal: joint(Vec3::new(-0.03, 0.0, -0.02)),
+            metacarpal: joint(Vec3::new(-0.02, 0.0, 0.0)),
+        },
+        index: finger(0.0, Vec3::new(0.0, 0.0, -0.09)),
+        middle: finger(0.02, Vec3::new(0.02, 0.0, -0.1)),
+        ring: finger(0.04, Vec3::new(0.04, 0.0, -0.09)),
+        little: finger(0.06, Vec3::new(0.06, 0.0, -0.08)),
+        palm: joint(Vec3::new(0.02, 0.0, -0.02)),
+        wrist: joint(Vec3::ZERO),
+        elbow: None,
+    }
+}
+
+/// A tracked hand at `transform`, open
+pub fn spawn_hand(app: &mut App, transform: Transform) -> Entity {
+    app.world_mut()
+        .spawn((
+            SpatialBundle::from_transform(transform),
+            HandInputMethodData::new(fake_hand(transform.translation, false)),
+        ))
+        .id()
+}
+
+/// Touches the thumb and index tips together, or opens them again
+pub fn set_pinched(app: &mut App, hand: Entity, pinched: bool) {
+    let position = app.world().get::<Transform>(hand).unwrap().translation;
+    *app.world_mut().get_mut::<HandInputMethodData>(hand).unwrap() =
+        HandInputMethodData::new(fake_hand(position, pinched));
+}
+
+/// Moves a hand along with its joints, keeping the pinch
+pub fn move_hand(app: &mut App, hand: Entity, translation: Vec3, pinched: bool) {
+    move_method(app, hand, translation);
+    set_pinched(app, hand, pinched);
+}
@@ -0,0 +1,86 @@
+mod harness;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext, EguiRenderToTextureHandle};
+use bevy_gaussian_oxr_simple::keyboard::{FocusedSpatialContext, KeyboardWS};
+use harness::*;
+
+
+/// Text typed into the spatial window under test
+#[derive(Component, Default)]
+struct TypedText(String);
+
+const TEXT_EDIT_ID: &str = "typed text";
+
+fn draw_text_edit(mut ctxs: Query<(&mut EguiContext, &mut TypedText)>) {
+    for (mut ctx, mut text) in ctxs.iter_mut() {
+        egui::CentralPanel::default().show(ctx.get_mut(), |ui| {
+            ui.add(egui::TextEdit::singleline(&mut text.0).id(egui::Id::new(TEXT_EDIT_ID)));
+        });
+    }
+}
+
+/// A keyboard and a spatial window with a focused text field
+fn keyboard_app() -> (App, Entity, Entity) {
+    let mut app = test_app();
+    app.add_systems(Update, draw_text_edit);
+
+    let keyboard = app
+        .world_mut()
+        .spawn((Name::new("Keyboard"), KeyboardWS, egui_bundle()))
+        .id();
+    let target = app
+        .world_mut()
+        .spawn((
+            Name::new("Spatial Window"),
+            EguiRenderToTextureHandle(Handle::default()),
+            TypedText::default(),
+            egui_bundle(),
+        ))
+        .id();
+    for _ in 0..3 {
+        app.update();
+    }
+
+    app.world_mut()
+        .get_mut::<EguiContext>(target)
+        .unwrap()
+        .get_mut()
+        .memory_mut(|m| m.request_focus(egui::Id::new(TEXT_EDIT_ID)));
+    app.update();
+    assert_eq!(app.world().resource::<FocusedSpatialContext>().0, Some(target));
+
+    (app, keyboard, target)
+}
+
+fn typed(app: &App, target: Entity) -> String {
+    app.world().get::<TypedText>(target).unwrap().0.clone()
+}
+
+#[test]
+fn keys_type_into_focused_window() {
+    let (mut app, keyboard, target) = keyboard_app();
+
+    click_text(&mut app, keyboard, "q");
+    click_text(&mut app, keyboard, "1");
+    app.update();
+    assert_eq!(typed(&app, target), "q1");
+
+    click_text(&mut app, keyboard, "Backspace");
+    app.update();
+    assert_eq!(typed(&app, target), "q");
+}
+
+#[test]
+fn shift_is_one_shot() {
+    let (mut app, keyboard, target) = keyboard_app();
+
+    click_text(&mut app, keyboard, "Shift");
+    app.update();
+    // The keys show what they type
+    click_text(&mut app, keyboard, "A");
+    app.update();
+    click_text(&mut app, keyboard, "a");
+    app.update();
+    assert_eq!(typed(&app, target), "Aa");
+}
@@ -0,0 +1,131 @@
+mod harness;
+
+use bevy::prelude::*;
+use bevy_gaussian_oxr_simple::edit_history::HistoryAction;
+use bevy_gaussian_oxr_simple::gaussian::{GaussianMarker, SelectedSplat, SplatSource};
+use bevy_gaussian_oxr_simple::main_menu::MainMenu;
+use bevy_gaussian_oxr_simple::splat_list::SplatListWS;
+use bevy_gaussian_oxr_simple::transform_gizmo::SplatTransformTool;
+use bevy_gaussian_oxr_simple::ui_window::ScreenSpaceWindow;
+use bevy_gaussian_splatting::GaussianCloudSettings;
+use harness::*;
+
+
+#[test]
+fn main_menu_is_a_screen_space_window() {
+    let mut app = test_app();
+    let mut menus = app
+        .world_mut()
+        .query_filtered::<Entity, (With<MainMenu>, With<ScreenSpaceWindow>)>();
+    assert_eq!(menus.iter(app.world()).count(), 1);
+
+    let window = primary_window(&mut app);
+    assert!(find_text(&app, window, "Load Splat").is_some());
+}
+
+#[test]
+fn load_splat_spawns_and_selects_it() {
+    let mut app = test_app();
+    let window = primary_window(&mut app);
+
+    click_text(&mut app, window, "Load Splat");
+
+    let mut splats = app
+        .world_mut()
+        .query_filtered::<(Entity, &SplatSource), With<GaussianMarker>>();
+    let splats: Vec<(Entity, String)> = splats
+        .iter(app.world())
+        .map(|(e, source)| (e, source.0.clone()))
+        .collect();
+    assert_eq!(splats.len(), 1);
+    assert_eq!(splats[0].1, "embedded://cat1.gcloud");
+    assert_eq!(app.world().resource::<SelectedSplat>().0, Some(splats[0].0));
+
+    click_text(&mut app, window, "Load Splat");
+    let mut splats = app.world_mut().query_filtered::<(), With<GaussianMarker>>();
+    assert_eq!(splats.iter(app.world()).count(), 2);
+}
+
+#[test]
+fn splat_list_toggle_spawns_and_despawns_window() {
+    let mut app = test_app();
+    let window = primary_window(&mut app);
+    let mut lists = app.world_mut().query_filtered::<(), With<SplatListWS>>();
+
+    click_text(&mut app, window, "Splat List");
+    assert_eq!(lists.iter(app.world()).count(), 1);
+
+    click_text(&mut app, window, "Splat List");
+    assert_eq!(lists.iter(app.world()).count(), 0);
+}
+
+#[test]
+fn one_to_one_resets_world_scale_only() {
+    let mut app = test_app();
+    let window = primary_window(&mut app);
+    click_text(&mut app, window, "Load Splat");
+    let splat = app.world().resource::<SelectedSplat>().0.unwrap();
+
+    // Scaled by something other than the menu, the controls pick it up
+    {
+        let mut settings = app.world_mut().get_mut::<GaussianCloudSettings>(splat).unwrap();
+        settings.transform.scale = Vec3::splat(3.0);
+        settings.global_scale = 0.5;
+    }
+    app.update();
+    click_text(&mut app, window, "1:1");
+
+    let settings = app.world().get::<GaussianCloudSettings>(splat).unwrap();
+    assert_eq!(settings.transform.scale, Vec3::ONE);
+    assert_eq!(settings.global_scale, 0.5);
+}
+
+#[test]
+fn showing_the_controls_leaves_loaded_values_alone() {
+    let mut app = test_app();
+    let window = primary_window(&mut app);
+    click_text(&mut app, window, "Load Splat");
+    let splat = app.world().resource::<SelectedSplat>().0.unwrap();
+
+    // Outside the slider ranges and not uniform, like a scene file can have
+    let scale = Vec3::new(200.0, 400.0, 200.0);
+    {
+        let mut settings = app.world_mut().get_mut::<GaussianCloudSettings>(splat).unwrap();
+        settings.transform.scale = scale;
+        settings.global_scale = 8.0;
+    }
+    app.update();
+    app.update();
+    let settings = app.world().get::<GaussianCloudSettings>(splat).unwrap();
+    assert_eq!(settings.transform.scale, scale);
+    assert_eq!(settings.global_scale, 8.0);
+
+    // 1:1 keeps the proportions
+    click_text(&mut app, window, "1:1");
+    let settings = app.world().get::<GaussianCloudSettings>(splat).unwrap();
+    assert_eq!(settings.transform.scale, Vec3::new(1.0, 2.0, 1.0));
+}
+
+#[test]
+fn undo_removes_and_redo_brings_back_a_tool() {
+    let mut app = test_app();
+    let window = primary_window(&mut app);
+    let mut tools = app.world_mut().query_filtered::<Entity, With<SplatTransformTool>>();
+
+    click_text(&mut app, window, "Toggle Splat Transform Tool");
+    app.update();
+    assert_eq!(tools.iter(app.world()).count(), 1);
+
+    app.world_mut().send_event(HistoryAction::Undo);
+    app.update();
+    assert_eq!(tools.iter(app.world()).count(), 0);
+
+    app.world_mut().send_event(HistoryAction::Redo);
+    app.update();
+    app.update();
+    assert_eq!(tools.iter(app.world()).count(), 1);
+
+    // The menu picked the new tool up, so the toggle turns it off
+    click_text(&mut app, window, "Toggle Splat Transform Tool");
+    assert_eq!(tools.iter(app.world()).count(), 0);
+}